@@ -0,0 +1,230 @@
+#![no_main]
+
+// Replays random sequences of swaps and liquidity operations against the pool math and checks that
+// k never decreases on a swap and that the reserves backing each LP token never decrease on a
+// deposit or withdrawal. Amounts are u32 so that the products checked here fit in a u128.
+use amm::constant::BASIS_POINTS;
+use amm::constant_product::{
+    calculate_liquidity, calculate_liquidity_amounts, calculate_removed_amounts, get_amount_in,
+    get_amount_out, protocol_fee_liquidity,
+};
+use amm::math::sqrt;
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+enum Operation {
+    SwapExactInput { zero_for_one: bool, amount_in: u32 },
+    SwapExactOutput { zero_for_one: bool, amount_out: u32 },
+    AddLiquidity { amount0: u32, amount1: u32 },
+    RemoveLiquidity { liquidity: u32 },
+}
+
+#[derive(Arbitrary, Debug)]
+struct Input {
+    fee: u16,
+    protocol_fee_share: u16,
+    amount0: u32,
+    amount1: u32,
+    operations: Vec<Operation>,
+}
+
+struct Pool {
+    fee: u64,
+    protocol_fee_share: u64,
+    k_last: u128,
+    reserve0: u64,
+    reserve1: u64,
+    lp_supply: u64,
+}
+
+impl Pool {
+    fn k(&self) -> u128 {
+        self.reserve0 as u128 * self.reserve1 as u128
+    }
+
+    fn swap(&mut self, zero_for_one: bool, amount_in: u64, amount_out: u64) {
+        let k = self.k();
+        if zero_for_one {
+            self.reserve0 += amount_in;
+            self.reserve1 -= amount_out;
+        } else {
+            self.reserve1 += amount_in;
+            self.reserve0 -= amount_out;
+        }
+        assert!(self.k() >= k, "swap decreased k");
+    }
+
+    fn reserves(&self, zero_for_one: bool) -> (u64, u64) {
+        if zero_for_one {
+            (self.reserve0, self.reserve1)
+        } else {
+            (self.reserve1, self.reserve0)
+        }
+    }
+
+    // LP supply once the protocol fee is minted ahead of a liquidity operation, as `mint_fee` does
+    fn lp_supply_after_fee(&self) -> u64 {
+        if self.protocol_fee_share == 0 {
+            return self.lp_supply;
+        }
+        let liquidity = protocol_fee_liquidity(
+            self.protocol_fee_share,
+            self.k_last,
+            self.reserve0,
+            self.reserve1,
+            self.lp_supply,
+        )
+        .unwrap();
+        let root_k = sqrt(self.k());
+        let root_k_last = sqrt(self.k_last);
+        if root_k <= root_k_last {
+            assert_eq!(liquidity, 0, "protocol fee minted without growth");
+        } else {
+            assert!(
+                liquidity as u128 * root_k * BASIS_POINTS as u128
+                    <= self.protocol_fee_share as u128
+                        * (root_k - root_k_last)
+                        * (self.lp_supply + liquidity) as u128,
+                "protocol fee above its share of the growth"
+            );
+        }
+        self.lp_supply + liquidity
+    }
+
+    // runs a liquidity operation and checks the reserves backing each LP token did not decrease
+    fn update_liquidity(
+        &mut self,
+        supply_before: u64,
+        reserve0: u64,
+        reserve1: u64,
+        lp_supply: u64,
+    ) {
+        let (supply_before, supply_after) = (supply_before as u128, lp_supply as u128);
+        assert!(
+            reserve0 as u128 * supply_before >= self.reserve0 as u128 * supply_after,
+            "token0 per LP decreased"
+        );
+        assert!(
+            reserve1 as u128 * supply_before >= self.reserve1 as u128 * supply_after,
+            "token1 per LP decreased"
+        );
+        self.reserve0 = reserve0;
+        self.reserve1 = reserve1;
+        self.lp_supply = lp_supply;
+        if self.protocol_fee_share > 0 {
+            self.k_last = self.k();
+        }
+    }
+}
+
+fuzz_target!(|input: Input| {
+    let amount0 = input.amount0 as u64;
+    let amount1 = input.amount1 as u64;
+    let Ok(liquidity) = calculate_liquidity(amount0, amount1, 0, 0, 0) else {
+        return;
+    };
+    let mut pool = Pool {
+        fee: input.fee as u64 % BASIS_POINTS,
+        protocol_fee_share: input.protocol_fee_share as u64 % BASIS_POINTS,
+        k_last: 0,
+        reserve0: amount0,
+        reserve1: amount1,
+        // the locked minimum liquidity counts towards the supply
+        lp_supply: sqrt(amount0 as u128 * amount1 as u128) as u64,
+    };
+    if pool.protocol_fee_share > 0 {
+        pool.k_last = pool.k();
+    }
+    let mut user_liquidity = liquidity;
+
+    for operation in input.operations {
+        match operation {
+            Operation::SwapExactInput {
+                zero_for_one,
+                amount_in,
+            } => {
+                let (reserve_in, reserve_out) = pool.reserves(zero_for_one);
+                let amount_in = amount_in as u64;
+                let amount_out = get_amount_out(pool.fee, amount_in, reserve_in, reserve_out);
+                let Ok(amount_out) = amount_out else {
+                    continue;
+                };
+                assert!(amount_out < reserve_out, "swap drained the pool");
+                pool.swap(zero_for_one, amount_in, amount_out);
+            }
+            Operation::SwapExactOutput {
+                zero_for_one,
+                amount_out,
+            } => {
+                let (reserve_in, reserve_out) = pool.reserves(zero_for_one);
+                let amount_out = amount_out as u64;
+                if amount_out == 0 || amount_out >= reserve_out {
+                    continue;
+                }
+                let Ok(amount_in) = get_amount_in(pool.fee, amount_out, reserve_in, reserve_out)
+                else {
+                    continue;
+                };
+                // a near-complete drain asks for more than any user holds
+                if amount_in > u32::MAX as u64 {
+                    continue;
+                }
+                pool.swap(zero_for_one, amount_in, amount_out);
+            }
+            Operation::AddLiquidity { amount0, amount1 } => {
+                let Ok((amount0, amount1)) = calculate_liquidity_amounts(
+                    pool.reserve0,
+                    pool.reserve1,
+                    amount0 as u64,
+                    amount1 as u64,
+                    0,
+                    0,
+                ) else {
+                    continue;
+                };
+                let lp_supply = pool.lp_supply_after_fee();
+                let Ok(liquidity) =
+                    calculate_liquidity(amount0, amount1, pool.reserve0, pool.reserve1, lp_supply)
+                else {
+                    continue;
+                };
+                pool.update_liquidity(
+                    lp_supply,
+                    pool.reserve0 + amount0,
+                    pool.reserve1 + amount1,
+                    lp_supply + liquidity,
+                );
+                user_liquidity += liquidity;
+            }
+            Operation::RemoveLiquidity { liquidity } => {
+                let liquidity = liquidity as u64;
+                if liquidity == 0 || liquidity > user_liquidity {
+                    continue;
+                }
+                let lp_supply = pool.lp_supply_after_fee();
+                let Ok((amount0, amount1)) = calculate_removed_amounts(
+                    liquidity,
+                    lp_supply,
+                    pool.reserve0,
+                    pool.reserve1,
+                    0,
+                    0,
+                ) else {
+                    continue;
+                };
+                assert!(
+                    amount0 < pool.reserve0 && amount1 < pool.reserve1,
+                    "withdrawal drained the pool"
+                );
+                pool.update_liquidity(
+                    lp_supply,
+                    pool.reserve0 - amount0,
+                    pool.reserve1 - amount1,
+                    lp_supply - liquidity,
+                );
+                user_liquidity -= liquidity;
+            }
+        }
+    }
+});
@@ -0,0 +1,522 @@
+// Harness running the compiled amm program in an in-process bank. Build the program with
+// `anchor build` or `cargo build-sbf --manifest-path programs/amm/Cargo.toml` first, it is loaded
+// from `target/deploy/amm.so`, then run `cargo test` here. The bank ships the token, Token-2022 and
+// associated token account programs the pool talks to.
+use amm::state::{ObservationSlot, Observations, Pool};
+use amm_sdk::instruction::{self, PoolKeys};
+use amm_sdk::pda::{self, TOKEN_PROGRAM_ID};
+use amm_sdk::state::{CurveParams, PoolState};
+use anchor_lang::{AccountDeserialize, AnchorDeserialize, Discriminator, Event};
+use anchor_spl::associated_token::spl_associated_token_account::instruction::create_associated_token_account;
+use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::instruction::initialize_transfer_fee_config;
+use anchor_spl::token_2022::spl_token_2022::extension::{ExtensionType, StateWithExtensions};
+use anchor_spl::token_2022::spl_token_2022::instruction::{
+    initialize_account3, initialize_mint2, mint_to, transfer_checked,
+};
+use anchor_spl::token_2022::spl_token_2022::state::{Account as TokenAccount, Mint};
+use anchor_spl::token_2022::spl_token_2022::ID as TOKEN_2022_PROGRAM_ID;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use solana_program_test::{BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::clock::Clock;
+use solana_sdk::instruction::{Instruction, InstructionError};
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::{Transaction, TransactionError};
+use std::mem;
+
+pub const DECIMALS: u8 = 9;
+// fee tier enabled by `TestContext::initialized`
+pub const FEE: u64 = 30;
+
+// `n` whole tokens
+pub fn amount(n: u64) -> u64 {
+    n * 10u64.pow(DECIMALS as u32)
+}
+
+pub struct TestContext {
+    pub context: ProgramTestContext,
+}
+
+impl TestContext {
+    pub async fn new() -> Self {
+        std::env::set_var(
+            "SBF_OUT_DIR",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/../../../target/deploy"),
+        );
+        let program_test = ProgramTest::new("amm", amm::ID, None);
+        Self {
+            context: program_test.start_with_context().await,
+        }
+    }
+
+    // a bank with the config initialized on the `FEE` tier, the payer is the owner and `fee_to`
+    pub async fn initialized() -> Self {
+        let mut test = Self::new().await;
+        let payer = test.payer();
+        test.process(&[instruction::initialize(payer, payer, FEE)], &[])
+            .await
+            .unwrap();
+        test
+    }
+
+    pub fn payer(&self) -> Pubkey {
+        self.context.payer.pubkey()
+    }
+
+    // the payer signs every transaction, `signers` are the others
+    async fn transaction(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Transaction {
+        // a fresh blockhash so that sending the same instructions twice is not deduplicated
+        let blockhash = self.context.get_new_latest_blockhash().await.unwrap();
+        let mut keypairs = vec![&self.context.payer];
+        keypairs.extend_from_slice(signers);
+        Transaction::new_signed_with_payer(
+            instructions,
+            Some(&self.context.payer.pubkey()),
+            &keypairs,
+            blockhash,
+        )
+    }
+
+    pub async fn process(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<(), BanksClientError> {
+        let transaction = self.transaction(instructions, signers).await;
+        self.context
+            .banks_client
+            .process_transaction(transaction)
+            .await
+    }
+
+    // the log messages of a successful transaction
+    pub async fn process_with_logs(
+        &mut self,
+        instructions: &[Instruction],
+        signers: &[&Keypair],
+    ) -> Result<Vec<String>, BanksClientError> {
+        let transaction = self.transaction(instructions, signers).await;
+        let result = self
+            .context
+            .banks_client
+            .process_transaction_with_metadata(transaction)
+            .await?;
+        result.result?;
+        Ok(result
+            .metadata
+            .map(|metadata| metadata.log_messages)
+            .unwrap_or_default())
+    }
+
+    // simulates a read-only instruction and decodes what it returned
+    pub async fn view<T: AnchorDeserialize>(
+        &mut self,
+        instruction: Instruction,
+    ) -> Result<T, BanksClientError> {
+        let transaction = self.transaction(&[instruction], &[]).await;
+        let simulation = self
+            .context
+            .banks_client
+            .simulate_transaction(transaction)
+            .await?;
+        if let Some(Err(err)) = simulation.result {
+            return Err(BanksClientError::TransactionError(err));
+        }
+        let data = simulation
+            .simulation_details
+            .and_then(|details| details.return_data)
+            .map(|return_data| return_data.data)
+            .unwrap_or_default();
+        Ok(T::try_from_slice(&data).unwrap())
+    }
+
+    // a keypair holding enough lamports to pay for the accounts it opens
+    pub async fn create_user(&mut self) -> Keypair {
+        let user = Keypair::new();
+        let payer = self.payer();
+        self.process(
+            &[system_instruction::transfer(
+                &payer,
+                &user.pubkey(),
+                10 * LAMPORTS_PER_SOL,
+            )],
+            &[],
+        )
+        .await
+        .unwrap();
+        user
+    }
+
+    async fn create_account(&mut self, space: usize, owner: &Pubkey) -> (Keypair, Instruction) {
+        let account = Keypair::new();
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+        let instruction = system_instruction::create_account(
+            &self.payer(),
+            &account.pubkey(),
+            rent.minimum_balance(space),
+            space as u64,
+            owner,
+        );
+        (account, instruction)
+    }
+
+    // a mint of `token_program`, the payer is its mint and freeze authority
+    pub async fn create_mint(&mut self, token_program: &Pubkey) -> Pubkey {
+        self.create_mint_with_decimals(token_program, DECIMALS)
+            .await
+    }
+
+    pub async fn create_mint_with_decimals(
+        &mut self,
+        token_program: &Pubkey,
+        decimals: u8,
+    ) -> Pubkey {
+        let (mint, create) = self.create_account(Mint::LEN, token_program).await;
+        let payer = self.payer();
+        let initialize = initialize_mint2(
+            token_program,
+            &mint.pubkey(),
+            &payer,
+            Some(&payer),
+            decimals,
+        )
+        .unwrap();
+        self.process(&[create, initialize], &[&mint]).await.unwrap();
+        mint.pubkey()
+    }
+
+    // a Token-2022 mint charging `transfer_fee_basis_points` on every transfer
+    pub async fn create_transfer_fee_mint(
+        &mut self,
+        transfer_fee_basis_points: u16,
+        maximum_fee: u64,
+    ) -> Pubkey {
+        let space =
+            ExtensionType::try_calculate_account_len::<Mint>(&[ExtensionType::TransferFeeConfig])
+                .unwrap();
+        let (mint, create) = self.create_account(space, &TOKEN_2022_PROGRAM_ID).await;
+        let payer = self.payer();
+        let instructions = [
+            create,
+            initialize_transfer_fee_config(
+                &TOKEN_2022_PROGRAM_ID,
+                &mint.pubkey(),
+                Some(&payer),
+                Some(&payer),
+                transfer_fee_basis_points,
+                maximum_fee,
+            )
+            .unwrap(),
+            initialize_mint2(
+                &TOKEN_2022_PROGRAM_ID,
+                &mint.pubkey(),
+                &payer,
+                None,
+                DECIMALS,
+            )
+            .unwrap(),
+        ];
+        self.process(&instructions, &[&mint]).await.unwrap();
+        mint.pubkey()
+    }
+
+    pub async fn create_ata(
+        &mut self,
+        owner: &Pubkey,
+        mint: &Pubkey,
+        token_program: &Pubkey,
+    ) -> Pubkey {
+        let create = create_associated_token_account(&self.payer(), owner, mint, token_program);
+        self.process(&[create], &[]).await.unwrap();
+        pda::associated_token_address(owner, mint, token_program)
+    }
+
+    // a token account that is not the associated one of `owner`
+    pub async fn create_token_account(
+        &mut self,
+        owner: &Pubkey,
+        mint: &Pubkey,
+        token_program: &Pubkey,
+    ) -> Pubkey {
+        let (account, create) = self.create_account(TokenAccount::LEN, token_program).await;
+        let initialize =
+            initialize_account3(token_program, &account.pubkey(), mint, owner).unwrap();
+        self.process(&[create, initialize], &[&account])
+            .await
+            .unwrap();
+        account.pubkey()
+    }
+
+    pub async fn mint_to(
+        &mut self,
+        mint: &Pubkey,
+        token_program: &Pubkey,
+        account: &Pubkey,
+        amount: u64,
+    ) {
+        let payer = self.payer();
+        let instruction = mint_to(token_program, mint, account, &payer, &[], amount).unwrap();
+        self.process(&[instruction], &[]).await.unwrap();
+    }
+
+    // opens `owner`'s associated token account for `mint` and mints `amount` to it
+    pub async fn fund(
+        &mut self,
+        owner: &Pubkey,
+        mint: &Pubkey,
+        token_program: &Pubkey,
+        amount: u64,
+    ) -> Pubkey {
+        let account = self.create_ata(owner, mint, token_program).await;
+        self.mint_to(mint, token_program, &account, amount).await;
+        account
+    }
+
+    pub async fn transfer(
+        &mut self,
+        source: &Pubkey,
+        destination: &Pubkey,
+        mint: &Pubkey,
+        token_program: &Pubkey,
+        authority: &Keypair,
+        amount: u64,
+    ) {
+        let instruction = transfer_checked(
+            token_program,
+            source,
+            mint,
+            destination,
+            &authority.pubkey(),
+            &[],
+            amount,
+            DECIMALS,
+        )
+        .unwrap();
+        self.process(&[instruction], &[authority]).await.unwrap();
+    }
+
+    async fn get_account(&mut self, address: &Pubkey) -> Account {
+        self.context
+            .banks_client
+            .get_account(*address)
+            .await
+            .unwrap()
+            .unwrap()
+    }
+
+    pub async fn exists(&mut self, address: &Pubkey) -> bool {
+        self.context
+            .banks_client
+            .get_account(*address)
+            .await
+            .unwrap()
+            .is_some()
+    }
+
+    // a program account, checked against its discriminator
+    pub async fn account<T: AccountDeserialize>(&mut self, address: &Pubkey) -> T {
+        let account = self.get_account(address).await;
+        T::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    pub async fn pool(&mut self, pool: &PoolKeys) -> Pool {
+        self.account(&pool.pool).await
+    }
+
+    // the SDK's view of a pool at the bank's current time
+    pub async fn pool_state(&mut self, pool: &PoolKeys) -> PoolState {
+        let account = self.get_account(&pool.pool).await;
+        let lp_supply = self.supply(&pool.lp_mint()).await;
+        let timestamp = self.clock().await.unix_timestamp;
+        PoolState::from_account_data(&account.data, lp_supply, timestamp).unwrap()
+    }
+
+    // the header of a pool's observations account and the number of slots following it
+    pub async fn observations(&mut self, pool: &PoolKeys) -> (Observations, usize) {
+        let account = self.get_account(&pool.observations()).await;
+        let header = 8 + mem::size_of::<Observations>();
+        let observations = Observations::try_deserialize(&mut &account.data[..header]).unwrap();
+        let slots = (account.data.len() - header) / mem::size_of::<ObservationSlot>();
+        (observations, slots)
+    }
+
+    // token amount of an account of either token program
+    pub async fn balance(&mut self, token_account: &Pubkey) -> u64 {
+        let account = self.get_account(token_account).await;
+        StateWithExtensions::<TokenAccount>::unpack(&account.data)
+            .unwrap()
+            .base
+            .amount
+    }
+
+    pub async fn supply(&mut self, mint: &Pubkey) -> u64 {
+        let account = self.get_account(mint).await;
+        StateWithExtensions::<Mint>::unpack(&account.data)
+            .unwrap()
+            .base
+            .supply
+    }
+
+    pub async fn lamports(&mut self, address: &Pubkey) -> u64 {
+        self.context
+            .banks_client
+            .get_balance(*address)
+            .await
+            .unwrap()
+    }
+
+    pub async fn clock(&mut self) -> Clock {
+        self.context
+            .banks_client
+            .get_sysvar::<Clock>()
+            .await
+            .unwrap()
+    }
+
+    // moves the bank's clock `seconds` forward
+    pub async fn advance_clock(&mut self, seconds: i64) {
+        let mut clock = self.clock().await;
+        clock.unix_timestamp += seconds;
+        self.context.set_sysvar(&clock);
+    }
+
+    // opens the pool authority's vaults, which have to exist before `create_pool`
+    pub async fn create_vaults(&mut self, pool: &PoolKeys) {
+        let authority = pool.authority();
+        self.create_ata(&authority, &pool.token0, &pool.token_program0)
+            .await;
+        self.create_ata(&authority, &pool.token1, &pool.token_program1)
+            .await;
+    }
+
+    // creates the pool of two mints on the `fee` tier and `curve` with the payer as creator
+    pub async fn create_pool(&mut self, pool: &PoolKeys, fee: u64, curve: CurveParams) {
+        self.create_vaults(pool).await;
+        let payer = self.payer();
+        let create_pool = instruction::create_pool(payer, pool, fee, curve, None);
+        self.process(&[create_pool], &[]).await.unwrap();
+    }
+
+    // a pool of two fresh mints of the original token program on the `FEE` tier
+    pub async fn create_default_pool(&mut self) -> PoolKeys {
+        let mint_a = self.create_mint(&TOKEN_PROGRAM_ID).await;
+        let mint_b = self.create_mint(&TOKEN_PROGRAM_ID).await;
+        let pool = pool_keys(
+            (mint_a, TOKEN_PROGRAM_ID),
+            (mint_b, TOKEN_PROGRAM_ID),
+            FEE,
+            CurveParams::ConstantProduct,
+        );
+        self.create_pool(&pool, FEE, CurveParams::ConstantProduct)
+            .await;
+        pool
+    }
+
+    // a funded user holding `tokens` whole units of both of the pool's tokens
+    pub async fn create_liquidity_provider(&mut self, pool: &PoolKeys, tokens: u64) -> Keypair {
+        let user = self.create_user().await;
+        self.fund(
+            &user.pubkey(),
+            &pool.token0,
+            &pool.token_program0,
+            amount(tokens),
+        )
+        .await;
+        self.fund(
+            &user.pubkey(),
+            &pool.token1,
+            &pool.token_program1,
+            amount(tokens),
+        )
+        .await;
+        user
+    }
+}
+
+// the keys of the pool of two (mint, token program) pairs, ordered the way pools are created here
+pub fn pool_keys(
+    a: (Pubkey, Pubkey),
+    b: (Pubkey, Pubkey),
+    fee: u64,
+    curve: CurveParams,
+) -> PoolKeys {
+    let ((token0, token_program0), (token1, token_program1)) =
+        if a.0 < b.0 { (a, b) } else { (b, a) };
+    PoolKeys::new(token0, token1, fee, &curve, token_program0, token_program1)
+}
+
+// the owner's associated token account for one of the pool's tokens
+pub fn user_ata(owner: &Pubkey, pool: &PoolKeys, mint: &Pubkey) -> Pubkey {
+    let token_program = if *mint == pool.token0 {
+        &pool.token_program0
+    } else {
+        &pool.token_program1
+    };
+    pda::associated_token_address(owner, mint, token_program)
+}
+
+pub fn user_lp_ata(owner: &Pubkey, pool: &PoolKeys) -> Pubkey {
+    pda::associated_token_address(owner, &pool.lp_mint(), &TOKEN_PROGRAM_ID)
+}
+
+// points every occurrence of `old` in the instruction's accounts at `new`
+pub fn replace_account(instruction: &mut Instruction, old: &Pubkey, new: &Pubkey) {
+    for account in &mut instruction.accounts {
+        if account.pubkey == *old {
+            account.pubkey = *new;
+        }
+    }
+}
+
+// exchanges `a` and `b` wherever they appear in the instruction's accounts
+pub fn swap_accounts(instruction: &mut Instruction, a: &Pubkey, b: &Pubkey) {
+    for account in &mut instruction.accounts {
+        if account.pubkey == *a {
+            account.pubkey = *b;
+        } else if account.pubkey == *b {
+            account.pubkey = *a;
+        }
+    }
+}
+
+// events of type `T` emitted in the logs of a transaction
+pub fn events<T: Event>(logs: &[String]) -> Vec<T> {
+    logs.iter()
+        .filter_map(|log| log.strip_prefix("Program data: "))
+        .filter_map(|data| BASE64.decode(data).ok())
+        .filter(|data| data.starts_with(&T::DISCRIMINATOR))
+        .filter_map(|data| T::try_from_slice(&data[8..]).ok())
+        .collect()
+}
+
+// the custom error a failed transaction ended with
+pub fn custom_error<T>(result: &Result<T, BanksClientError>) -> Option<u32> {
+    let err = match result {
+        Err(BanksClientError::TransactionError(err))
+        | Err(BanksClientError::SimulationError { err, .. }) => err,
+        _ => return None,
+    };
+    match err {
+        TransactionError::InstructionError(_, InstructionError::Custom(code)) => Some(*code),
+        _ => None,
+    }
+}
+
+// fails unless the transaction failed with error `code`, anchor's own or the program's
+#[track_caller]
+pub fn assert_error<T: std::fmt::Debug>(result: Result<T, BanksClientError>, code: u32) {
+    assert_eq!(
+        custom_error(&result),
+        Some(code),
+        "expected error {code}, got {result:?}"
+    );
+}
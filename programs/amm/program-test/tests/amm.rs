@@ -0,0 +1,1561 @@
+// The scenarios of tests/amm.ts, each on a bank of its own.
+use amm::constant::{
+    BASIS_POINTS, DEFAULT_PROTOCOL_FEE_SHARE, MAX_OBSERVATION_CARDINALITY, MAX_PROTOCOL_FEE_SHARE,
+    MINIMUM_LIQUIDITY, MIN_RAMP_DURATION,
+};
+use amm::error::ErrorCode;
+use amm::state::{Config, FeeTiers, Observation};
+use amm_program_test::{
+    amount, assert_error, events, pool_keys, replace_account, swap_accounts, user_ata, user_lp_ata,
+    TestContext, DECIMALS, FEE,
+};
+use amm_sdk::instruction::{self, CreationFee, PoolKeys};
+use amm_sdk::pda::{self, TOKEN_2022_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use amm_sdk::state::{CurveParams, LiquidityQuote, PriceObservation, Twap};
+use amm_sdk::{math, stable_swap, weighted_product};
+use anchor_lang::error::ErrorCode as AnchorErrorCode;
+use anchor_spl::token::spl_token;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+
+fn code(error: ErrorCode) -> u32 {
+    error.into()
+}
+
+// a pool on the `FEE` tier holding 50 of each token, deposited by the returned provider who keeps
+// another 50 of each
+async fn pool_with_liquidity() -> (TestContext, PoolKeys, Keypair) {
+    let mut test = TestContext::initialized().await;
+    let pool = test.create_default_pool().await;
+    let provider = test.create_liquidity_provider(&pool, 100).await;
+    let deposit = instruction::add_liquidity(
+        provider.pubkey(),
+        &pool,
+        amount(50),
+        amount(50),
+        amount(50),
+        amount(50),
+        None,
+    );
+    test.process(&[deposit], &[&provider]).await.unwrap();
+    (test, pool, provider)
+}
+
+fn swap_token0(provider: &Keypair, pool: &PoolKeys, amount_in: u64, min_out: u64) -> Instruction {
+    instruction::swap_exact_input(
+        provider.pubkey(),
+        pool,
+        pool.token0,
+        amount_in,
+        min_out,
+        None,
+    )
+    .unwrap()
+}
+
+#[tokio::test]
+async fn initialize() {
+    let mut test = TestContext::initialized().await;
+    let payer = test.payer();
+
+    let config: Config = test.account(&pda::config()).await;
+    assert_eq!(config.owner, payer);
+    assert_eq!(config.fee_to, payer);
+    let fee_tiers: FeeTiers = test.account(&pda::fee_tiers()).await;
+    assert_eq!(fee_tiers.fees, vec![FEE]);
+}
+
+#[tokio::test]
+async fn set_fee_to() {
+    let mut test = TestContext::initialized().await;
+    let fee_to = Pubkey::new_unique();
+
+    test.process(&[instruction::set_fee_to(test.payer(), fee_to)], &[])
+        .await
+        .unwrap();
+
+    let config: Config = test.account(&pda::config()).await;
+    assert_eq!(config.fee_to, fee_to);
+}
+
+#[tokio::test]
+async fn fee_tiers() {
+    let mut test = TestContext::initialized().await;
+    let owner = test.payer();
+
+    let result = test
+        .process(&[instruction::enable_fee_tier(owner, BASIS_POINTS)], &[])
+        .await;
+    assert_error(result, code(ErrorCode::InvalidFee));
+    let result = test
+        .process(&[instruction::enable_fee_tier(owner, FEE)], &[])
+        .await;
+    assert_error(result, code(ErrorCode::FeeTierAlreadyEnabled));
+
+    test.process(
+        &[
+            instruction::enable_fee_tier(owner, 5),
+            instruction::enable_fee_tier(owner, 100),
+            instruction::disable_fee_tier(owner, 100),
+            instruction::enable_fee_tier(owner, 50),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let fee_tiers: FeeTiers = test.account(&pda::fee_tiers()).await;
+    assert_eq!(fee_tiers.fees, vec![FEE, 5, 50]);
+}
+
+#[tokio::test]
+async fn set_fee() {
+    let (mut test, pool, provider) = pool_with_liquidity().await;
+    let owner = test.payer();
+
+    let result = test
+        .process(&[instruction::set_fee(owner, pool.pool, BASIS_POINTS)], &[])
+        .await;
+    assert_error(result, code(ErrorCode::InvalidFee));
+    let result = test
+        .process(
+            &[instruction::set_fee(provider.pubkey(), pool.pool, 5)],
+            &[&provider],
+        )
+        .await;
+    assert_error(result, AnchorErrorCode::ConstraintHasOne as u32);
+
+    // the pool stays at its address and swaps at the new fee
+    let new_fee = 5;
+    test.process(&[instruction::set_fee(owner, pool.pool, new_fee)], &[])
+        .await
+        .unwrap();
+    let state = test.pool(&pool).await;
+    assert_eq!(state.fee, new_fee);
+
+    let ata1 = user_ata(&provider.pubkey(), &pool, &pool.token1);
+    let amount_in = amount(10);
+    let amount_out =
+        math::get_amount_out(new_fee, amount_in, state.reserve0, state.reserve1).unwrap();
+    let balance1 = test.balance(&ata1).await;
+    test.process(
+        &[swap_token0(&provider, &pool, amount_in, amount_out)],
+        &[&provider],
+    )
+    .await
+    .unwrap();
+    assert_eq!(test.balance(&ata1).await - balance1, amount_out);
+}
+
+#[tokio::test]
+async fn ownership_transfer() {
+    let mut test = TestContext::initialized().await;
+    let owner = test.payer();
+    let new_owner = test.create_user().await;
+
+    test.process(
+        &[instruction::propose_owner(owner, new_owner.pubkey())],
+        &[],
+    )
+    .await
+    .unwrap();
+    let config: Config = test.account(&pda::config()).await;
+    assert_eq!(config.owner, owner);
+    assert_eq!(config.pending_owner, new_owner.pubkey());
+
+    // only the pending owner can accept
+    let stranger = test.create_user().await;
+    let result = test
+        .process(
+            &[instruction::accept_owner(stranger.pubkey())],
+            &[&stranger],
+        )
+        .await;
+    assert_error(result, AnchorErrorCode::ConstraintHasOne as u32);
+
+    test.process(
+        &[instruction::accept_owner(new_owner.pubkey())],
+        &[&new_owner],
+    )
+    .await
+    .unwrap();
+    let config: Config = test.account(&pda::config()).await;
+    assert_eq!(config.owner, new_owner.pubkey());
+    assert_eq!(config.pending_owner, Pubkey::default());
+
+    // and back
+    test.process(
+        &[instruction::propose_owner(new_owner.pubkey(), owner)],
+        &[&new_owner],
+    )
+    .await
+    .unwrap();
+    test.process(&[instruction::accept_owner(owner)], &[])
+        .await
+        .unwrap();
+    let config: Config = test.account(&pda::config()).await;
+    assert_eq!(config.owner, owner);
+}
+
+#[tokio::test]
+async fn create_pool() {
+    let mut test = TestContext::initialized().await;
+    let payer = test.payer();
+    let mint_a = test.create_mint(&TOKEN_PROGRAM_ID).await;
+    let mint_b = test.create_mint(&TOKEN_PROGRAM_ID).await;
+    let pool = pool_keys(
+        (mint_a, TOKEN_PROGRAM_ID),
+        (mint_b, TOKEN_PROGRAM_ID),
+        FEE,
+        CurveParams::ConstantProduct,
+    );
+
+    // both vaults have to exist
+    let authority = pool.authority();
+    test.create_ata(&authority, &pool.token0, &TOKEN_PROGRAM_ID)
+        .await;
+    let result = test
+        .process(
+            &[instruction::create_pool(
+                payer,
+                &pool,
+                FEE,
+                CurveParams::ConstantProduct,
+                None,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, AnchorErrorCode::AccountNotInitialized as u32);
+    test.create_ata(&authority, &pool.token1, &TOKEN_PROGRAM_ID)
+        .await;
+
+    let disabled = pool_keys(
+        (mint_a, TOKEN_PROGRAM_ID),
+        (mint_b, TOKEN_PROGRAM_ID),
+        100,
+        CurveParams::ConstantProduct,
+    );
+    test.create_vaults(&disabled).await;
+    let result = test
+        .process(
+            &[instruction::create_pool(
+                payer,
+                &disabled,
+                100,
+                CurveParams::ConstantProduct,
+                None,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, code(ErrorCode::FeeTierNotEnabled));
+
+    test.process(
+        &[instruction::create_pool(
+            payer,
+            &pool,
+            FEE,
+            CurveParams::ConstantProduct,
+            None,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let pool_data = test.pool(&pool).await;
+    assert_eq!(pool_data.token0, pool.token0);
+    assert_eq!(pool_data.token1, pool.token1);
+    assert_eq!(pool_data.fee, FEE);
+    assert_eq!(pool_data.k_last, 0);
+}
+
+#[tokio::test]
+async fn add_liquidity() {
+    let (mut test, pool, provider) = pool_with_liquidity().await;
+    let owner = provider.pubkey();
+    let (ata0, ata1) = (
+        user_ata(&owner, &pool, &pool.token0),
+        user_ata(&owner, &pool, &pool.token1),
+    );
+
+    let pool_data = test.pool(&pool).await;
+    assert_eq!(pool_data.k_last, amount(50) as u128 * amount(50) as u128);
+    assert_eq!(pool_data.reserve0, amount(50));
+    assert_eq!(pool_data.reserve1, amount(50));
+    assert_eq!(test.balance(&ata0).await, amount(50));
+    assert_eq!(test.balance(&ata1).await, amount(50));
+    let liquidity = amount(50) - MINIMUM_LIQUIDITY;
+    assert_eq!(test.balance(&user_lp_ata(&owner, &pool)).await, liquidity);
+    assert_eq!(test.balance(&pool.vault_lp()).await, MINIMUM_LIQUIDITY);
+    assert_eq!(test.balance(&pool.vault0()).await, amount(50));
+    assert_eq!(test.balance(&pool.vault1()).await, amount(50));
+    assert_eq!(
+        test.supply(&pool.lp_mint()).await,
+        liquidity + MINIMUM_LIQUIDITY
+    );
+
+    // accounts must follow the pool's token order
+    let deposit = instruction::add_liquidity(
+        owner,
+        &pool,
+        amount(10),
+        amount(10),
+        amount(10),
+        amount(10),
+        None,
+    );
+    let mut reversed = deposit.clone();
+    swap_accounts(&mut reversed, &pool.vault0(), &pool.vault1());
+    swap_accounts(&mut reversed, &pool.token0, &pool.token1);
+    swap_accounts(&mut reversed, &ata0, &ata1);
+    let result = test.process(&[reversed], &[&provider]).await;
+    assert_error(result, code(ErrorCode::InvalidUserTokenAccount));
+
+    let mut swapped_vaults = deposit.clone();
+    swap_accounts(&mut swapped_vaults, &pool.vault0(), &pool.vault1());
+    let result = test.process(&[swapped_vaults], &[&provider]).await;
+    assert_error(result, code(ErrorCode::InvalidVault));
+
+    // a token account of the right mint held by the pool authority is still not the vault
+    let stray_vault = test
+        .create_token_account(&pool.authority(), &pool.token0, &TOKEN_PROGRAM_ID)
+        .await;
+    let mut stray = deposit.clone();
+    replace_account(&mut stray, &pool.vault0(), &stray_vault);
+    let result = test.process(&[stray], &[&provider]).await;
+    assert_error(result, code(ErrorCode::InvalidVault));
+
+    test.process(&[deposit], &[&provider]).await.unwrap();
+}
+
+#[tokio::test]
+async fn remove_liquidity() {
+    let (mut test, pool, provider) = pool_with_liquidity().await;
+    let owner = provider.pubkey();
+    let (ata0, ata1, lp_ata) = (
+        user_ata(&owner, &pool, &pool.token0),
+        user_ata(&owner, &pool, &pool.token1),
+        user_lp_ata(&owner, &pool),
+    );
+
+    let pool_data = test.pool(&pool).await;
+    let lp_supply = test.supply(&pool.lp_mint()).await;
+    let lp_before = test.balance(&lp_ata).await;
+    let liquidity = lp_before / 2;
+    let (amount0, amount1) = math::calculate_removed_amounts(
+        liquidity,
+        lp_supply,
+        pool_data.reserve0,
+        pool_data.reserve1,
+        0,
+        0,
+    )
+    .unwrap();
+    let (balance0, balance1) = (test.balance(&ata0).await, test.balance(&ata1).await);
+
+    test.process(
+        &[instruction::remove_liquidity(
+            owner, &pool, liquidity, amount0, amount1, None,
+        )],
+        &[&provider],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(test.balance(&ata0).await - balance0, amount0);
+    assert_eq!(test.balance(&ata1).await - balance1, amount1);
+    assert_eq!(lp_before - test.balance(&lp_ata).await, liquidity);
+}
+
+#[tokio::test]
+async fn add_liquidity_single() {
+    let (mut test, pool, _) = pool_with_liquidity().await;
+    // a user holding only token0
+    let user = test.create_user().await;
+    let owner = user.pubkey();
+    let amount_in = amount(10);
+    let ata0 = test
+        .fund(&owner, &pool.token0, &pool.token_program0, amount_in)
+        .await;
+
+    let pool_data = test.pool(&pool).await;
+    let lp_supply = test.supply(&pool.lp_mint()).await;
+    let swap_amount = math::get_zap_amount(FEE, amount_in, pool_data.reserve0).unwrap();
+    let swap_amount_out =
+        math::get_amount_out(FEE, swap_amount, pool_data.reserve0, pool_data.reserve1).unwrap();
+    let (reserve0, reserve1) = (
+        pool_data.reserve0 + swap_amount,
+        pool_data.reserve1 - swap_amount_out,
+    );
+    let liquidity = math::calculate_liquidity(
+        amount_in - swap_amount,
+        swap_amount_out,
+        reserve0,
+        reserve1,
+        lp_supply,
+    )
+    .unwrap();
+    // the two halves are in the ratio of the reserves after the swap, each worth the same LP to
+    // within the rounding of the swap
+    let share = |amount: u64, reserve: u64| amount as u128 * lp_supply as u128 / reserve as u128;
+    let (liquidity0, liquidity1) = (
+        share(amount_in - swap_amount, reserve0),
+        share(swap_amount_out, reserve1),
+    );
+    assert!(liquidity0.abs_diff(liquidity1) <= 4);
+
+    let deposit = |min_liquidity| {
+        instruction::add_liquidity_single(owner, &pool, pool.token0, amount_in, min_liquidity, None)
+            .unwrap()
+    };
+    let result = test.process(&[deposit(liquidity + 1)], &[&user]).await;
+    assert_error(result, code(ErrorCode::InsufficientLiquidityMinted));
+
+    test.process(&[deposit(liquidity)], &[&user]).await.unwrap();
+    assert_eq!(test.balance(&ata0).await, 0);
+    assert_eq!(test.balance(&user_lp_ata(&owner, &pool)).await, liquidity);
+    let pool_data = test.pool(&pool).await;
+    assert_eq!(pool_data.reserve0, amount(60));
+    assert_eq!(pool_data.reserve1, amount(50));
+
+    // the first deposit needs both tokens
+    let empty = test.create_default_pool().await;
+    let ata = test
+        .fund(&owner, &empty.token0, &empty.token_program0, amount_in)
+        .await;
+    let deposit =
+        instruction::add_liquidity_single(owner, &empty, empty.token0, amount_in, 0, None).unwrap();
+    let result = test.process(&[deposit], &[&user]).await;
+    assert_error(result, code(ErrorCode::InsufficientLiquidity));
+    assert_eq!(test.balance(&ata).await, amount_in);
+}
+
+#[tokio::test]
+async fn remove_liquidity_single() {
+    let (mut test, pool, provider) = pool_with_liquidity().await;
+    let owner = provider.pubkey();
+    let (ata0, ata1, lp_ata) = (
+        user_ata(&owner, &pool, &pool.token0),
+        user_ata(&owner, &pool, &pool.token1),
+        user_lp_ata(&owner, &pool),
+    );
+
+    let pool_data = test.pool(&pool).await;
+    let lp_supply = test.supply(&pool.lp_mint()).await;
+    let lp_before = test.balance(&lp_ata).await;
+    let liquidity = lp_before / 2;
+    let (amount0, amount1) = math::calculate_removed_amounts(
+        liquidity,
+        lp_supply,
+        pool_data.reserve0,
+        pool_data.reserve1,
+        0,
+        0,
+    )
+    .unwrap();
+    // token1's share is swapped for token0 on the reserves after the burn
+    let swap_amount_out = math::get_amount_out(
+        FEE,
+        amount1,
+        pool_data.reserve1 - amount1,
+        pool_data.reserve0 - amount0,
+    )
+    .unwrap();
+    let amount_out = amount0 + swap_amount_out;
+    assert!(amount_out > amount0 && amount_out < 2 * amount0);
+    let (balance0, balance1) = (test.balance(&ata0).await, test.balance(&ata1).await);
+
+    let withdraw = |min_amount_out| {
+        instruction::remove_liquidity_single(
+            owner,
+            &pool,
+            pool.token0,
+            liquidity,
+            min_amount_out,
+            None,
+        )
+        .unwrap()
+    };
+    let result = test
+        .process(&[withdraw(amount_out + 1)], &[&provider])
+        .await;
+    assert_error(result, code(ErrorCode::InsufficientOutputAmount));
+
+    test.process(&[withdraw(amount_out)], &[&provider])
+        .await
+        .unwrap();
+    assert_eq!(test.balance(&ata0).await - balance0, amount_out);
+    assert_eq!(test.balance(&ata1).await, balance1);
+    assert_eq!(lp_before - test.balance(&lp_ata).await, liquidity);
+    // token1 never left its vault
+    let pool_data_after = test.pool(&pool).await;
+    assert_eq!(pool_data_after.reserve0, pool_data.reserve0 - amount_out);
+    assert_eq!(pool_data_after.reserve1, pool_data.reserve1);
+}
+
+#[tokio::test]
+async fn swap_exact_input() {
+    let (mut test, pool, provider) = pool_with_liquidity().await;
+    let owner = provider.pubkey();
+    let (ata0, ata1) = (
+        user_ata(&owner, &pool, &pool.token0),
+        user_ata(&owner, &pool, &pool.token1),
+    );
+
+    let amount_in = amount(10);
+    let reserve0 = test.balance(&pool.vault0()).await;
+    let reserve1 = test.balance(&pool.vault1()).await;
+    let amount_out = math::get_amount_out(FEE, amount_in, reserve0, reserve1).unwrap();
+
+    let expired = test.clock().await.unix_timestamp - 60;
+    let swap = instruction::swap_exact_input(
+        owner,
+        &pool,
+        pool.token0,
+        amount_in,
+        amount_out,
+        Some(expired),
+    )
+    .unwrap();
+    let result = test.process(&[swap], &[&provider]).await;
+    assert_error(result, code(ErrorCode::Expired));
+
+    let mut same_mint = swap_token0(&provider, &pool, amount_in, amount_out);
+    replace_account(&mut same_mint, &ata1, &ata0);
+    replace_account(&mut same_mint, &pool.vault1(), &pool.vault0());
+    replace_account(&mut same_mint, &pool.token1, &pool.token0);
+    let result = test.process(&[same_mint], &[&provider]).await;
+    assert_error(result, code(ErrorCode::InvalidSwapDirection));
+
+    let (balance0, balance1) = (test.balance(&ata0).await, test.balance(&ata1).await);
+    test.process(
+        &[swap_token0(&provider, &pool, amount_in, amount_out)],
+        &[&provider],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(balance0 - test.balance(&ata0).await, amount_in);
+    assert_eq!(test.balance(&ata1).await - balance1, amount_out);
+}
+
+#[tokio::test]
+async fn swap_exact_output() {
+    let (mut test, pool, provider) = pool_with_liquidity().await;
+    let owner = provider.pubkey();
+    let (ata0, ata1) = (
+        user_ata(&owner, &pool, &pool.token0),
+        user_ata(&owner, &pool, &pool.token1),
+    );
+
+    let amount_out = amount(1);
+    let reserve0 = test.balance(&pool.vault0()).await;
+    let reserve1 = test.balance(&pool.vault1()).await;
+    let amount_in = math::get_amount_in(FEE, amount_out, reserve0, reserve1).unwrap();
+    let (balance0, balance1) = (test.balance(&ata0).await, test.balance(&ata1).await);
+
+    let swap =
+        instruction::swap_exact_output(owner, &pool, pool.token0, amount_out, amount_in, None)
+            .unwrap();
+    let logs = test.process_with_logs(&[swap], &[&provider]).await.unwrap();
+
+    assert_eq!(balance0 - test.balance(&ata0).await, amount_in);
+    assert_eq!(test.balance(&ata1).await - balance1, amount_out);
+
+    let swap_events = events::<amm::events::Swap>(&logs);
+    assert_eq!(swap_events.len(), 1);
+    let swap_event = &swap_events[0];
+    let pool_data = test.pool(&pool).await;
+    assert_eq!(swap_event.pool, pool.pool);
+    assert_eq!(swap_event.amount_in, amount_in);
+    assert_eq!(swap_event.amount_out, amount_out);
+    assert_eq!(
+        swap_event.fee_amount,
+        math::swap_fee(FEE, amount_in).unwrap()
+    );
+    assert_eq!(swap_event.reserve0, pool_data.reserve0);
+    assert_eq!(swap_event.reserve1, pool_data.reserve1);
+}
+
+#[tokio::test]
+async fn swap_route() {
+    let (mut test, pool, provider) = pool_with_liquidity().await;
+    let owner = provider.pubkey();
+
+    // a second pool pairing token1 with a fresh mint2
+    let mint2 = test.create_mint(&TOKEN_PROGRAM_ID).await;
+    let pool_b = pool_keys(
+        (pool.token1, TOKEN_PROGRAM_ID),
+        (mint2, TOKEN_PROGRAM_ID),
+        FEE,
+        CurveParams::ConstantProduct,
+    );
+    test.create_pool(&pool_b, FEE, CurveParams::ConstantProduct)
+        .await;
+    let ata2 = test
+        .fund(&owner, &mint2, &TOKEN_PROGRAM_ID, amount(100))
+        .await;
+    test.process(
+        &[instruction::add_liquidity(
+            owner,
+            &pool_b,
+            amount(20),
+            amount(20),
+            0,
+            0,
+            None,
+        )],
+        &[&provider],
+    )
+    .await
+    .unwrap();
+
+    // token0 -> token1 through the first pool, token1 -> mint2 through the second
+    let pool_a = test.pool(&pool).await;
+    let (reserve_b1, reserve_b2) = test.pool(&pool_b).await.reserves_for(pool.token1);
+    let amount_in = amount(1);
+    let amount_mid =
+        math::get_amount_out(FEE, amount_in, pool_a.reserve0, pool_a.reserve1).unwrap();
+    let amount_out = math::get_amount_out(FEE, amount_mid, reserve_b1, reserve_b2).unwrap();
+
+    let route = [pool, pool_b];
+    let swap = |min_output_amount| {
+        instruction::swap_exact_input_route(
+            owner,
+            &route,
+            pool.token0,
+            amount_in,
+            min_output_amount,
+            None,
+        )
+        .unwrap()
+    };
+    let result = test.process(&[swap(amount_out + 1)], &[&provider]).await;
+    assert_error(result, code(ErrorCode::InsufficientOutputAmount));
+
+    let balance2 = test.balance(&ata2).await;
+    test.process(&[swap(amount_out)], &[&provider])
+        .await
+        .unwrap();
+
+    assert_eq!(test.balance(&ata2).await - balance2, amount_out);
+    let pool_data = test.pool(&pool).await;
+    assert_eq!(pool_data.reserve0, pool_a.reserve0 + amount_in);
+    assert_eq!(pool_data.reserve1, pool_a.reserve1 - amount_mid);
+}
+
+#[tokio::test]
+async fn flash_swap() {
+    let (mut test, pool, provider) = pool_with_liquidity().await;
+    let owner = provider.pubkey();
+    let (ata0, ata1) = (
+        user_ata(&owner, &pool, &pool.token0),
+        user_ata(&owner, &pool, &pool.token1),
+    );
+
+    let vault0_before = test.balance(&pool.vault0()).await;
+    let vault1_before = test.balance(&pool.vault1()).await;
+    let balance0 = test.balance(&ata0).await;
+
+    let amount_out = amount(1);
+    // one unit of headroom for the fee being rounded up on-chain
+    let repayment = math::get_amount_in(FEE, amount_out, vault1_before, vault0_before).unwrap() + 1;
+
+    // the token program itself serves as callback: it repays the pool in token1
+    let flash_swap = |repayment| {
+        let repay = spl_token::instruction::transfer(
+            &TOKEN_PROGRAM_ID,
+            &ata1,
+            &pool.vault1(),
+            &owner,
+            &[],
+            repayment,
+        )
+        .unwrap();
+        let mut flash_swap = instruction::flash_swap(
+            owner,
+            &pool,
+            (ata0, ata1),
+            amount_out,
+            0,
+            TOKEN_PROGRAM_ID,
+            repay.data,
+        );
+        flash_swap.accounts.extend(repay.accounts);
+        flash_swap
+    };
+
+    // underpaying breaks the invariant
+    let result = test
+        .process(&[flash_swap(repayment - 3)], &[&provider])
+        .await;
+    assert_error(result, code(ErrorCode::InvariantViolated));
+
+    test.process(&[flash_swap(repayment)], &[&provider])
+        .await
+        .unwrap();
+
+    assert_eq!(test.balance(&ata0).await - balance0, amount_out);
+    assert_eq!(
+        test.balance(&pool.vault1()).await - vault1_before,
+        repayment
+    );
+}
+
+#[tokio::test]
+async fn skim_and_sync() {
+    let (mut test, pool, provider) = pool_with_liquidity().await;
+    let owner = provider.pubkey();
+    let (ata0, ata1) = (
+        user_ata(&owner, &pool, &pool.token0),
+        user_ata(&owner, &pool, &pool.token1),
+    );
+    let donation = amount(1);
+
+    test.transfer(
+        &ata0,
+        &pool.vault0(),
+        &pool.token0,
+        &TOKEN_PROGRAM_ID,
+        &provider,
+        donation,
+    )
+    .await;
+
+    // donations do not move the reserves
+    let reserve0 = test.pool(&pool).await.reserve0;
+    assert_eq!(test.balance(&pool.vault0()).await - reserve0, donation);
+
+    let balance0 = test.balance(&ata0).await;
+    test.process(&[instruction::skim(&pool, ata0, ata1)], &[])
+        .await
+        .unwrap();
+    assert_eq!(test.balance(&ata0).await - balance0, donation);
+
+    // sync adopts a donation as reserves
+    test.transfer(
+        &ata0,
+        &pool.vault0(),
+        &pool.token0,
+        &TOKEN_PROGRAM_ID,
+        &provider,
+        donation,
+    )
+    .await;
+    test.process(&[instruction::sync(&pool)], &[])
+        .await
+        .unwrap();
+
+    let reserve0 = test.pool(&pool).await.reserve0;
+    assert_eq!(reserve0, test.balance(&pool.vault0()).await);
+}
+
+#[tokio::test]
+async fn protocol_fee() {
+    let (mut test, pool, provider) = pool_with_liquidity().await;
+    let owner = test.payer();
+    let fee_to = test.create_user().await;
+    test.process(&[instruction::set_fee_to(owner, fee_to.pubkey())], &[])
+        .await
+        .unwrap();
+    // grow k past the last liquidity event
+    test.process(
+        &[swap_token0(&provider, &pool, amount(10), 0)],
+        &[&provider],
+    )
+    .await
+    .unwrap();
+
+    let result = test
+        .process(
+            &[instruction::set_protocol_fee_share(
+                owner,
+                MAX_PROTOCOL_FEE_SHARE + 1,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, code(ErrorCode::InvalidProtocolFeeShare));
+    let protocol_fee_share = 2000;
+    test.process(
+        &[instruction::set_protocol_fee_share(
+            owner,
+            protocol_fee_share,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    // the protocol's share is minted on the next liquidity event
+    let pool_data = test.pool(&pool).await;
+    let lp_supply = test.supply(&pool.lp_mint()).await;
+    let protocol_fee = math::mint_fee(
+        protocol_fee_share,
+        pool_data.k_last,
+        pool_data.reserve0,
+        pool_data.reserve1,
+        lp_supply,
+    )
+    .unwrap();
+    assert!(protocol_fee > 0);
+
+    let remove_liquidity =
+        instruction::remove_liquidity(provider.pubkey(), &pool, MINIMUM_LIQUIDITY, 0, 0, None);
+    test.process(&[remove_liquidity.clone()], &[&provider])
+        .await
+        .unwrap();
+
+    assert_eq!(test.pool(&pool).await.protocol_fees, protocol_fee);
+    assert_eq!(
+        test.balance(&pool.vault_lp()).await,
+        protocol_fee + MINIMUM_LIQUIDITY
+    );
+
+    // only fee_to can claim
+    let recipient = test
+        .create_ata(&fee_to.pubkey(), &pool.lp_mint(), &TOKEN_PROGRAM_ID)
+        .await;
+    test.process(
+        &[instruction::claim_protocol_fees(
+            fee_to.pubkey(),
+            &pool,
+            recipient,
+        )],
+        &[&fee_to],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(test.balance(&recipient).await, protocol_fee);
+    assert_eq!(test.pool(&pool).await.protocol_fees, 0);
+    assert_eq!(test.balance(&pool.vault_lp()).await, MINIMUM_LIQUIDITY);
+
+    // unsetting fee_to switches the protocol fee off and clears k_last
+    test.process(&[instruction::set_fee_to(owner, Pubkey::default())], &[])
+        .await
+        .unwrap();
+    test.process(&[remove_liquidity], &[&provider])
+        .await
+        .unwrap();
+    assert_eq!(test.pool(&pool).await.k_last, 0);
+}
+
+#[tokio::test]
+async fn pause() {
+    let (mut test, pool, provider) = pool_with_liquidity().await;
+    let owner = test.payer();
+
+    test.process(&[instruction::set_paused(owner, true)], &[])
+        .await
+        .unwrap();
+    let result = test
+        .process(&[swap_token0(&provider, &pool, amount(1), 0)], &[&provider])
+        .await;
+    assert_error(result, code(ErrorCode::Paused));
+    // LPs can always exit to both tokens, a withdrawal to one swaps and stops with the swaps
+    let result = test
+        .process(
+            &[instruction::remove_liquidity_single(
+                provider.pubkey(),
+                &pool,
+                pool.token0,
+                MINIMUM_LIQUIDITY,
+                0,
+                None,
+            )
+            .unwrap()],
+            &[&provider],
+        )
+        .await;
+    assert_error(result, code(ErrorCode::Paused));
+    test.process(
+        &[instruction::remove_liquidity(
+            provider.pubkey(),
+            &pool,
+            MINIMUM_LIQUIDITY,
+            0,
+            0,
+            None,
+        )],
+        &[&provider],
+    )
+    .await
+    .unwrap();
+    test.process(&[instruction::set_paused(owner, false)], &[])
+        .await
+        .unwrap();
+
+    test.process(&[instruction::set_pool_paused(owner, pool.pool, true)], &[])
+        .await
+        .unwrap();
+    let result = test
+        .process(
+            &[instruction::add_liquidity(
+                provider.pubkey(),
+                &pool,
+                amount(1),
+                amount(1),
+                0,
+                0,
+                None,
+            )],
+            &[&provider],
+        )
+        .await;
+    assert_error(result, code(ErrorCode::Paused));
+    test.process(
+        &[instruction::set_pool_paused(owner, pool.pool, false)],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    test.process(&[swap_token0(&provider, &pool, amount(1), 0)], &[&provider])
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn quotes() {
+    let (mut test, pool, provider) = pool_with_liquidity().await;
+    let owner = provider.pubkey();
+    let (ata0, ata1, lp_ata) = (
+        user_ata(&owner, &pool, &pool.token0),
+        user_ata(&owner, &pool, &pool.token1),
+        user_lp_ata(&owner, &pool),
+    );
+
+    // the quoted output is exactly what the swap pays out
+    let amount_in = amount(1);
+    let quoted_out: u64 = test
+        .view(instruction::quote_exact_input(&pool, pool.token0, amount_in).unwrap())
+        .await
+        .unwrap();
+    let balance1 = test.balance(&ata1).await;
+    test.process(
+        &[swap_token0(&provider, &pool, amount_in, quoted_out)],
+        &[&provider],
+    )
+    .await
+    .unwrap();
+    assert_eq!(test.balance(&ata1).await - balance1, quoted_out);
+
+    let quoted_in: u64 = test
+        .view(instruction::quote_exact_output(&pool, pool.token1, quoted_out).unwrap())
+        .await
+        .unwrap();
+    assert!(quoted_in > 0);
+
+    let mut same_mint = instruction::quote_exact_input(&pool, pool.token0, amount_in).unwrap();
+    replace_account(&mut same_mint, &pool.token1, &pool.token0);
+    let result = test.view::<u64>(same_mint).await;
+    assert_error(result, code(ErrorCode::InvalidSwapDirection));
+
+    // the quoted deposit is priced after the pending protocol fee is minted
+    let deposit: LiquidityQuote = test
+        .view(instruction::quote_add_liquidity(
+            &pool,
+            amount(1),
+            amount(1),
+        ))
+        .await
+        .unwrap();
+    let lp_before = test.balance(&lp_ata).await;
+    test.process(
+        &[instruction::add_liquidity(
+            owner,
+            &pool,
+            amount(1),
+            amount(1),
+            deposit.amount0,
+            deposit.amount1,
+            None,
+        )],
+        &[&provider],
+    )
+    .await
+    .unwrap();
+    assert_eq!(test.balance(&lp_ata).await - lp_before, deposit.liquidity);
+
+    let withdrawal: LiquidityQuote = test
+        .view(instruction::quote_remove_liquidity(
+            &pool,
+            deposit.liquidity,
+        ))
+        .await
+        .unwrap();
+    let balance0 = test.balance(&ata0).await;
+    test.process(
+        &[instruction::remove_liquidity(
+            owner,
+            &pool,
+            deposit.liquidity,
+            withdrawal.amount0,
+            withdrawal.amount1,
+            None,
+        )],
+        &[&provider],
+    )
+    .await
+    .unwrap();
+    assert_eq!(test.balance(&ata0).await - balance0, withdrawal.amount0);
+}
+
+#[tokio::test]
+async fn twap() {
+    let (mut test, pool, _) = pool_with_liquidity().await;
+    let before = test.pool(&pool).await;
+    assert!(before.block_timestamp_last > 0);
+
+    // let some time pass so the window is non-empty
+    test.advance_clock(2).await;
+
+    let twap: Twap = test
+        .view(instruction::get_twap(
+            pool.pool,
+            PriceObservation {
+                timestamp: before.block_timestamp_last,
+                price0_cumulative: before.price0_cumulative_last,
+                price1_cumulative: before.price1_cumulative_last,
+            },
+        ))
+        .await
+        .unwrap();
+
+    // reserves have not moved since the deposit, so the average is the spot price
+    let spot_price0 = ((before.reserve1 as u128) << 64) / before.reserve0 as u128;
+    assert!(twap.price0_average.abs_diff(spot_price0) <= 1);
+    assert!(twap.observation.timestamp > before.block_timestamp_last);
+}
+
+#[tokio::test]
+async fn observations() {
+    let (mut test, pool, _) = pool_with_liquidity().await;
+    let payer = test.payer();
+
+    test.process(
+        &[instruction::increase_observation_cardinality(
+            payer, pool.pool, 4,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let (observations, slots) = test.observations(&pool).await;
+    assert_eq!(observations.cardinality, 1);
+    assert_eq!(observations.cardinality_next, 4);
+    assert_eq!(slots, 4);
+
+    test.advance_clock(10).await;
+    let consulted: Vec<Observation> = test
+        .view(instruction::consult(pool.pool, vec![0, 1]))
+        .await
+        .unwrap();
+    let (now, earlier) = (&consulted[0], &consulted[1]);
+    assert_eq!(now.timestamp - earlier.timestamp, 1);
+    assert!(now.price0_cumulative > earlier.price0_cumulative);
+
+    let result = test
+        .view::<Vec<Observation>>(instruction::consult(pool.pool, vec![1_000_000]))
+        .await;
+    assert_error(result, code(ErrorCode::ObservationTooOld));
+}
+
+#[tokio::test]
+async fn observations_at_max_cardinality() {
+    let (mut test, pool, provider) = pool_with_liquidity().await;
+    let payer = test.payer();
+    let grow = |cardinality_next| {
+        instruction::increase_observation_cardinality(payer, pool.pool, cardinality_next)
+    };
+
+    // an account grows by at most 10KB per instruction, 160 slots
+    let mut cardinality_next = 1;
+    while cardinality_next < MAX_OBSERVATION_CARDINALITY {
+        cardinality_next = (cardinality_next + 160).min(MAX_OBSERVATION_CARDINALITY);
+        test.process(&[grow(cardinality_next)], &[]).await.unwrap();
+    }
+    let (observations, slots) = test.observations(&pool).await;
+    assert_eq!(observations.cardinality_next, MAX_OBSERVATION_CARDINALITY);
+    assert_eq!(slots, MAX_OBSERVATION_CARDINALITY as usize);
+
+    let result = test
+        .process(&[grow(MAX_OBSERVATION_CARDINALITY + 1)], &[])
+        .await;
+    assert_error(result, code(ErrorCode::InvalidObservationCardinality));
+
+    // the buffer is used in place, a full one leaves the liquidity withdrawable
+    test.advance_clock(10).await;
+    let owner = provider.pubkey();
+    let liquidity = test.balance(&user_lp_ata(&owner, &pool)).await;
+    test.process(
+        &[instruction::remove_liquidity(
+            owner, &pool, liquidity, 0, 0, None,
+        )],
+        &[&provider],
+    )
+    .await
+    .unwrap();
+    assert_eq!(test.balance(&user_lp_ata(&owner, &pool)).await, 0);
+    let (observations, _) = test.observations(&pool).await;
+    assert_eq!(observations.cardinality, MAX_OBSERVATION_CARDINALITY);
+}
+
+#[tokio::test]
+async fn token_2022_transfer_fee() {
+    let mut test = TestContext::initialized().await;
+    // 1% transfer fee on a Token-2022 mint, paired with a classic mint
+    let transfer_fee_basis_points = 100;
+    let fee_mint = test
+        .create_transfer_fee_mint(transfer_fee_basis_points, amount(1000))
+        .await;
+    let classic_mint = test.create_mint(&TOKEN_PROGRAM_ID).await;
+    let transfer_fee =
+        |amount: u64| (amount * transfer_fee_basis_points as u64).div_ceil(BASIS_POINTS);
+
+    let pool = pool_keys(
+        (fee_mint, TOKEN_2022_PROGRAM_ID),
+        (classic_mint, TOKEN_PROGRAM_ID),
+        FEE,
+        CurveParams::ConstantProduct,
+    );
+    test.create_pool(&pool, FEE, CurveParams::ConstantProduct)
+        .await;
+    let provider = test.create_liquidity_provider(&pool, 100).await;
+    let owner = provider.pubkey();
+
+    let deposit = amount(10);
+    test.process(
+        &[instruction::add_liquidity(
+            owner, &pool, deposit, deposit, 0, 0, None,
+        )],
+        &[&provider],
+    )
+    .await
+    .unwrap();
+
+    // the reserves only count what arrived in the vaults, the classic side pays no fee
+    let (reserve_fee, reserve_classic) = test.pool(&pool).await.reserves_for(fee_mint);
+    assert_eq!(reserve_fee, deposit - transfer_fee(deposit));
+    assert_eq!(reserve_classic, deposit);
+
+    let amount_in = amount(1);
+    let amount_out = math::get_amount_out(
+        FEE,
+        amount_in - transfer_fee(amount_in),
+        reserve_fee,
+        reserve_classic,
+    )
+    .unwrap();
+    let classic_ata = user_ata(&owner, &pool, &classic_mint);
+    let balance = test.balance(&classic_ata).await;
+    test.process(
+        &[
+            instruction::swap_exact_input(owner, &pool, fee_mint, amount_in, amount_out, None)
+                .unwrap(),
+        ],
+        &[&provider],
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(test.balance(&classic_ata).await - balance, amount_out);
+    let (reserve_fee, _) = test.pool(&pool).await.reserves_for(fee_mint);
+    let vault_fee = pda::vault(&pool.pool, &fee_mint, &TOKEN_2022_PROGRAM_ID);
+    assert_eq!(reserve_fee, test.balance(&vault_fee).await);
+
+    // the withdrawal minimums are checked against what arrives after the transfer fee
+    let liquidity = test.balance(&user_lp_ata(&owner, &pool)).await;
+    let withdrawal: LiquidityQuote = test
+        .view(instruction::quote_remove_liquidity(&pool, liquidity))
+        .await
+        .unwrap();
+    let fee_first = pool.token0 == fee_mint;
+    let (min_fee, min_classic) = if fee_first {
+        (withdrawal.amount0, withdrawal.amount1)
+    } else {
+        (withdrawal.amount1, withdrawal.amount0)
+    };
+    let remove_liquidity = |min_fee: u64| {
+        let (amount0_min, amount1_min) = if fee_first {
+            (min_fee, min_classic)
+        } else {
+            (min_classic, min_fee)
+        };
+        instruction::remove_liquidity(owner, &pool, liquidity, amount0_min, amount1_min, None)
+    };
+    let result = test
+        .process(&[remove_liquidity(min_fee + 1)], &[&provider])
+        .await;
+    assert_error(result, code(ErrorCode::InsufficientAmount));
+    let fee_ata = user_ata(&owner, &pool, &fee_mint);
+    let balance = test.balance(&fee_ata).await;
+    test.process(&[remove_liquidity(min_fee)], &[&provider])
+        .await
+        .unwrap();
+    assert_eq!(test.balance(&fee_ata).await - balance, min_fee);
+}
+
+#[tokio::test]
+async fn permissionless_pool_creation() {
+    let mut test = TestContext::initialized().await;
+    let owner = test.payer();
+    let creator = test.create_user().await;
+    let fee_to = Pubkey::new_unique();
+    test.process(&[instruction::set_fee_to(owner, fee_to)], &[])
+        .await
+        .unwrap();
+
+    let mint_a = test.create_mint(&TOKEN_PROGRAM_ID).await;
+    let mint_b = test.create_mint(&TOKEN_PROGRAM_ID).await;
+    let pool = pool_keys(
+        (mint_a, TOKEN_PROGRAM_ID),
+        (mint_b, TOKEN_PROGRAM_ID),
+        FEE,
+        CurveParams::ConstantProduct,
+    );
+    test.create_vaults(&pool).await;
+    let create_pool = |creation_fee| {
+        instruction::create_pool(
+            creator.pubkey(),
+            &pool,
+            FEE,
+            CurveParams::ConstantProduct,
+            creation_fee,
+        )
+    };
+
+    // allowlist mode: only the owner lists pools
+    test.process(
+        &[instruction::set_pool_creation_restricted(owner, true)],
+        &[],
+    )
+    .await
+    .unwrap();
+    let result = test.process(&[create_pool(None)], &[&creator]).await;
+    assert_error(result, code(ErrorCode::PoolCreationRestricted));
+    test.process(
+        &[instruction::set_pool_creation_restricted(owner, false)],
+        &[],
+    )
+    .await
+    .unwrap();
+
+    let creation_fee = LAMPORTS_PER_SOL / 2;
+    test.process(
+        &[instruction::set_pool_creation_fee(
+            owner,
+            Pubkey::default(),
+            creation_fee,
+        )],
+        &[],
+    )
+    .await
+    .unwrap();
+    let result = test.process(&[create_pool(None)], &[&creator]).await;
+    assert_error(result, code(ErrorCode::MissingCreationFeeAccount));
+
+    let fee_to_before = test.lamports(&fee_to).await;
+    test.process(
+        &[create_pool(Some(CreationFee::Lamports { fee_to }))],
+        &[&creator],
+    )
+    .await
+    .unwrap();
+    assert_eq!(test.lamports(&fee_to).await - fee_to_before, creation_fee);
+    assert_eq!(test.pool(&pool).await.token0, pool.token0);
+}
+
+#[tokio::test]
+async fn renounce_ownership() {
+    let mut test = TestContext::initialized().await;
+    let owner = test.payer();
+
+    test.process(&[instruction::renounce_owner(owner)], &[])
+        .await
+        .unwrap();
+
+    let config: Config = test.account(&pda::config()).await;
+    assert_eq!(config.owner, Pubkey::default());
+    let result = test
+        .process(&[instruction::set_fee_to(owner, owner)], &[])
+        .await;
+    assert_error(result, AnchorErrorCode::ConstraintHasOne as u32);
+}
+
+#[tokio::test]
+async fn stable_pool() {
+    let mut test = TestContext::initialized().await;
+    let owner = test.payer();
+    let amp = 100;
+    let mint_a = test.create_mint(&TOKEN_PROGRAM_ID).await;
+    let mint_b = test.create_mint(&TOKEN_PROGRAM_ID).await;
+
+    // the pegged tokens must have the same decimals
+    let mint_c = test
+        .create_mint_with_decimals(&TOKEN_PROGRAM_ID, DECIMALS + 3)
+        .await;
+    let curve = CurveParams::Stable { amp };
+    let mismatched = pool_keys(
+        (mint_a, TOKEN_PROGRAM_ID),
+        (mint_c, TOKEN_PROGRAM_ID),
+        FEE,
+        curve,
+    );
+    test.create_vaults(&mismatched).await;
+    let create_pool = instruction::create_pool(owner, &mismatched, FEE, curve, None);
+    let result = test.process(&[create_pool], &[]).await;
+    assert_error(result, code(ErrorCode::InvalidDecimals));
+
+    let pool = pool_keys(
+        (mint_a, TOKEN_PROGRAM_ID),
+        (mint_b, TOKEN_PROGRAM_ID),
+        FEE,
+        curve,
+    );
+    test.create_pool(&pool, FEE, curve).await;
+    // the pair keeps a constant product pool of its own on the same tier
+    let constant_product = pool_keys(
+        (mint_a, TOKEN_PROGRAM_ID),
+        (mint_b, TOKEN_PROGRAM_ID),
+        FEE,
+        CurveParams::ConstantProduct,
+    );
+    assert_ne!(constant_product.pool, pool.pool);
+    test.create_pool(&constant_product, FEE, CurveParams::ConstantProduct)
+        .await;
+    let provider = test.create_liquidity_provider(&pool, 100).await;
+    let user = provider.pubkey();
+
+    // the first deposit is minted D, which is the sum of the reserves at balance
+    let deposit = amount(50);
+    test.process(
+        &[instruction::add_liquidity(
+            user, &pool, deposit, deposit, 0, 0, None,
+        )],
+        &[&provider],
+    )
+    .await
+    .unwrap();
+    assert_eq!(test.supply(&pool.lp_mint()).await, 2 * deposit);
+    assert_eq!(
+        test.balance(&user_lp_ata(&user, &pool)).await,
+        2 * deposit - MINIMUM_LIQUIDITY
+    );
+
+    // around balance the curve pays out close to one for one, more than the constant product
+    let amount_in = amount(1);
+    let amount_out = stable_swap::get_amount_out(amp, FEE, amount_in, deposit, deposit).unwrap();
+    assert!(amount_out > math::get_amount_out(FEE, amount_in, deposit, deposit).unwrap());
+    let ata_out = user_ata(&user, &pool, &pool.token1);
+    let balance = test.balance(&ata_out).await;
+    test.process(
+        &[
+            instruction::swap_exact_input(user, &pool, pool.token0, amount_in, amount_out, None)
+                .unwrap(),
+        ],
+        &[&provider],
+    )
+    .await
+    .unwrap();
+    assert_eq!(test.balance(&ata_out).await - balance, amount_out);
+
+    // a single-sided deposit fits the default compute budget, D is computed once for the zap
+    zap(&mut test, &pool, &provider).await;
+
+    // only stable pools ramp
+    let constant_product = test.create_default_pool().await;
+    let now = test.clock().await.unix_timestamp;
+    let ramp = |pool: Pubkey, future_amp: u64, future_amp_time: i64| {
+        instruction::ramp_amp(owner, pool, future_amp, future_amp_time)
+    };
+    let result = test
+        .process(
+            &[ramp(
+                constant_product.pool,
+                2 * amp,
+                now + 2 * MIN_RAMP_DURATION,
+            )],
+            &[],
+        )
+        .await;
+    assert_error(result, code(ErrorCode::InvalidCurve));
+
+    // not before a day since the pool's creation, nor by more than 10x
+    let result = test
+        .process(
+            &[ramp(pool.pool, 2 * amp, now + 2 * MIN_RAMP_DURATION)],
+            &[],
+        )
+        .await;
+    assert_error(result, code(ErrorCode::InvalidAmplificationRamp));
+    test.advance_clock(MIN_RAMP_DURATION).await;
+    let now = now + MIN_RAMP_DURATION;
+    let result = test
+        .process(&[ramp(pool.pool, 11 * amp, now + MIN_RAMP_DURATION)], &[])
+        .await;
+    assert_error(result, code(ErrorCode::InvalidAmplificationRamp));
+
+    test.process(&[ramp(pool.pool, 2 * amp, now + MIN_RAMP_DURATION)], &[])
+        .await
+        .unwrap();
+    let pool_data = test.pool(&pool).await;
+    assert_eq!(pool_data.amp(now), amp);
+    assert_eq!(pool_data.amp(now + MIN_RAMP_DURATION / 2), amp + amp / 2);
+    assert_eq!(pool_data.amp(now + MIN_RAMP_DURATION), 2 * amp);
+
+    // the ramp raises D of the unbalanced reserves without any fee earned, the protocol is not
+    // minted a share of that
+    test.process(
+        &[
+            instruction::set_fee_to(owner, owner),
+            instruction::set_protocol_fee_share(owner, 2000),
+        ],
+        &[],
+    )
+    .await
+    .unwrap();
+    let remove_liquidity =
+        |liquidity: u64| instruction::remove_liquidity(user, &pool, liquidity, 0, 0, None);
+    test.process(&[remove_liquidity(MINIMUM_LIQUIDITY)], &[&provider])
+        .await
+        .unwrap();
+    assert!(test.pool(&pool).await.k_last > 0);
+    test.advance_clock(MIN_RAMP_DURATION).await;
+    test.process(&[remove_liquidity(2 * MINIMUM_LIQUIDITY)], &[&provider])
+        .await
+        .unwrap();
+    assert_eq!(test.pool(&pool).await.protocol_fees, 0);
+}
+
+#[tokio::test]
+async fn weighted_pool() {
+    let mut test = TestContext::initialized().await;
+    let payer = test.payer();
+    let (weight0, weight1) = (8000, 2000);
+    let mint_a = test.create_mint(&TOKEN_PROGRAM_ID).await;
+    let mint_b = test.create_mint(&TOKEN_PROGRAM_ID).await;
+    let keys = |curve| {
+        pool_keys(
+            (mint_a, TOKEN_PROGRAM_ID),
+            (mint_b, TOKEN_PROGRAM_ID),
+            FEE,
+            curve,
+        )
+    };
+
+    // the weights add up to 100% and none is below 1%
+    for (weight0, weight1) in [(7000, 2000), (9950, 50)] {
+        let curve = CurveParams::Weighted { weight0, weight1 };
+        let invalid = keys(curve);
+        test.create_vaults(&invalid).await;
+        let result = test
+            .process(
+                &[instruction::create_pool(payer, &invalid, FEE, curve, None)],
+                &[],
+            )
+            .await;
+        assert_error(result, code(ErrorCode::InvalidWeights));
+    }
+    let curve = CurveParams::Weighted { weight0, weight1 };
+    let pool = keys(curve);
+    test.create_vaults(&pool).await;
+    test.process(
+        &[instruction::create_pool(payer, &pool, FEE, curve, None)],
+        &[],
+    )
+    .await
+    .unwrap();
+    let provider = test.create_liquidity_provider(&pool, 100).await;
+    let user = provider.pubkey();
+
+    // an 80/20 pool holding 80 of token0 and 20 of token1 prices them one for one
+    let (reserve0, reserve1) = (amount(80), amount(20));
+    test.process(
+        &[instruction::add_liquidity(
+            user, &pool, reserve0, reserve1, 0, 0, None,
+        )],
+        &[&provider],
+    )
+    .await
+    .unwrap();
+    let invariant = weighted_product::compute_invariant(weight0, weight1, reserve0, reserve1);
+    assert_eq!(
+        test.supply(&pool.lp_mint()).await as u128,
+        invariant.unwrap()
+    );
+
+    let amount_in = amount(1) / 100;
+    let amount_out =
+        weighted_product::get_amount_out(weight0, weight1, FEE, amount_in, reserve0, reserve1)
+            .unwrap();
+    assert!(amount_out < amount_in && amount_out > amount_in * 99 / 100);
+    let ata_out = user_ata(&user, &pool, &pool.token1);
+    let balance = test.balance(&ata_out).await;
+    test.process(
+        &[
+            instruction::swap_exact_input(user, &pool, pool.token0, amount_in, amount_out, None)
+                .unwrap(),
+        ],
+        &[&provider],
+    )
+    .await
+    .unwrap();
+    assert_eq!(test.balance(&ata_out).await - balance, amount_out);
+
+    // buying `amount_out` of token0 back costs a little more token1 than the swap paid out
+    let cost = weighted_product::get_amount_in(
+        weight1,
+        weight0,
+        FEE,
+        amount_out,
+        reserve1 - amount_out,
+        reserve0 + amount_in,
+    )
+    .unwrap();
+    assert!(cost > amount_out);
+    let balance = test.balance(&ata_out).await;
+    test.process(
+        &[
+            instruction::swap_exact_output(user, &pool, pool.token1, amount_out, cost, None)
+                .unwrap(),
+        ],
+        &[&provider],
+    )
+    .await
+    .unwrap();
+    assert_eq!(balance - test.balance(&ata_out).await, cost);
+
+    // a single-sided deposit fits the default compute budget, the zap starting from its closed form
+    // without a fee
+    zap(&mut test, &pool, &provider).await;
+}
+
+// deposits 10 of token0 alone and checks the LP minted against the SDK
+async fn zap(test: &mut TestContext, pool: &PoolKeys, provider: &Keypair) {
+    let user = provider.pubkey();
+    let amount_in = amount(10);
+    let (_, liquidity) = test
+        .pool_state(pool)
+        .await
+        .add_liquidity_single(pool.token0, amount_in, DEFAULT_PROTOCOL_FEE_SHARE)
+        .unwrap();
+    let lp_ata = user_lp_ata(&user, pool);
+    let balance = test.balance(&lp_ata).await;
+    test.process(
+        &[
+            instruction::add_liquidity_single(user, pool, pool.token0, amount_in, liquidity, None)
+                .unwrap(),
+        ],
+        &[provider],
+    )
+    .await
+    .unwrap();
+    assert_eq!(test.balance(&lp_ata).await - balance, liquidity);
+}
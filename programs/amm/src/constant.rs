@@ -1 +1,22 @@
 pub const BASIS_POINTS: u64 = 10000;
+// LP tokens locked in the pool's own LP account on the first deposit
+pub const MINIMUM_LIQUIDITY: u64 = 1000;
+// upper bound on the number of fee tiers enabled at once
+pub const MAX_FEE_TIERS: usize = 16;
+// upper bound on the slots of a pool's observation ring buffer, 64KB of account data
+pub const MAX_OBSERVATION_CARDINALITY: u16 = 1024;
+// share of the LP fee growth minted to `fee_to` when the protocol fee is on, in basis points, about
+// the 1/6 of Uniswap V2
+pub const DEFAULT_PROTOCOL_FEE_SHARE: u64 = 1667;
+// upper bound on the protocol fee share, the LPs keep at least half of the fee growth
+pub const MAX_PROTOCOL_FEE_SHARE: u64 = 5000;
+// bounds of a StableSwap pool's amplification coefficient
+pub const MIN_AMP: u64 = 1;
+pub const MAX_AMP: u64 = 1_000_000;
+// an amplification ramp changes the coefficient by at most this factor and lasts at least a day, a
+// new ramp only starts a day after the previous one did
+pub const MAX_AMP_CHANGE: u64 = 10;
+pub const MIN_RAMP_DURATION: i64 = 86400;
+// smallest weight of a token in a weighted pool, in basis points, the weights of a pool adding up to
+// `BASIS_POINTS`
+pub const MIN_WEIGHT: u64 = 100;
@@ -0,0 +1,202 @@
+// Constant product invariant of a two-token pool, as in Uniswap V2:
+//
+//   x · y = k
+//
+// The fee is taken out of the input before it is priced, and both directions round in the pool's
+// favour.
+use crate::constant::{BASIS_POINTS, MINIMUM_LIQUIDITY};
+use crate::error::ErrorCode;
+use crate::math::{invariant_growth_liquidity, sqrt, to_u128, to_u64, CheckedMath, U256};
+use anchor_lang::prelude::*;
+use fixed::types::U64F64;
+use std::cmp::min;
+
+pub fn get_amount_out(fee: u64, amount_in: u64, reserve_in: u64, reserve_out: u64) -> Result<u64> {
+    require!(
+        reserve_in > 0 && reserve_out > 0,
+        ErrorCode::InsufficientLiquidity,
+    );
+    let amount_in_with_fee = (amount_in as u128).safe_mul(BASIS_POINTS.safe_sub(fee)? as u128)?;
+    let numerator = amount_in_with_fee.safe_mul(reserve_out as u128)?;
+    let denominator = (reserve_in as u128)
+        .safe_mul(BASIS_POINTS as u128)?
+        .safe_add(amount_in_with_fee)?;
+    to_u64(numerator.safe_div(denominator)?)
+}
+
+pub fn get_amount_in(fee: u64, amount_out: u64, reserve_in: u64, reserve_out: u64) -> Result<u64> {
+    require!(
+        reserve_in > 0 && reserve_out > 0,
+        ErrorCode::InsufficientLiquidity,
+    );
+    let numerator = (reserve_in as u128)
+        .safe_mul(amount_out as u128)?
+        .safe_mul(BASIS_POINTS as u128)?;
+    let denominator =
+        (reserve_out.safe_sub(amount_out)? as u128).safe_mul(BASIS_POINTS.safe_sub(fee)? as u128)?;
+    to_u64(numerator.safe_div(denominator)?.safe_add(1)?)
+}
+
+// spot prices of token0 in token1 and of token1 in token0, Q64.64 fixed point; reserves must be non-zero
+pub fn spot_prices(reserve0: u64, reserve1: u64) -> (u128, u128) {
+    (
+        (U64F64::from_num(reserve1) / U64F64::from_num(reserve0)).to_bits(),
+        (U64F64::from_num(reserve0) / U64F64::from_num(reserve1)).to_bits(),
+    )
+}
+
+// part of `amount_in` to swap so that the rest and the output are in the ratio of the reserves after
+// the swap, the root of γ·s² + (1 + γ)·r·s - r·a = 0 with γ the fee complement:
+//
+//   s = (√((1 + γ)²·r² + 4·γ·r·a) - (1 + γ)·r) / 2·γ
+pub fn get_zap_amount(fee: u64, amount_in: u64, reserve_in: u64) -> Result<u64> {
+    require!(reserve_in > 0, ErrorCode::InsufficientLiquidity);
+    // in basis points, γ·B and (1 + γ)·B
+    let fee_complement = U256::from(BASIS_POINTS.safe_sub(fee)?);
+    let fee_sum = U256::from(BASIS_POINTS).safe_add(fee_complement)?;
+    let reserve_in = U256::from(reserve_in);
+    let b = fee_sum.safe_mul(reserve_in)?;
+    let discriminant = b.safe_mul(b)?.safe_add(
+        U256::from(4 * BASIS_POINTS)
+            .safe_mul(fee_complement)?
+            .safe_mul(reserve_in)?
+            .safe_mul(U256::from(amount_in))?,
+    )?;
+    to_u64(to_u128(
+        discriminant
+            .integer_sqrt()
+            .safe_sub(b)?
+            .safe_div(U256::from(2).safe_mul(fee_complement)?)?,
+    )?)
+}
+
+// LP minted to the depositor of `amount0` and `amount1`, the first deposit locks MINIMUM_LIQUIDITY
+// of its share
+pub fn calculate_liquidity(
+    amount0: u64,
+    amount1: u64,
+    reserve0: u64,
+    reserve1: u64,
+    lp_supply: u64,
+) -> Result<u64> {
+    let liquidity: u64 = if lp_supply == 0 {
+        let liquidity = to_u64(sqrt((amount0 as u128).safe_mul(amount1 as u128)?))?;
+        require!(
+            liquidity > MINIMUM_LIQUIDITY,
+            ErrorCode::InsufficientLiquidityMinted
+        );
+        liquidity.safe_sub(MINIMUM_LIQUIDITY)?
+    } else {
+        to_u64(min(
+            (amount0 as u128)
+                .safe_mul(lp_supply as u128)?
+                .safe_div(reserve0 as u128)?,
+            (amount1 as u128)
+                .safe_mul(lp_supply as u128)?
+                .safe_div(reserve1 as u128)?,
+        ))?
+    };
+    require!(liquidity > 0, ErrorCode::InsufficientLiquidityMinted);
+
+    Ok(liquidity)
+}
+
+pub fn calculate_liquidity_amounts(
+    reserve0: u64,
+    reserve1: u64,
+    amount0_desired: u64,
+    amount1_desired: u64,
+    amount0_min: u64,
+    amount1_min: u64,
+) -> Result<(u64, u64)> {
+    let amount0: u64;
+    let amount1: u64;
+    if reserve0 == 0 && reserve1 == 0 {
+        (amount0, amount1) = (amount0_desired, amount1_desired);
+    } else {
+        let amount1_optimal = quote(amount0_desired, reserve0, reserve1)?;
+        if amount1_optimal <= amount1_desired {
+            require!(
+                amount1_optimal >= amount1_min,
+                ErrorCode::InsufficientAmount
+            );
+            (amount0, amount1) = (amount0_desired, amount1_optimal);
+        } else {
+            let amount0_optimal = quote(amount1_desired, reserve1, reserve0)?;
+            require!(
+                amount0_optimal <= amount0_desired,
+                ErrorCode::InsufficientAmount
+            );
+            require!(
+                amount0_optimal >= amount0_min,
+                ErrorCode::InsufficientAmount
+            );
+            (amount0, amount1) = (amount0_optimal, amount1_desired);
+        }
+    }
+
+    Ok((amount0, amount1))
+}
+
+// given some amount of an asset and pair reserves, returns an equivalent amount of the other asset
+pub fn quote(amount0: u64, reserve0: u64, reserve1: u64) -> Result<u64> {
+    require!(amount0 > 0, ErrorCode::InsufficientAmount);
+    require!(
+        reserve0 > 0 && reserve1 > 0,
+        ErrorCode::InsufficientReserves
+    );
+
+    to_u64(
+        (amount0 as u128)
+            .safe_mul(reserve1 as u128)?
+            .safe_div(reserve0 as u128)?,
+    )
+}
+
+pub fn calculate_removed_amounts(
+    liquidity: u64,
+    lp_supply: u64,
+    reserve0: u64,
+    reserve1: u64,
+    amount0_min: u64,
+    amount1_min: u64,
+) -> Result<(u64, u64)> {
+    let amount0 = to_u64(
+        (liquidity as u128)
+            .safe_mul(reserve0 as u128)?
+            .safe_div(lp_supply as u128)?,
+    )?;
+    let amount1 = to_u64(
+        (liquidity as u128)
+            .safe_mul(reserve1 as u128)?
+            .safe_div(lp_supply as u128)?,
+    )?;
+
+    require!(
+        amount0 >= amount0_min && amount1 >= amount1_min,
+        ErrorCode::InsufficientAmount
+    );
+
+    require!(
+        amount0 != 0 && amount1 != 0,
+        ErrorCode::InsufficientLiquidityBurned
+    );
+
+    Ok((amount0, amount1))
+}
+
+// LP owed to the protocol for the fee growth from `k_last` to the current reserves
+pub fn protocol_fee_liquidity(
+    protocol_fee_share: u64,
+    k_last: u128,
+    reserve0: u64,
+    reserve1: u64,
+    lp_supply: u64,
+) -> Result<u64> {
+    if k_last == 0 {
+        return Ok(0);
+    }
+
+    let root_k = sqrt((reserve0 as u128).safe_mul(reserve1 as u128)?);
+    invariant_growth_liquidity(protocol_fee_share, sqrt(k_last), root_k, lp_supply)
+}
@@ -0,0 +1,337 @@
+// Pricing of a pool, behind one interface so that the instructions work the same whatever the curve.
+// Amounts and reserves are in pool token order, `zero_for_one` a swap of token0 for token1.
+use crate::constant::MINIMUM_LIQUIDITY;
+use crate::constant_product::{
+    self, calculate_liquidity, calculate_removed_amounts, protocol_fee_liquidity,
+};
+use crate::error::ErrorCode;
+use crate::math::{invariant_growth_liquidity, sqrt, to_u64, CheckedMath};
+use crate::stable_swap;
+use crate::weighted_product;
+use anchor_lang::prelude::*;
+
+pub trait Curve {
+    // output for `amount_in` arriving in the pool
+    fn swap_exact_input(
+        &self,
+        zero_for_one: bool,
+        amount_in: u64,
+        reserve0: u64,
+        reserve1: u64,
+    ) -> Result<u64>;
+
+    // input asked to arrive in the pool for `amount_out`
+    fn swap_exact_output(
+        &self,
+        zero_for_one: bool,
+        amount_out: u64,
+        reserve0: u64,
+        reserve1: u64,
+    ) -> Result<u64>;
+
+    // invariant of the reserves, growing linearly with them
+    fn invariant(&self, reserve0: u64, reserve1: u64) -> Result<u128>;
+
+    // spot prices of token0 in token1 and of token1 in token0, Q64.64 fixed point; reserves must be
+    // non-zero
+    fn spot_prices(&self, reserve0: u64, reserve1: u64) -> Result<(u128, u128)>;
+
+    // part of `amount_in` a single-sided deposit swaps for the other token so that the rest and the
+    // output are in the ratio of the reserves after the swap
+    fn zap_amount(
+        &self,
+        zero_for_one: bool,
+        amount_in: u64,
+        reserve0: u64,
+        reserve1: u64,
+    ) -> Result<u64>;
+
+    // whether the invariant of the balances is at least that of the reserves
+    fn invariant_holds(
+        &self,
+        balance0: u64,
+        balance1: u64,
+        reserve0: u64,
+        reserve1: u64,
+    ) -> Result<bool> {
+        Ok(self.invariant(balance0, balance1)? >= self.invariant(reserve0, reserve1)?)
+    }
+
+    // LP minted for depositing `amount0` and `amount1`: the invariant of the first deposit less the
+    // locked `MINIMUM_LIQUIDITY`, then in proportion to the reserves
+    fn deposit(
+        &self,
+        amount0: u64,
+        amount1: u64,
+        reserve0: u64,
+        reserve1: u64,
+        lp_supply: u64,
+    ) -> Result<u64> {
+        if lp_supply > 0 {
+            return calculate_liquidity(amount0, amount1, reserve0, reserve1, lp_supply);
+        }
+        let invariant = to_u64(self.invariant(amount0, amount1)?)?;
+        require!(
+            invariant > MINIMUM_LIQUIDITY,
+            ErrorCode::InsufficientLiquidityMinted
+        );
+        invariant.safe_sub(MINIMUM_LIQUIDITY)
+    }
+
+    // amounts paid out for burning `liquidity`, in proportion to the reserves
+    fn withdraw(
+        &self,
+        liquidity: u64,
+        lp_supply: u64,
+        reserve0: u64,
+        reserve1: u64,
+    ) -> Result<(u64, u64)> {
+        calculate_removed_amounts(liquidity, lp_supply, reserve0, reserve1, 0, 0)
+    }
+
+    // what `Pool.k_last` records of the reserves
+    fn k_last(&self, reserve0: u64, reserve1: u64) -> Result<u128> {
+        self.invariant(reserve0, reserve1)
+    }
+
+    // LP owed to the protocol for the growth of the invariant since `k_last`
+    fn protocol_fee_liquidity(
+        &self,
+        protocol_fee_share: u64,
+        k_last: u128,
+        reserve0: u64,
+        reserve1: u64,
+        lp_supply: u64,
+    ) -> Result<u64> {
+        if k_last == 0 {
+            return Ok(0);
+        }
+        let invariant = self.invariant(reserve0, reserve1)?;
+        invariant_growth_liquidity(protocol_fee_share, k_last, invariant, lp_supply)
+    }
+}
+
+// (in, out) of a pair kept in pool token order
+fn in_out<T>(zero_for_one: bool, value0: T, value1: T) -> (T, T) {
+    if zero_for_one {
+        (value0, value1)
+    } else {
+        (value1, value0)
+    }
+}
+
+pub struct ConstantProduct {
+    pub fee: u64,
+}
+
+impl Curve for ConstantProduct {
+    fn swap_exact_input(
+        &self,
+        zero_for_one: bool,
+        amount_in: u64,
+        reserve0: u64,
+        reserve1: u64,
+    ) -> Result<u64> {
+        let (reserve_in, reserve_out) = in_out(zero_for_one, reserve0, reserve1);
+        constant_product::get_amount_out(self.fee, amount_in, reserve_in, reserve_out)
+    }
+
+    fn swap_exact_output(
+        &self,
+        zero_for_one: bool,
+        amount_out: u64,
+        reserve0: u64,
+        reserve1: u64,
+    ) -> Result<u64> {
+        let (reserve_in, reserve_out) = in_out(zero_for_one, reserve0, reserve1);
+        constant_product::get_amount_in(self.fee, amount_out, reserve_in, reserve_out)
+    }
+
+    // √k
+    fn invariant(&self, reserve0: u64, reserve1: u64) -> Result<u128> {
+        Ok(sqrt((reserve0 as u128).safe_mul(reserve1 as u128)?))
+    }
+
+    fn spot_prices(&self, reserve0: u64, reserve1: u64) -> Result<(u128, u128)> {
+        Ok(constant_product::spot_prices(reserve0, reserve1))
+    }
+
+    // solved for directly
+    fn zap_amount(
+        &self,
+        zero_for_one: bool,
+        amount_in: u64,
+        reserve0: u64,
+        reserve1: u64,
+    ) -> Result<u64> {
+        let (reserve_in, _) = in_out(zero_for_one, reserve0, reserve1);
+        constant_product::get_zap_amount(self.fee, amount_in, reserve_in)
+    }
+
+    // on k itself, √k rounds down
+    fn invariant_holds(
+        &self,
+        balance0: u64,
+        balance1: u64,
+        reserve0: u64,
+        reserve1: u64,
+    ) -> Result<bool> {
+        Ok((balance0 as u128).safe_mul(balance1 as u128)?
+            >= (reserve0 as u128).safe_mul(reserve1 as u128)?)
+    }
+
+    // k rather than √k, as constant product pools always have
+    fn k_last(&self, reserve0: u64, reserve1: u64) -> Result<u128> {
+        (reserve0 as u128).safe_mul(reserve1 as u128)
+    }
+
+    fn protocol_fee_liquidity(
+        &self,
+        protocol_fee_share: u64,
+        k_last: u128,
+        reserve0: u64,
+        reserve1: u64,
+        lp_supply: u64,
+    ) -> Result<u64> {
+        protocol_fee_liquidity(protocol_fee_share, k_last, reserve0, reserve1, lp_supply)
+    }
+}
+
+pub struct StableSwap {
+    pub fee: u64,
+    // amplification coefficient at the time of pricing
+    pub amp: u64,
+}
+
+impl Curve for StableSwap {
+    fn swap_exact_input(
+        &self,
+        zero_for_one: bool,
+        amount_in: u64,
+        reserve0: u64,
+        reserve1: u64,
+    ) -> Result<u64> {
+        let (reserve_in, reserve_out) = in_out(zero_for_one, reserve0, reserve1);
+        stable_swap::get_amount_out(self.amp, self.fee, amount_in, reserve_in, reserve_out)
+    }
+
+    fn swap_exact_output(
+        &self,
+        zero_for_one: bool,
+        amount_out: u64,
+        reserve0: u64,
+        reserve1: u64,
+    ) -> Result<u64> {
+        let (reserve_in, reserve_out) = in_out(zero_for_one, reserve0, reserve1);
+        stable_swap::get_amount_in(self.amp, self.fee, amount_out, reserve_in, reserve_out)
+    }
+
+    // D, which a ramp of the amplification moves as well, so `Pool` recomputes D_last at the same
+    // amplification rather than keeping it
+    fn invariant(&self, reserve0: u64, reserve1: u64) -> Result<u128> {
+        stable_swap::compute_d(self.amp, reserve0, reserve1)
+    }
+
+    fn spot_prices(&self, reserve0: u64, reserve1: u64) -> Result<(u128, u128)> {
+        stable_swap::spot_prices(self.amp, reserve0, reserve1)
+    }
+
+    // on D computed once rather than by swapping
+    fn zap_amount(
+        &self,
+        zero_for_one: bool,
+        amount_in: u64,
+        reserve0: u64,
+        reserve1: u64,
+    ) -> Result<u64> {
+        let (reserve_in, reserve_out) = in_out(zero_for_one, reserve0, reserve1);
+        stable_swap::get_zap_amount(self.amp, self.fee, amount_in, reserve_in, reserve_out)
+    }
+}
+
+pub struct WeightedProduct {
+    pub fee: u64,
+    // in basis points, adding up to `BASIS_POINTS`
+    pub weight0: u64,
+    pub weight1: u64,
+}
+
+impl Curve for WeightedProduct {
+    fn swap_exact_input(
+        &self,
+        zero_for_one: bool,
+        amount_in: u64,
+        reserve0: u64,
+        reserve1: u64,
+    ) -> Result<u64> {
+        let (reserve_in, reserve_out) = in_out(zero_for_one, reserve0, reserve1);
+        let (weight_in, weight_out) = in_out(zero_for_one, self.weight0, self.weight1);
+        weighted_product::get_amount_out(
+            weight_in,
+            weight_out,
+            self.fee,
+            amount_in,
+            reserve_in,
+            reserve_out,
+        )
+    }
+
+    fn swap_exact_output(
+        &self,
+        zero_for_one: bool,
+        amount_out: u64,
+        reserve0: u64,
+        reserve1: u64,
+    ) -> Result<u64> {
+        let (reserve_in, reserve_out) = in_out(zero_for_one, reserve0, reserve1);
+        let (weight_in, weight_out) = in_out(zero_for_one, self.weight0, self.weight1);
+        weighted_product::get_amount_in(
+            weight_in,
+            weight_out,
+            self.fee,
+            amount_out,
+            reserve_in,
+            reserve_out,
+        )
+    }
+
+    // V
+    fn invariant(&self, reserve0: u64, reserve1: u64) -> Result<u128> {
+        weighted_product::compute_invariant(self.weight0, self.weight1, reserve0, reserve1)
+    }
+
+    fn spot_prices(&self, reserve0: u64, reserve1: u64) -> Result<(u128, u128)> {
+        weighted_product::spot_prices(self.weight0, self.weight1, reserve0, reserve1)
+    }
+
+    // from the closed form without a fee
+    fn zap_amount(
+        &self,
+        zero_for_one: bool,
+        amount_in: u64,
+        reserve0: u64,
+        reserve1: u64,
+    ) -> Result<u64> {
+        let (reserve_in, _) = in_out(zero_for_one, reserve0, reserve1);
+        let (weight_in, weight_out) = in_out(zero_for_one, self.weight0, self.weight1);
+        weighted_product::get_zap_amount(weight_in, weight_out, self.fee, amount_in, reserve_in)
+    }
+
+    // V is only known to within the slack of its powers
+    fn invariant_holds(
+        &self,
+        balance0: u64,
+        balance1: u64,
+        reserve0: u64,
+        reserve1: u64,
+    ) -> Result<bool> {
+        weighted_product::invariant_holds(
+            self.weight0,
+            self.weight1,
+            balance0,
+            balance1,
+            reserve0,
+            reserve1,
+        )
+    }
+}
@@ -22,4 +22,58 @@ pub enum ErrorCode {
     InsufficientLiquidity,
     #[msg("InsufficientUserBalance")]
     InsufficientUserBalance,
+    #[msg("InvalidObservation")]
+    InvalidObservation,
+    #[msg("ObservationTooOld")]
+    ObservationTooOld,
+    #[msg("InvalidObservationCardinality")]
+    InvalidObservationCardinality,
+    #[msg("InvariantViolated")]
+    InvariantViolated,
+    #[msg("InvalidRoute")]
+    InvalidRoute,
+    #[msg("UnsupportedMint")]
+    UnsupportedMint,
+    #[msg("TransferFeeCalculationFailed")]
+    TransferFeeCalculationFailed,
+    #[msg("FeeTierNotEnabled")]
+    FeeTierNotEnabled,
+    #[msg("FeeTierAlreadyEnabled")]
+    FeeTierAlreadyEnabled,
+    #[msg("TooManyFeeTiers")]
+    TooManyFeeTiers,
+    #[msg("InvalidProtocolFeeShare")]
+    InvalidProtocolFeeShare,
+    #[msg("Expired")]
+    Expired,
+    #[msg("PoolCreationRestricted")]
+    PoolCreationRestricted,
+    #[msg("MissingCreationFeeAccount")]
+    MissingCreationFeeAccount,
+    #[msg("Paused")]
+    Paused,
+    #[msg("InvalidSwapDirection")]
+    InvalidSwapDirection,
+    #[msg("InvalidVault")]
+    InvalidVault,
+    #[msg("InvalidUserTokenAccount")]
+    InvalidUserTokenAccount,
+    #[msg("InvalidMint")]
+    InvalidMint,
+    #[msg("MathOverflow")]
+    MathOverflow,
+    #[msg("DivisionByZero")]
+    DivisionByZero,
+    #[msg("InvalidCurve")]
+    InvalidCurve,
+    #[msg("InvalidAmplification")]
+    InvalidAmplification,
+    #[msg("InvalidAmplificationRamp")]
+    InvalidAmplificationRamp,
+    #[msg("InvariantNotConverged")]
+    InvariantNotConverged,
+    #[msg("InvalidWeights")]
+    InvalidWeights,
+    #[msg("InvalidDecimals")]
+    InvalidDecimals,
 }
@@ -0,0 +1,82 @@
+use crate::state::CurveType;
+use anchor_lang::prelude::*;
+
+// amounts and reserves are in token0/token1 order unless named otherwise, reserves are the ones
+// left after the instruction
+
+#[event]
+pub struct PoolCreated {
+    pub pool: Pubkey,
+    pub creator: Pubkey,
+    pub token0: Pubkey,
+    pub token1: Pubkey,
+    pub fee: u64,
+    pub curve_type: CurveType,
+}
+
+#[event]
+pub struct Swap {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub mint_in: Pubkey,
+    pub mint_out: Pubkey,
+    // amounts as they arrive in and leave the vaults
+    pub amount_in: u64,
+    pub amount_out: u64,
+    // part of `amount_in` kept by the pool as the swap fee
+    pub fee_amount: u64,
+    pub reserve0: u64,
+    pub reserve1: u64,
+}
+
+#[event]
+pub struct Mint {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub amount0: u64,
+    pub amount1: u64,
+    pub liquidity: u64,
+    pub reserve0: u64,
+    pub reserve1: u64,
+}
+
+#[event]
+pub struct Burn {
+    pub pool: Pubkey,
+    pub user: Pubkey,
+    pub amount0: u64,
+    pub amount1: u64,
+    pub liquidity: u64,
+    pub reserve0: u64,
+    pub reserve1: u64,
+}
+
+#[event]
+pub struct Sync {
+    pub pool: Pubkey,
+    pub reserve0: u64,
+    pub reserve1: u64,
+}
+
+// `pool` is set when one pool's fee is changed, unset when a fee tier is enabled or disabled
+#[event]
+pub struct FeeChanged {
+    pub pool: Option<Pubkey>,
+    pub fee: u64,
+    pub enabled: bool,
+}
+
+#[event]
+pub struct FeeToChanged {
+    pub old_fee_to: Pubkey,
+    pub new_fee_to: Pubkey,
+}
+
+#[event]
+pub struct AmpRamped {
+    pub pool: Pubkey,
+    pub initial_amp: u64,
+    pub future_amp: u64,
+    pub initial_amp_time: i64,
+    pub future_amp_time: i64,
+}
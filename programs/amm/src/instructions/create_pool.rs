@@ -1,20 +1,37 @@
+use crate::error::ErrorCode;
+use crate::events::PoolCreated;
 use crate::state::Config;
-use crate::state::Pool;
+use crate::state::FeeTiers;
+use crate::state::Observations;
+use crate::state::ObservationsAccount;
+use crate::state::{CurveParams, Pool};
+use crate::utils::{is_supported_mint, vault_address};
 use anchor_lang::prelude::*;
+use anchor_lang::system_program::{transfer, Transfer};
 use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::token::{Mint, Token, TokenAccount};
+use anchor_spl::token::Token;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
 
 #[derive(Accounts)]
+#[instruction(fee: u64, curve: CurveParams)]
 pub struct CreatePool<'info> {
-    pub mint0: Account<'info, Mint>,
-    pub mint1: Account<'info, Mint>,
+    #[account(mint::token_program = token_program0)]
+    pub mint0: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mint::token_program = token_program1)]
+    pub mint1: Box<InterfaceAccount<'info, Mint>>,
 
     #[account(mut)]
-    pub owner: Signer<'info>,
-    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = owner)]
-    pub config: Account<'info, Config>,
+    pub payer: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+    #[account(seeds = [b"fee_tiers"], bump = fee_tiers.bump)]
+    pub fee_tiers: Account<'info, FeeTiers>,
 
-    #[account(init, seeds= [b"pool", mint0.key().as_ref(), mint1.key().as_ref()], bump, payer = owner, space = 8 + Pool::INIT_SPACE)]
+    // one pool per mint pair, fee tier and curve, the seed is spelled as a call so that the IDL leaves
+    // the address to clients instead of describing it as derived from the encoded `curve` argument
+    #[account(init, seeds= [b"pool", mint0.key().as_ref(), mint1.key().as_ref(), &fee.to_le_bytes(), &CurveParams::seed(&curve)], bump, payer = payer, space = 8 + Pool::INIT_SPACE)]
     pub pool: Box<Account<'info, Pool>>,
 
     /// CHECK: authority so 1 acc pass in can derive all other pdas
@@ -24,27 +41,156 @@ pub struct CreatePool<'info> {
     // Required to create the associated token accounts before the pool is created
     // to avoid stack issues.
     #[account(
-        associated_token::mint = mint0,
-        associated_token::authority = pool_authority
+        constraint = vault0.key() == vault_address(&pool_authority.key(), &mint0.key(), &token_program0.key()) @ ErrorCode::InvalidVault,
     )]
-    pub vault0: Box<Account<'info, TokenAccount>>,
+    pub vault0: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
-        associated_token::mint = mint1,
-        associated_token::authority = pool_authority
+        constraint = vault1.key() == vault_address(&pool_authority.key(), &mint1.key(), &token_program1.key()) @ ErrorCode::InvalidVault,
     )]
-    pub vault1: Box<Account<'info, TokenAccount>>,
+    pub vault1: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    #[account(init, payer = owner, seeds = [b"lp_mint", pool.key().as_ref()], bump, mint::decimals = 6, mint::authority = pool_authority)]
-    pub lp_mint: Box<Account<'info, Mint>>,
+    #[account(init, payer = payer, seeds = [b"lp_mint", pool.key().as_ref()], bump, mint::decimals = 6, mint::authority = pool_authority)]
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(init, payer = payer, seeds = [b"observations", pool.key().as_ref()], bump, space = Observations::space(1))]
+    pub observations: AccountLoader<'info, Observations>,
+
+    // creation fee accounts, only needed when a fee is charged: `fee_to` for a fee in lamports, the
+    // rest for a fee in tokens
+    /// CHECK: only receives lamports
+    #[account(mut, constraint = fee_to.key() == config.fee_to @ anchor_lang::error::ErrorCode::ConstraintAddress)]
+    pub fee_to: Option<AccountInfo<'info>>,
+    #[account(constraint = fee_mint.key() == config.pool_creation_fee_mint @ ErrorCode::InvalidMint, mint::token_program = fee_token_program)]
+    pub fee_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    #[account(mut, token::mint = config.pool_creation_fee_mint, token::authority = payer)]
+    pub payer_fee_ata: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    #[account(mut, token::mint = config.pool_creation_fee_mint, token::authority = config.fee_to)]
+    pub fee_to_ata: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+    pub fee_token_program: Option<Interface<'info, TokenInterface>>,
 
     pub associated_token_program: Program<'info, AssociatedToken>,
+    // the LP mint always lives under the original token program
     pub token_program: Program<'info, Token>,
+    pub token_program0: Interface<'info, TokenInterface>,
+    pub token_program1: Interface<'info, TokenInterface>,
     pub rent: Sysvar<'info, Rent>,
     pub system_program: Program<'info, System>,
 }
 
-pub fn create_pool(ctx: Context<CreatePool>) -> Result<()> {
+pub fn create_pool(ctx: Context<CreatePool>, fee: u64, curve: CurveParams) -> Result<()> {
+    let config = &ctx.accounts.config;
+    let is_owner = ctx.accounts.payer.key() == config.owner;
+    require!(
+        is_owner || !config.pool_creation_restricted,
+        ErrorCode::PoolCreationRestricted
+    );
+    // like the protocol fee, the creation fee is off while `fee_to` is unset
+    if !is_owner && config.pool_creation_fee > 0 && config.fee_to != Pubkey::default() {
+        charge_creation_fee(&ctx)?;
+    }
+
+    require!(
+        ctx.accounts.fee_tiers.is_enabled(fee),
+        ErrorCode::FeeTierNotEnabled
+    );
+    require!(
+        is_supported_mint(&ctx.accounts.mint0)? && is_supported_mint(&ctx.accounts.mint1)?,
+        ErrorCode::UnsupportedMint
+    );
+    // StableSwap prices raw amounts one for one at balance, the pegged tokens must share decimals
+    require!(
+        !matches!(curve, CurveParams::Stable { .. })
+            || ctx.accounts.mint0.decimals == ctx.accounts.mint1.decimals,
+        ErrorCode::InvalidDecimals
+    );
+
+    let timestamp = Clock::get()?.unix_timestamp;
     let pool = &mut ctx.accounts.pool;
+    pool.initialize(
+        ctx.accounts.mint0.key(),
+        ctx.accounts.mint1.key(),
+        fee,
+        curve,
+        timestamp,
+    )?;
+
+    ctx.accounts.observations.initialize(
+        ctx.bumps.observations,
+        ctx.accounts.pool.key(),
+        timestamp,
+    )?;
+
+    emit!(PoolCreated {
+        pool: ctx.accounts.pool.key(),
+        creator: ctx.accounts.payer.key(),
+        token0: ctx.accounts.mint0.key(),
+        token1: ctx.accounts.mint1.key(),
+        fee,
+        curve_type: ctx.accounts.pool.curve_type,
+    });
+
+    Ok(())
+}
+
+fn charge_creation_fee(ctx: &Context<CreatePool>) -> Result<()> {
+    let config = &ctx.accounts.config;
+    if config.pool_creation_fee_mint == Pubkey::default() {
+        let fee_to = ctx
+            .accounts
+            .fee_to
+            .as_ref()
+            .ok_or(ErrorCode::MissingCreationFeeAccount)?;
+        return transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: fee_to.to_account_info(),
+                },
+            ),
+            config.pool_creation_fee,
+        );
+    }
+
+    let (Some(fee_mint), Some(payer_fee_ata), Some(fee_to_ata), Some(fee_token_program)) = (
+        ctx.accounts.fee_mint.as_ref(),
+        ctx.accounts.payer_fee_ata.as_ref(),
+        ctx.accounts.fee_to_ata.as_ref(),
+        ctx.accounts.fee_token_program.as_ref(),
+    ) else {
+        return err!(ErrorCode::MissingCreationFeeAccount);
+    };
+    transfer_checked(
+        CpiContext::new(
+            fee_token_program.to_account_info(),
+            TransferChecked {
+                from: payer_fee_ata.to_account_info(),
+                mint: fee_mint.to_account_info(),
+                to: fee_to_ata.to_account_info(),
+                authority: ctx.accounts.payer.to_account_info(),
+            },
+        ),
+        config.pool_creation_fee,
+        fee_mint.decimals,
+    )
+}
+
+#[derive(Accounts)]
+pub struct SetPoolCreation<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = owner)]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn set_pool_creation_restricted(ctx: Context<SetPoolCreation>, restricted: bool) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.set_pool_creation_restricted(restricted)
+}
 
-    Ok(pool.initialize(ctx.accounts.mint0.key(), ctx.accounts.mint1.key())?)
+// `mint` is the default pubkey for a fee in lamports
+pub fn set_pool_creation_fee(ctx: Context<SetPoolCreation>, mint: Pubkey, fee: u64) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.set_pool_creation_fee(mint, fee)
 }
@@ -0,0 +1,191 @@
+use crate::constant::BASIS_POINTS;
+use crate::error::ErrorCode;
+use crate::math::{to_u64, CheckedMath};
+use crate::state::Config;
+use crate::state::Observations;
+use crate::state::ObservationsAccount;
+use crate::state::Pool;
+use crate::utils::{check_not_paused, vault_address};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::invoke;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+#[derive(Accounts)]
+pub struct FlashSwap<'info> {
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+
+    pub owner: Signer<'info>,
+    // receivers of the borrowed tokens, not necessarily owned by the signer
+    #[account(mut, constraint = receiver0.mint == pool.token0 @ ErrorCode::InvalidUserTokenAccount)]
+    pub receiver0: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, constraint = receiver1.mint == pool.token1 @ ErrorCode::InvalidUserTokenAccount)]
+    pub receiver1: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub pool: Box<Account<'info, Pool>>,
+    /// CHECK: authority so 1 acc pass in can derive all other pdas
+    #[account(seeds=[b"authority", pool.key().as_ref()], bump)]
+    pub pool_authority: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = vault0.key() == vault_address(&pool_authority.key(), &pool.token0, &token_program0.key()) @ ErrorCode::InvalidVault,
+    )]
+    pub vault0: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = vault1.key() == vault_address(&pool_authority.key(), &pool.token1, &token_program1.key()) @ ErrorCode::InvalidVault,
+    )]
+    pub vault1: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, seeds = [b"observations", pool.key().as_ref()], bump = observations.load()?.bump)]
+    pub observations: AccountLoader<'info, Observations>,
+    #[account(constraint = mint0.key() == pool.token0 @ ErrorCode::InvalidMint, mint::token_program = token_program0)]
+    pub mint0: Box<InterfaceAccount<'info, Mint>>,
+    #[account(constraint = mint1.key() == pool.token1 @ ErrorCode::InvalidMint, mint::token_program = token_program1)]
+    pub mint1: Box<InterfaceAccount<'info, Mint>>,
+
+    /// CHECK: arbitrary program invoked with `data` and the remaining accounts once the tokens are
+    /// sent, it must pay the vaults back before returning
+    #[account(executable)]
+    pub callback_program: AccountInfo<'info>,
+
+    pub token_program0: Interface<'info, TokenInterface>,
+    pub token_program1: Interface<'info, TokenInterface>,
+}
+
+// Sends the requested amounts to the receivers first, then hands control to the callback program.
+// The pool's invariant, net of `Pool.fee` on whatever was paid in, must hold on the reserves once
+// the callback returns. The runtime does not allow the callback to re-enter this program.
+pub fn flash_swap<'info>(
+    ctx: Context<'_, '_, '_, 'info, FlashSwap<'info>>,
+    amount0_out: u64,
+    amount1_out: u64,
+    data: Vec<u8>,
+) -> Result<()> {
+    check_not_paused(&ctx.accounts.config, &ctx.accounts.pool)?;
+    require!(
+        amount0_out > 0 || amount1_out > 0,
+        ErrorCode::InsufficientOutputAmount
+    );
+    let pool: &Account<Pool> = &ctx.accounts.pool;
+    let (reserve0, reserve1) = (pool.reserve0, pool.reserve1);
+    require!(
+        amount0_out < reserve0 && amount1_out < reserve1,
+        ErrorCode::InsufficientLiquidity
+    );
+    // tokens already sitting in the vaults beyond the reserves do not count as repayment, and since
+    // repayment is measured on the vaults any transfer fee is already excluded from it
+    let (vault0_before, vault1_before) = (ctx.accounts.vault0.amount, ctx.accounts.vault1.amount);
+
+    let pool_key = pool.key();
+    let pool_sign = &[b"authority", pool_key.as_ref(), &[ctx.bumps.pool_authority]];
+
+    // optimistically transfer tokens from vaults to receivers
+    if amount0_out > 0 {
+        transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program0.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault0.to_account_info(),
+                    mint: ctx.accounts.mint0.to_account_info(),
+                    to: ctx.accounts.receiver0.to_account_info(),
+                    authority: ctx.accounts.pool_authority.to_account_info(),
+                },
+            )
+            .with_signer(&[pool_sign]),
+            amount0_out,
+            ctx.accounts.mint0.decimals,
+        )?;
+    }
+    if amount1_out > 0 {
+        transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program1.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault1.to_account_info(),
+                    mint: ctx.accounts.mint1.to_account_info(),
+                    to: ctx.accounts.receiver1.to_account_info(),
+                    authority: ctx.accounts.pool_authority.to_account_info(),
+                },
+            )
+            .with_signer(&[pool_sign]),
+            amount1_out,
+            ctx.accounts.mint1.decimals,
+        )?;
+    }
+
+    // hand over to the callback, signer privileges of the remaining accounts are forwarded
+    let callback_accounts = ctx
+        .remaining_accounts
+        .iter()
+        .map(|account| {
+            if account.is_writable {
+                AccountMeta::new(account.key(), account.is_signer)
+            } else {
+                AccountMeta::new_readonly(account.key(), account.is_signer)
+            }
+        })
+        .collect();
+    let mut callback_infos = ctx.remaining_accounts.to_vec();
+    callback_infos.push(ctx.accounts.callback_program.to_account_info());
+    invoke(
+        &Instruction {
+            program_id: ctx.accounts.callback_program.key(),
+            accounts: callback_accounts,
+            data,
+        },
+        &callback_infos,
+    )?;
+
+    ctx.accounts.vault0.reload()?;
+    ctx.accounts.vault1.reload()?;
+    let amount0_in = ctx
+        .accounts
+        .vault0
+        .amount
+        .saturating_sub(vault0_before.safe_sub(amount0_out)?);
+    let amount1_in = ctx
+        .accounts
+        .vault1
+        .amount
+        .saturating_sub(vault1_before.safe_sub(amount1_out)?);
+    require!(
+        amount0_in > 0 || amount1_in > 0,
+        ErrorCode::InsufficientInputAmount
+    );
+    let balance0 = reserve0.safe_sub(amount0_out)?.safe_add(amount0_in)?;
+    let balance1 = reserve1.safe_sub(amount1_out)?.safe_add(amount1_in)?;
+
+    // the fee on each side is rounded up so rounding never favours the borrower
+    let fee = pool.fee as u128;
+    let balance0_adjusted = (balance0 as u128).safe_sub(
+        (amount0_in as u128)
+            .safe_mul(fee)?
+            .div_ceil(BASIS_POINTS as u128),
+    )?;
+    let balance1_adjusted = (balance1 as u128).safe_sub(
+        (amount1_in as u128)
+            .safe_mul(fee)?
+            .div_ceil(BASIS_POINTS as u128),
+    )?;
+    require!(
+        pool.curve()?.invariant_holds(
+            to_u64(balance0_adjusted)?,
+            to_u64(balance1_adjusted)?,
+            reserve0,
+            reserve1,
+        )?,
+        ErrorCode::InvariantViolated
+    );
+
+    let pool: &mut Account<Pool> = &mut ctx.accounts.pool;
+    let timestamp = Clock::get()?.unix_timestamp;
+    ctx.accounts.observations.write(timestamp, pool)?;
+    pool.update(balance0, balance1, timestamp)?;
+
+    Ok(())
+}
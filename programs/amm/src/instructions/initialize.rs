@@ -1,4 +1,5 @@
 use crate::state::config::Config;
+use crate::state::FeeTiers;
 use anchor_lang::prelude::*;
 
 #[derive(Accounts)]
@@ -7,12 +8,18 @@ pub struct Initialize<'info> {
     pub owner: Signer<'info>,
     #[account(init, payer = owner, seeds = [b"config"], bump, space = 8 + Config::INIT_SPACE)]
     pub config: Account<'info, Config>,
+    #[account(init, payer = owner, seeds = [b"fee_tiers"], bump, space = 8 + FeeTiers::INIT_SPACE)]
+    pub fee_tiers: Account<'info, FeeTiers>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
 }
 
+// `fee` is enabled as the first fee tier
 pub fn initialize(ctx: Context<Initialize>, fee_to: Pubkey, fee: u64) -> Result<()> {
     let config = &mut ctx.accounts.config;
+    config.initialize(ctx.bumps.config, *ctx.accounts.owner.key, fee_to)?;
 
-    Ok(config.initialize(ctx.bumps.config, *ctx.accounts.owner.key, fee_to, fee)?)
+    let fee_tiers = &mut ctx.accounts.fee_tiers;
+    fee_tiers.initialize(ctx.bumps.fee_tiers);
+    fee_tiers.enable(fee)
 }
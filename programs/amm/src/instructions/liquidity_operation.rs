@@ -1,14 +1,23 @@
+use crate::constant::MINIMUM_LIQUIDITY;
+use crate::constant_product::calculate_liquidity_amounts;
 use crate::error::ErrorCode;
+use crate::events;
 use crate::instructions::mint_fee;
+use crate::math::CheckedMath;
 use crate::state::Config;
+use crate::state::Observations;
+use crate::state::ObservationsAccount;
 use crate::state::Pool;
+use crate::utils::{
+    check_deadline, check_not_paused, inverse_transfer_fee, transfer_fee, vault_address,
+};
 use anchor_lang::prelude::*;
 use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::token::{
-    burn, mint_to, transfer, Burn, Mint, MintTo, Token, TokenAccount, Transfer,
+use anchor_spl::token::Token;
+use anchor_spl::token_interface::{
+    burn, mint_to, transfer_checked, Burn, Mint, MintTo, TokenAccount, TokenInterface,
+    TransferChecked,
 };
-use fixed::types::U128F0;
-use std::cmp::min;
 
 #[derive(Accounts)]
 pub struct LiquidityOperation<'info> {
@@ -20,20 +29,23 @@ pub struct LiquidityOperation<'info> {
     #[account(
         mut,
         has_one = owner,
+        constraint = user_ata0.mint == pool.token0 @ ErrorCode::InvalidUserTokenAccount,
     )]
-    pub user_ata0: Box<Account<'info, TokenAccount>>,
+    pub user_ata0: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
         has_one = owner,
+        constraint = user_ata1.mint == pool.token1 @ ErrorCode::InvalidUserTokenAccount,
     )]
-    pub user_ata1: Box<Account<'info, TokenAccount>>,
+    pub user_ata1: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         init_if_needed,
         payer = owner,
         associated_token::mint = lp_mint,
-        associated_token::authority = owner
+        associated_token::authority = owner,
+        associated_token::token_program = token_program
     )]
-    pub user_lp_ata: Box<Account<'info, TokenAccount>>,
+    pub user_lp_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut)]
     pub pool: Box<Account<'info, Pool>>,
@@ -41,28 +53,39 @@ pub struct LiquidityOperation<'info> {
     #[account(seeds=[b"authority", pool.key().as_ref()], bump)]
     pub pool_authority: AccountInfo<'info>,
 
+    // never transferred out of, holds the locked MINIMUM_LIQUIDITY
     #[account(
         init_if_needed,
         payer = owner,
         associated_token::mint = lp_mint,
-        associated_token::authority = pool_authority
+        associated_token::authority = pool_authority,
+        associated_token::token_program = token_program
     )]
-    pub vault_lp: Box<Account<'info, TokenAccount>>,
+    pub vault_lp: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
-        constraint = vault0.mint == user_ata0.mint,
+        constraint = vault0.key() == vault_address(&pool_authority.key(), &pool.token0, &token_program0.key()) @ ErrorCode::InvalidVault,
     )]
-    pub vault0: Box<Account<'info, TokenAccount>>,
+    pub vault0: Box<InterfaceAccount<'info, TokenAccount>>,
     #[account(
         mut,
-        constraint = vault1.mint == user_ata1.mint,
+        constraint = vault1.key() == vault_address(&pool_authority.key(), &pool.token1, &token_program1.key()) @ ErrorCode::InvalidVault,
     )]
-    pub vault1: Box<Account<'info, TokenAccount>>,
+    pub vault1: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(constraint = mint0.key() == pool.token0 @ ErrorCode::InvalidMint, mint::token_program = token_program0)]
+    pub mint0: Box<InterfaceAccount<'info, Mint>>,
+    #[account(constraint = mint1.key() == pool.token1 @ ErrorCode::InvalidMint, mint::token_program = token_program1)]
+    pub mint1: Box<InterfaceAccount<'info, Mint>>,
     #[account(mut, seeds = [b"lp_mint", pool.key().as_ref()], bump)]
-    pub lp_mint: Box<Account<'info, Mint>>,
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut, seeds = [b"observations", pool.key().as_ref()], bump = observations.load()?.bump)]
+    pub observations: AccountLoader<'info, Observations>,
 
     pub associated_token_program: Program<'info, AssociatedToken>,
+    // the LP mint always lives under the original token program
     pub token_program: Program<'info, Token>,
+    pub token_program0: Interface<'info, TokenInterface>,
+    pub token_program1: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
@@ -72,15 +95,20 @@ pub fn add_liquidity(
     amount1_desired: u64,
     amount0_min: u64,
     amount1_min: u64,
+    deadline: Option<i64>,
 ) -> Result<()> {
-    let pool: &Box<Account<Pool>> = &ctx.accounts.pool;
-    let (reserve0, reserve1) = (ctx.accounts.vault0.amount, ctx.accounts.vault1.amount);
+    check_deadline(deadline)?;
+    check_not_paused(&ctx.accounts.config, &ctx.accounts.pool)?;
+
+    let pool: &Account<Pool> = &ctx.accounts.pool;
+    let (reserve0, reserve1) = (pool.reserve0, pool.reserve1);
 
+    // amounts are priced on what arrives in the vaults, net of any transfer fee
     let (amount0, amount1) = calculate_liquidity_amounts(
         reserve0,
         reserve1,
-        amount0_desired,
-        amount1_desired,
+        amount0_desired.safe_sub(transfer_fee(&ctx.accounts.mint0, amount0_desired)?)?,
+        amount1_desired.safe_sub(transfer_fee(&ctx.accounts.mint1, amount1_desired)?)?,
         amount0_min,
         amount1_min,
     )?;
@@ -96,30 +124,36 @@ pub fn add_liquidity(
             authority: ctx.accounts.pool_authority.to_account_info(),
         },
     );
-    mint_fee(
+    let fee_on = mint_fee(
         &ctx.accounts.config,
-        &ctx.accounts.pool,
+        &mut ctx.accounts.pool,
         reserve0,
         reserve1,
         ctx.accounts.lp_mint.supply,
         mint_fee_ctx.with_signer(&[pool_sign]),
     )?;
-
-    let lp_mint = &ctx.accounts.lp_mint;
-    let liquidity: u64;
-    if lp_mint.supply == 0 {
-        liquidity = U128F0::from_num((amount0 as u128) * (amount1 as u128))
-            .sqrt()
-            .to_num::<u64>();
-    } else {
-        liquidity = min(
-            amount0 as u128 * lp_mint.supply as u128 / reserve0 as u128,
-            amount1 as u128 * lp_mint.supply as u128 / reserve1 as u128,
-        ) as u64
+    // the supply the new liquidity is priced against includes the fee just minted
+    ctx.accounts.lp_mint.reload()?;
+
+    let lp_supply = ctx.accounts.lp_mint.supply;
+    let liquidity = ctx
+        .accounts
+        .pool
+        .curve()?
+        .deposit(amount0, amount1, reserve0, reserve1, lp_supply)?;
+    if lp_supply == 0 {
+        // permanently lock the first MINIMUM_LIQUIDITY tokens
+        let lock_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                to: ctx.accounts.vault_lp.to_account_info(),
+                mint: ctx.accounts.lp_mint.to_account_info(),
+                authority: ctx.accounts.pool_authority.to_account_info(),
+            },
+        );
+        mint_to(lock_ctx.with_signer(&[pool_sign]), MINIMUM_LIQUIDITY)?;
     }
 
-    require!(liquidity > 0, ErrorCode::InsufficientLiquidityMinted);
-
     let mint_ctx = CpiContext::new(
         ctx.accounts.token_program.to_account_info(),
         MintTo {
@@ -130,87 +164,55 @@ pub fn add_liquidity(
     );
     mint_to(mint_ctx.with_signer(&[pool_sign]), liquidity)?;
 
-    // transfer tokens from user to vault
-    transfer(
+    // transfer tokens from user to vault, grossed up so that amount0 and amount1 arrive
+    transfer_checked(
         CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
+            ctx.accounts.token_program0.to_account_info(),
+            TransferChecked {
                 from: ctx.accounts.user_ata0.to_account_info(),
+                mint: ctx.accounts.mint0.to_account_info(),
                 to: ctx.accounts.vault0.to_account_info(),
                 authority: ctx.accounts.owner.to_account_info(),
             },
         ),
-        amount0,
+        amount0.safe_add(inverse_transfer_fee(&ctx.accounts.mint0, amount0)?)?,
+        ctx.accounts.mint0.decimals,
     )?;
 
-    transfer(
+    transfer_checked(
         CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
+            ctx.accounts.token_program1.to_account_info(),
+            TransferChecked {
                 from: ctx.accounts.user_ata1.to_account_info(),
+                mint: ctx.accounts.mint1.to_account_info(),
                 to: ctx.accounts.vault1.to_account_info(),
                 authority: ctx.accounts.owner.to_account_info(),
             },
         ),
-        amount1,
+        amount1.safe_add(inverse_transfer_fee(&ctx.accounts.mint1, amount1)?)?,
+        ctx.accounts.mint1.decimals,
     )?;
 
-    let pool: &mut Box<Account<Pool>> = &mut ctx.accounts.pool;
-    ctx.accounts.vault0.reload()?;
-    ctx.accounts.vault1.reload()?;
-    let (reserve0, reserve1) = (ctx.accounts.vault0.amount, ctx.accounts.vault1.amount);
-
-    pool.update_k_last(reserve0, reserve1);
-
-    Ok(())
-}
-
-fn calculate_liquidity_amounts(
-    reserve0: u64,
-    reserve1: u64,
-    amount0_desired: u64,
-    amount1_desired: u64,
-    amount0_min: u64,
-    amount1_min: u64,
-) -> Result<(u64, u64)> {
-    let amount0: u64;
-    let amount1: u64;
-    if reserve0 == 0 && reserve1 == 0 {
-        (amount0, amount1) = (amount0_desired, amount1_desired);
-    } else {
-        let amount1_optimal = quote(amount0_desired, reserve0, reserve1)?;
-        if amount1_optimal <= amount1_desired {
-            require!(
-                amount1_optimal >= amount1_min,
-                ErrorCode::InsufficientAmount
-            );
-            (amount0, amount1) = (amount0_desired, amount1_optimal);
-        } else {
-            let amount0_optimal = quote(amount1_desired, reserve1, reserve0)?;
-            require!(
-                amount0_optimal <= amount0_desired,
-                ErrorCode::InsufficientAmount
-            );
-            require!(
-                amount0_optimal >= amount0_min,
-                ErrorCode::InsufficientAmount
-            );
-            (amount0, amount1) = (amount0_optimal, amount1_desired);
-        }
+    let pool: &mut Account<Pool> = &mut ctx.accounts.pool;
+    let (reserve0, reserve1) = (reserve0.safe_add(amount0)?, reserve1.safe_add(amount1)?);
+    let timestamp = Clock::get()?.unix_timestamp;
+    ctx.accounts.observations.write(timestamp, pool)?;
+    pool.update(reserve0, reserve1, timestamp)?;
+    if fee_on {
+        pool.update_k_last(reserve0, reserve1)?;
     }
 
-    Ok((amount0, amount1))
-}
-
-// given some amount of an asset and pair reserves, returns an equivalent amount of the other asset
-fn quote(amount0: u64, reserve0: u64, reserve1: u64) -> Result<u64> {
-    require!(amount0 > 0, ErrorCode::InsufficientAmount);
-    require!(
-        reserve0 > 0 && reserve1 > 0,
-        ErrorCode::InsufficientReserves
-    );
+    emit!(events::Mint {
+        pool: pool.key(),
+        user: ctx.accounts.owner.key(),
+        amount0,
+        amount1,
+        liquidity,
+        reserve0,
+        reserve1,
+    });
 
-    Ok((amount0 as u128 * reserve1 as u128 / reserve0 as u128) as u64)
+    Ok(())
 }
 
 pub fn remove_liquidity(
@@ -218,9 +220,12 @@ pub fn remove_liquidity(
     liquidity: u64,
     amount0_min: u64,
     amount1_min: u64,
+    deadline: Option<i64>,
 ) -> Result<()> {
-    let pool: &Box<Account<Pool>> = &ctx.accounts.pool;
-    let (reserve0, reserve1) = (ctx.accounts.vault0.amount, ctx.accounts.vault1.amount);
+    check_deadline(deadline)?;
+
+    let pool: &Account<Pool> = &ctx.accounts.pool;
+    let (reserve0, reserve1) = (pool.reserve0, pool.reserve1);
 
     let pool_key = pool.key();
     let pool_sign = &[b"authority", pool_key.as_ref(), &[ctx.bumps.pool_authority]];
@@ -233,23 +238,30 @@ pub fn remove_liquidity(
             authority: ctx.accounts.pool_authority.to_account_info(),
         },
     );
-    mint_fee(
+    let fee_on = mint_fee(
         &ctx.accounts.config,
-        &ctx.accounts.pool,
+        &mut ctx.accounts.pool,
         reserve0,
         reserve1,
         ctx.accounts.lp_mint.supply,
         mint_fee_ctx.with_signer(&[pool_sign]),
     )?;
+    // the supply the liquidity is redeemed against includes the fee just minted
+    ctx.accounts.lp_mint.reload()?;
 
-    let (amount0, amount1) = calculate_removed_amounts(
+    let (amount0, amount1) = ctx.accounts.pool.curve()?.withdraw(
         liquidity,
         ctx.accounts.lp_mint.supply,
         reserve0,
         reserve1,
-        amount0_min,
-        amount1_min,
     )?;
+    // the minimums apply to what reaches the user, after any transfer fee
+    let received0 = amount0.safe_sub(transfer_fee(&ctx.accounts.mint0, amount0)?)?;
+    let received1 = amount1.safe_sub(transfer_fee(&ctx.accounts.mint1, amount1)?)?;
+    require!(
+        received0 >= amount0_min && received1 >= amount1_min,
+        ErrorCode::InsufficientAmount
+    );
 
     // burn liquidity tokens
     let burn_ctx = CpiContext::new(
@@ -263,62 +275,54 @@ pub fn remove_liquidity(
     burn(burn_ctx.with_signer(&[pool_sign]), liquidity)?;
 
     // transfer tokens from vault to user
-    transfer(
+    transfer_checked(
         CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
+            ctx.accounts.token_program0.to_account_info(),
+            TransferChecked {
                 from: ctx.accounts.vault0.to_account_info(),
+                mint: ctx.accounts.mint0.to_account_info(),
                 to: ctx.accounts.user_ata0.to_account_info(),
                 authority: ctx.accounts.pool_authority.to_account_info(),
             },
         )
         .with_signer(&[pool_sign]),
         amount0,
+        ctx.accounts.mint0.decimals,
     )?;
 
-    transfer(
+    transfer_checked(
         CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
+            ctx.accounts.token_program1.to_account_info(),
+            TransferChecked {
                 from: ctx.accounts.vault1.to_account_info(),
+                mint: ctx.accounts.mint1.to_account_info(),
                 to: ctx.accounts.user_ata1.to_account_info(),
                 authority: ctx.accounts.pool_authority.to_account_info(),
             },
         )
         .with_signer(&[pool_sign]),
         amount1,
+        ctx.accounts.mint1.decimals,
     )?;
 
-    let pool: &mut Box<Account<Pool>> = &mut ctx.accounts.pool;
-    ctx.accounts.vault0.reload()?;
-    ctx.accounts.vault1.reload()?;
-    let (reserve0, reserve1) = (ctx.accounts.vault0.amount, ctx.accounts.vault1.amount);
+    let pool: &mut Account<Pool> = &mut ctx.accounts.pool;
+    let (reserve0, reserve1) = (reserve0.safe_sub(amount0)?, reserve1.safe_sub(amount1)?);
+    let timestamp = Clock::get()?.unix_timestamp;
+    ctx.accounts.observations.write(timestamp, pool)?;
+    pool.update(reserve0, reserve1, timestamp)?;
+    if fee_on {
+        pool.update_k_last(reserve0, reserve1)?;
+    }
 
-    pool.update_k_last(reserve0, reserve1);
+    emit!(events::Burn {
+        pool: pool.key(),
+        user: ctx.accounts.owner.key(),
+        amount0,
+        amount1,
+        liquidity,
+        reserve0,
+        reserve1,
+    });
 
     Ok(())
 }
-
-fn calculate_removed_amounts(
-    liquidity: u64,
-    lp_supply: u64,
-    reserve0: u64,
-    reserve1: u64,
-    amount0_min: u64,
-    amount1_min: u64,
-) -> Result<(u64, u64)> {
-    let amount0: u64 = (liquidity as u128 * reserve0 as u128 / lp_supply as u128) as u64;
-    let amount1: u64 = (liquidity as u128 * reserve1 as u128 / lp_supply as u128) as u64;
-
-    require!(
-        amount0 >= amount0_min && amount1 >= amount1_min,
-        ErrorCode::InsufficientAmount
-    );
-
-    require!(
-        amount0 != 0 && amount1 != 0,
-        ErrorCode::InsufficientLiquidityBurned
-    );
-
-    Ok((amount0, amount1))
-}
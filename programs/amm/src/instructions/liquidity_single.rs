@@ -0,0 +1,378 @@
+use crate::error::ErrorCode;
+use crate::events;
+use crate::instructions::mint_fee;
+use crate::math::{swap_fee, CheckedMath};
+use crate::state::Config;
+use crate::state::Observations;
+use crate::state::ObservationsAccount;
+use crate::state::Pool;
+use crate::utils::{check_deadline, check_not_paused, transfer_fee, vault_address};
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::token::Token;
+use anchor_spl::token_interface::{
+    burn, mint_to, transfer_checked, Burn, Mint, MintTo, TokenAccount, TokenInterface,
+    TransferChecked,
+};
+
+// as `LiquidityOperation`, with the user holding only one of the pool's tokens
+#[derive(Accounts)]
+pub struct SingleLiquidityOperation<'info> {
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    // the token deposited or withdrawn, either of the pool's
+    #[account(
+        mut,
+        has_one = owner,
+        constraint = user_ata.mint == pool.token0 || user_ata.mint == pool.token1 @ ErrorCode::InvalidUserTokenAccount,
+    )]
+    pub user_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = lp_mint,
+        associated_token::authority = owner,
+        associated_token::token_program = token_program
+    )]
+    pub user_lp_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub pool: Box<Account<'info, Pool>>,
+    /// CHECK: authority so 1 acc pass in can derive all other pdas
+    #[account(seeds=[b"authority", pool.key().as_ref()], bump)]
+    pub pool_authority: AccountInfo<'info>,
+
+    // never transferred out of, holds the locked MINIMUM_LIQUIDITY
+    #[account(
+        init_if_needed,
+        payer = owner,
+        associated_token::mint = lp_mint,
+        associated_token::authority = pool_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = vault0.key() == vault_address(&pool_authority.key(), &pool.token0, &token_program0.key()) @ ErrorCode::InvalidVault,
+    )]
+    pub vault0: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = vault1.key() == vault_address(&pool_authority.key(), &pool.token1, &token_program1.key()) @ ErrorCode::InvalidVault,
+    )]
+    pub vault1: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(constraint = mint0.key() == pool.token0 @ ErrorCode::InvalidMint, mint::token_program = token_program0)]
+    pub mint0: Box<InterfaceAccount<'info, Mint>>,
+    #[account(constraint = mint1.key() == pool.token1 @ ErrorCode::InvalidMint, mint::token_program = token_program1)]
+    pub mint1: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut, seeds = [b"lp_mint", pool.key().as_ref()], bump)]
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut, seeds = [b"observations", pool.key().as_ref()], bump = observations.load()?.bump)]
+    pub observations: AccountLoader<'info, Observations>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    // the LP mint always lives under the original token program
+    pub token_program: Program<'info, Token>,
+    pub token_program0: Interface<'info, TokenInterface>,
+    pub token_program1: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+impl<'info> SingleLiquidityOperation<'info> {
+    // whether the user's token is token0
+    fn is_token0(&self) -> bool {
+        self.user_ata.mint == self.pool.token0
+    }
+
+    // (mint, vault, token program) of the user's token
+    fn user_side(
+        &self,
+    ) -> (
+        &InterfaceAccount<'info, Mint>,
+        &InterfaceAccount<'info, TokenAccount>,
+        &Interface<'info, TokenInterface>,
+    ) {
+        if self.is_token0() {
+            (&self.mint0, &self.vault0, &self.token_program0)
+        } else {
+            (&self.mint1, &self.vault1, &self.token_program1)
+        }
+    }
+}
+
+// Deposits `input_amount` of one token, leaving the user before any transfer fee. Part of what
+// arrives is swapped against the pool for the other token, the curve choosing the part so that the
+// two are in the ratio of the reserves after the swap, and the pair is deposited as `add_liquidity`
+// would. The swap pays the pool's fee like any other.
+pub fn add_liquidity_single(
+    ctx: Context<SingleLiquidityOperation>,
+    input_amount: u64,
+    min_liquidity: u64,
+    deadline: Option<i64>,
+) -> Result<()> {
+    check_deadline(deadline)?;
+    check_not_paused(&ctx.accounts.config, &ctx.accounts.pool)?;
+    require!(
+        ctx.accounts.lp_mint.supply > 0,
+        ErrorCode::InsufficientLiquidity
+    );
+
+    let accounts = &ctx.accounts;
+    let pool: &Account<Pool> = &accounts.pool;
+    let zero_for_one = accounts.is_token0();
+    let (mint_in, _, _) = accounts.user_side();
+    let mint_out = if zero_for_one {
+        pool.token1
+    } else {
+        pool.token0
+    };
+
+    // priced on what arrives in the vault, net of any transfer fee
+    let amount_in = input_amount.safe_sub(transfer_fee(mint_in, input_amount)?)?;
+    let curve = pool.curve()?;
+    let swap_amount = curve.zap_amount(zero_for_one, amount_in, pool.reserve0, pool.reserve1)?;
+    let swap_amount_out =
+        curve.swap_exact_input(zero_for_one, swap_amount, pool.reserve0, pool.reserve1)?;
+    // the reserves after the swap, which the rest is deposited against
+    let (reserve_in, reserve_out) = pool.reserves_for(mint_in.key());
+    let (reserve0, reserve1) = pool.sorted_reserves(
+        mint_in.key(),
+        reserve_in.safe_add(swap_amount)?,
+        reserve_out.safe_sub(swap_amount_out)?,
+    );
+    let (amount0, amount1) = pool.sorted_reserves(
+        mint_in.key(),
+        amount_in.safe_sub(swap_amount)?,
+        swap_amount_out,
+    );
+    let swap_fee_amount = swap_fee(pool.fee, swap_amount)?;
+
+    let pool_key = pool.key();
+    let pool_sign = &[b"authority", pool_key.as_ref(), &[ctx.bumps.pool_authority]];
+
+    // the protocol fee is charged on the reserves after the swap, as for a swap and then a deposit
+    let mint_fee_ctx = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        MintTo {
+            to: ctx.accounts.vault_lp.to_account_info(),
+            mint: ctx.accounts.lp_mint.to_account_info(),
+            authority: ctx.accounts.pool_authority.to_account_info(),
+        },
+    );
+    let fee_on = mint_fee(
+        &ctx.accounts.config,
+        &mut ctx.accounts.pool,
+        reserve0,
+        reserve1,
+        ctx.accounts.lp_mint.supply,
+        mint_fee_ctx.with_signer(&[pool_sign]),
+    )?;
+    // the supply the new liquidity is priced against includes the fee just minted
+    ctx.accounts.lp_mint.reload()?;
+
+    let liquidity = curve.deposit(
+        amount0,
+        amount1,
+        reserve0,
+        reserve1,
+        ctx.accounts.lp_mint.supply,
+    )?;
+    require!(
+        liquidity >= min_liquidity,
+        ErrorCode::InsufficientLiquidityMinted
+    );
+
+    let mint_ctx = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        MintTo {
+            to: ctx.accounts.user_lp_ata.to_account_info(),
+            mint: ctx.accounts.lp_mint.to_account_info(),
+            authority: ctx.accounts.pool_authority.to_account_info(),
+        },
+    );
+    mint_to(mint_ctx.with_signer(&[pool_sign]), liquidity)?;
+
+    // transfer tokens from user to vault, the swapped part never leaves it
+    let (mint_in, vault_in, token_program_in) = ctx.accounts.user_side();
+    transfer_checked(
+        CpiContext::new(
+            token_program_in.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.user_ata.to_account_info(),
+                mint: mint_in.to_account_info(),
+                to: vault_in.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        ),
+        input_amount,
+        mint_in.decimals,
+    )?;
+    let mint_in = mint_in.key();
+
+    let pool: &mut Account<Pool> = &mut ctx.accounts.pool;
+    let timestamp = Clock::get()?.unix_timestamp;
+    ctx.accounts.observations.write(timestamp, pool)?;
+    let (reserve0_after, reserve1_after) =
+        (reserve0.safe_add(amount0)?, reserve1.safe_add(amount1)?);
+    pool.update(reserve0_after, reserve1_after, timestamp)?;
+    if fee_on {
+        pool.update_k_last(reserve0_after, reserve1_after)?;
+    }
+
+    emit!(events::Swap {
+        pool: pool.key(),
+        user: ctx.accounts.owner.key(),
+        mint_in,
+        mint_out,
+        amount_in: swap_amount,
+        amount_out: swap_amount_out,
+        fee_amount: swap_fee_amount,
+        reserve0,
+        reserve1,
+    });
+    emit!(events::Mint {
+        pool: pool.key(),
+        user: ctx.accounts.owner.key(),
+        amount0,
+        amount1,
+        liquidity,
+        reserve0: reserve0_after,
+        reserve1: reserve1_after,
+    });
+
+    Ok(())
+}
+
+// Burns `liquidity` as `remove_liquidity` does and swaps the other token's share back into the pool
+// for the user's token, on the reserves after the burn. The swap pays the pool's fee like any other
+// and `min_amount_out` applies to what reaches the user, after any transfer fee. It also stops like
+// any other swap while the pool or the protocol is paused, when LPs exit through `remove_liquidity`.
+pub fn remove_liquidity_single(
+    ctx: Context<SingleLiquidityOperation>,
+    liquidity: u64,
+    min_amount_out: u64,
+    deadline: Option<i64>,
+) -> Result<()> {
+    check_deadline(deadline)?;
+    check_not_paused(&ctx.accounts.config, &ctx.accounts.pool)?;
+
+    let pool: &Account<Pool> = &ctx.accounts.pool;
+    let (reserve0, reserve1) = (pool.reserve0, pool.reserve1);
+
+    let pool_key = pool.key();
+    let pool_sign = &[b"authority", pool_key.as_ref(), &[ctx.bumps.pool_authority]];
+
+    let mint_fee_ctx = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        MintTo {
+            to: ctx.accounts.vault_lp.to_account_info(),
+            mint: ctx.accounts.lp_mint.to_account_info(),
+            authority: ctx.accounts.pool_authority.to_account_info(),
+        },
+    );
+    let fee_on = mint_fee(
+        &ctx.accounts.config,
+        &mut ctx.accounts.pool,
+        reserve0,
+        reserve1,
+        ctx.accounts.lp_mint.supply,
+        mint_fee_ctx.with_signer(&[pool_sign]),
+    )?;
+    // the supply the liquidity is redeemed against includes the fee just minted
+    ctx.accounts.lp_mint.reload()?;
+
+    let accounts = &ctx.accounts;
+    let pool: &Account<Pool> = &accounts.pool;
+    let curve = pool.curve()?;
+    let (amount0, amount1) =
+        curve.withdraw(liquidity, accounts.lp_mint.supply, reserve0, reserve1)?;
+    let (reserve0_burned, reserve1_burned) =
+        (reserve0.safe_sub(amount0)?, reserve1.safe_sub(amount1)?);
+
+    // the other token's share never leaves its vault, it is swapped for the user's token
+    let zero_for_one = !accounts.is_token0();
+    let (mint_out, vault_out, _) = accounts.user_side();
+    let (mint_in, amount, swap_amount) = if zero_for_one {
+        (pool.token0, amount1, amount0)
+    } else {
+        (pool.token1, amount0, amount1)
+    };
+    let swap_amount_out =
+        curve.swap_exact_input(zero_for_one, swap_amount, reserve0_burned, reserve1_burned)?;
+    let amount_out = amount.safe_add(swap_amount_out)?;
+    let amount_received = amount_out.safe_sub(transfer_fee(mint_out, amount_out)?)?;
+    require!(
+        amount_received >= min_amount_out,
+        ErrorCode::InsufficientOutputAmount
+    );
+    let swap_fee_amount = swap_fee(pool.fee, swap_amount)?;
+    let (reserve0_after, reserve1_after) = if zero_for_one {
+        (reserve0, reserve1_burned.safe_sub(swap_amount_out)?)
+    } else {
+        (reserve0_burned.safe_sub(swap_amount_out)?, reserve1)
+    };
+    let vault_out = vault_out.to_account_info();
+
+    // burn liquidity tokens
+    let burn_ctx = CpiContext::new(
+        ctx.accounts.token_program.to_account_info(),
+        Burn {
+            from: ctx.accounts.user_lp_ata.to_account_info(),
+            mint: ctx.accounts.lp_mint.to_account_info(),
+            authority: ctx.accounts.owner.to_account_info(),
+        },
+    );
+    burn(burn_ctx.with_signer(&[pool_sign]), liquidity)?;
+
+    // transfer tokens from vault to user
+    let (mint_out, _, token_program_out) = ctx.accounts.user_side();
+    transfer_checked(
+        CpiContext::new(
+            token_program_out.to_account_info(),
+            TransferChecked {
+                from: vault_out,
+                mint: mint_out.to_account_info(),
+                to: ctx.accounts.user_ata.to_account_info(),
+                authority: ctx.accounts.pool_authority.to_account_info(),
+            },
+        )
+        .with_signer(&[pool_sign]),
+        amount_out,
+        mint_out.decimals,
+    )?;
+    let mint_out = mint_out.key();
+
+    let pool: &mut Account<Pool> = &mut ctx.accounts.pool;
+    let timestamp = Clock::get()?.unix_timestamp;
+    ctx.accounts.observations.write(timestamp, pool)?;
+    pool.update(reserve0_after, reserve1_after, timestamp)?;
+    if fee_on {
+        pool.update_k_last(reserve0_after, reserve1_after)?;
+    }
+
+    emit!(events::Burn {
+        pool: pool.key(),
+        user: ctx.accounts.owner.key(),
+        amount0,
+        amount1,
+        liquidity,
+        reserve0: reserve0_burned,
+        reserve1: reserve1_burned,
+    });
+    emit!(events::Swap {
+        pool: pool.key(),
+        user: ctx.accounts.owner.key(),
+        mint_in,
+        mint_out,
+        amount_in: swap_amount,
+        amount_out: swap_amount_out,
+        fee_amount: swap_fee_amount,
+        reserve0: reserve0_after,
+        reserve1: reserve1_after,
+    });
+
+    Ok(())
+}
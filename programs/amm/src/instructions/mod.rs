@@ -1,11 +1,29 @@
-pub mod liquidity_operation;
 pub mod create_pool;
+pub mod flash_swap;
 pub mod initialize;
+pub mod liquidity_operation;
+pub mod liquidity_single;
+pub mod oracle;
+pub mod ownership;
+pub mod pause;
+pub mod quote;
+pub mod ramp_amp;
+pub mod route;
 pub mod set_fee;
 pub mod swap;
+pub mod sync;
 
-pub use liquidity_operation::*;
 pub use create_pool::*;
+pub use flash_swap::*;
 pub use initialize::*;
+pub use liquidity_operation::*;
+pub use liquidity_single::*;
+pub use oracle::*;
+pub use ownership::*;
+pub use pause::*;
+pub use quote::*;
+pub use ramp_amp::*;
+pub use route::*;
 pub use set_fee::*;
 pub use swap::*;
+pub use sync::*;
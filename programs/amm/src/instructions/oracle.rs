@@ -0,0 +1,97 @@
+use crate::error::ErrorCode;
+use crate::state::Observation;
+use crate::state::Observations;
+use crate::state::ObservationsAccount;
+use crate::state::Pool;
+use anchor_lang::prelude::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriceObservation {
+    pub timestamp: i64,
+    pub price0_cumulative: u128,
+    pub price1_cumulative: u128,
+}
+
+// average prices over the observed window, Q64.64 fixed point
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Twap {
+    pub price0_average: u128,
+    pub price1_average: u128,
+    pub observation: PriceObservation,
+}
+
+#[derive(Accounts)]
+pub struct GetTwap<'info> {
+    pub pool: Box<Account<'info, Pool>>,
+}
+
+// returns the average prices between `start` (a snapshot of the pool's cumulative prices taken
+// earlier by the caller) and the current block, along with the current observation
+pub fn get_twap(ctx: Context<GetTwap>, start: PriceObservation) -> Result<Twap> {
+    let pool = &ctx.accounts.pool;
+    let timestamp = Clock::get()?.unix_timestamp;
+    let (price0_cumulative, price1_cumulative) = pool.current_cumulative_prices(timestamp)?;
+
+    let end = PriceObservation {
+        timestamp,
+        price0_cumulative,
+        price1_cumulative,
+    };
+    twap_between(&start, &end)
+}
+
+pub fn twap_between(start: &PriceObservation, end: &PriceObservation) -> Result<Twap> {
+    require!(
+        end.timestamp > start.timestamp,
+        ErrorCode::InvalidObservation
+    );
+    let time_elapsed = (end.timestamp - start.timestamp) as u128;
+
+    Ok(Twap {
+        price0_average: end.price0_cumulative.wrapping_sub(start.price0_cumulative) / time_elapsed,
+        price1_average: end.price1_cumulative.wrapping_sub(start.price1_cumulative) / time_elapsed,
+        observation: *end,
+    })
+}
+
+#[derive(Accounts)]
+#[instruction(cardinality_next: u16)]
+pub struct IncreaseObservationCardinality<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub pool: Box<Account<'info, Pool>>,
+    #[account(
+        mut,
+        seeds = [b"observations", pool.key().as_ref()],
+        bump = observations.load()?.bump,
+        realloc = Observations::space(cardinality_next),
+        realloc::payer = payer,
+        realloc::zero = true,
+    )]
+    pub observations: AccountLoader<'info, Observations>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn increase_observation_cardinality(
+    ctx: Context<IncreaseObservationCardinality>,
+    cardinality_next: u16,
+) -> Result<()> {
+    ctx.accounts.observations.grow(cardinality_next)
+}
+
+#[derive(Accounts)]
+pub struct Consult<'info> {
+    pub pool: Box<Account<'info, Pool>>,
+    #[account(seeds = [b"observations", pool.key().as_ref()], bump = observations.load()?.bump)]
+    pub observations: AccountLoader<'info, Observations>,
+}
+
+// cumulative prices and liquidity `seconds_agos[i]` seconds before the current block; the average
+// over a window is the difference of two results divided by their time difference
+pub fn consult(ctx: Context<Consult>, seconds_agos: Vec<u32>) -> Result<Vec<Observation>> {
+    ctx.accounts.observations.observe(
+        Clock::get()?.unix_timestamp,
+        &seconds_agos,
+        &ctx.accounts.pool,
+    )
+}
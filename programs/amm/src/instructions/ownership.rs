@@ -0,0 +1,44 @@
+use crate::state::config::Config;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct ProposeOwner<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = owner)]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn propose_owner(ctx: Context<ProposeOwner>, new_owner: Pubkey) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.propose_owner(new_owner)
+}
+
+#[derive(Accounts)]
+pub struct AcceptOwner<'info> {
+    #[account(mut)]
+    pub pending_owner: Signer<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = pending_owner)]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn accept_owner(ctx: Context<AcceptOwner>) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.accept_owner()
+}
+
+#[derive(Accounts)]
+pub struct RenounceOwner<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = owner)]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn renounce_owner(ctx: Context<RenounceOwner>) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.renounce_owner()
+}
@@ -0,0 +1,33 @@
+use crate::state::config::Config;
+use crate::state::Pool;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct SetPaused<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut, seeds = [b"config"], bump = config.bump, has_one = owner)]
+    pub config: Account<'info, Config>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn set_paused(ctx: Context<SetPaused>, paused: bool) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.set_paused(paused)
+}
+
+#[derive(Accounts)]
+pub struct SetPoolPaused<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = owner)]
+    pub config: Account<'info, Config>,
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn set_pool_paused(ctx: Context<SetPoolPaused>, paused: bool) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    pool.set_paused(paused)
+}
@@ -0,0 +1,136 @@
+use crate::constant_product::calculate_liquidity_amounts;
+use crate::error::ErrorCode;
+use crate::instructions::{exact_input_amounts, exact_output_amounts};
+use crate::math::CheckedMath;
+use crate::state::Config;
+use crate::state::Pool;
+use crate::utils::{inverse_transfer_fee, transfer_fee};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::Mint;
+
+// amounts on the user's side of a deposit or withdrawal, in pool token order
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LiquidityQuote {
+    pub amount0: u64,
+    pub amount1: u64,
+    pub liquidity: u64,
+}
+
+#[derive(Accounts)]
+pub struct QuoteSwap<'info> {
+    pub pool: Box<Account<'info, Pool>>,
+    #[account(
+        constraint = mint_src.key() == pool.token0 || mint_src.key() == pool.token1 @ ErrorCode::InvalidSwapDirection
+    )]
+    pub mint_src: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        constraint = mint_des.key() == pool.token0 || mint_des.key() == pool.token1 @ ErrorCode::InvalidSwapDirection,
+        constraint = mint_des.key() != mint_src.key() @ ErrorCode::InvalidSwapDirection
+    )]
+    pub mint_des: Box<InterfaceAccount<'info, Mint>>,
+}
+
+// what reaches the user for `input_amount` leaving the user, as `swap_exact_input` would price it
+pub fn quote_exact_input(ctx: Context<QuoteSwap>, input_amount: u64) -> Result<u64> {
+    let (_, _, amount_received) = exact_input_amounts(
+        &ctx.accounts.pool,
+        &ctx.accounts.mint_src,
+        &ctx.accounts.mint_des,
+        input_amount,
+    )?;
+    Ok(amount_received)
+}
+
+// what leaves the user for `output_amount` reaching the user, as `swap_exact_output` would price it
+pub fn quote_exact_output(ctx: Context<QuoteSwap>, output_amount: u64) -> Result<u64> {
+    let (input_amount, _, _) = exact_output_amounts(
+        &ctx.accounts.pool,
+        &ctx.accounts.mint_src,
+        &ctx.accounts.mint_des,
+        output_amount,
+    )?;
+    Ok(input_amount)
+}
+
+#[derive(Accounts)]
+pub struct QuoteLiquidity<'info> {
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+    pub pool: Box<Account<'info, Pool>>,
+    #[account(constraint = mint0.key() == pool.token0 @ ErrorCode::InvalidMint)]
+    pub mint0: Box<InterfaceAccount<'info, Mint>>,
+    #[account(constraint = mint1.key() == pool.token1 @ ErrorCode::InvalidMint)]
+    pub mint1: Box<InterfaceAccount<'info, Mint>>,
+    #[account(seeds = [b"lp_mint", pool.key().as_ref()], bump)]
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+}
+
+impl<'info> QuoteLiquidity<'info> {
+    // LP supply after the protocol fee a liquidity operation would mint first
+    fn lp_supply(&self) -> Result<u64> {
+        let supply = self.lp_mint.supply;
+        if !self.config.is_protocol_fee_on() {
+            return Ok(supply);
+        }
+        supply.safe_add(self.pool.protocol_fee_liquidity(
+            self.config.protocol_fee_share,
+            self.pool.reserve0,
+            self.pool.reserve1,
+            supply,
+        )?)
+    }
+}
+
+// amounts leaving the user and the LP minted for a deposit of at most the desired amounts, as
+// `add_liquidity` would price it with no minimums
+pub fn quote_add_liquidity(
+    ctx: Context<QuoteLiquidity>,
+    amount0_desired: u64,
+    amount1_desired: u64,
+) -> Result<LiquidityQuote> {
+    let accounts = &ctx.accounts;
+    let pool = &accounts.pool;
+    let (amount0, amount1) = calculate_liquidity_amounts(
+        pool.reserve0,
+        pool.reserve1,
+        amount0_desired.safe_sub(transfer_fee(&accounts.mint0, amount0_desired)?)?,
+        amount1_desired.safe_sub(transfer_fee(&accounts.mint1, amount1_desired)?)?,
+        0,
+        0,
+    )?;
+    let liquidity = pool.curve()?.deposit(
+        amount0,
+        amount1,
+        pool.reserve0,
+        pool.reserve1,
+        accounts.lp_supply()?,
+    )?;
+
+    Ok(LiquidityQuote {
+        amount0: amount0.safe_add(inverse_transfer_fee(&accounts.mint0, amount0)?)?,
+        amount1: amount1.safe_add(inverse_transfer_fee(&accounts.mint1, amount1)?)?,
+        liquidity,
+    })
+}
+
+// amounts reaching the user for burning `liquidity`, as `remove_liquidity` would price it with no
+// minimums
+pub fn quote_remove_liquidity(
+    ctx: Context<QuoteLiquidity>,
+    liquidity: u64,
+) -> Result<LiquidityQuote> {
+    let accounts = &ctx.accounts;
+    let pool = &accounts.pool;
+    let (amount0, amount1) = pool.curve()?.withdraw(
+        liquidity,
+        accounts.lp_supply()?,
+        pool.reserve0,
+        pool.reserve1,
+    )?;
+
+    Ok(LiquidityQuote {
+        amount0: amount0.safe_sub(transfer_fee(&accounts.mint0, amount0)?)?,
+        amount1: amount1.safe_sub(transfer_fee(&accounts.mint1, amount1)?)?,
+        liquidity,
+    })
+}
@@ -0,0 +1,30 @@
+use crate::events::AmpRamped;
+use crate::state::Config;
+use crate::state::Pool;
+use anchor_lang::prelude::*;
+
+#[derive(Accounts)]
+pub struct RampAmp<'info> {
+    pub owner: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = owner)]
+    pub config: Account<'info, Config>,
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+}
+
+// moves a stable pool's amplification coefficient linearly from its current value to `future_amp`
+// at `future_amp_time`, so that the curve reshapes without a jump in price
+pub fn ramp_amp(ctx: Context<RampAmp>, future_amp: u64, future_amp_time: i64) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    pool.ramp_amp(future_amp, future_amp_time, Clock::get()?.unix_timestamp)?;
+
+    emit!(AmpRamped {
+        pool: pool.key(),
+        initial_amp: pool.initial_amp,
+        future_amp: pool.future_amp,
+        initial_amp_time: pool.initial_amp_time,
+        future_amp_time: pool.future_amp_time,
+    });
+
+    Ok(())
+}
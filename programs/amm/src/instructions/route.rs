@@ -0,0 +1,301 @@
+use crate::error::ErrorCode;
+use crate::events;
+use crate::math::{swap_fee, CheckedMath};
+use crate::state::Config;
+use crate::state::Observations;
+use crate::state::ObservationsAccount;
+use crate::state::Pool;
+use crate::utils::{
+    check_deadline, check_not_paused, inverse_transfer_fee, transfer_fee, vault_address,
+};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+// accounts per hop in `remaining_accounts`: pool, pool_authority, vault_src, vault_des, observations,
+// mint_des, token_program_des
+pub const ROUTE_HOP_ACCOUNTS: usize = 7;
+
+#[derive(Accounts)]
+pub struct SwapRoute<'info> {
+    #[account(seeds = [b"config"], bump = config.bump)]
+    pub config: Box<Account<'info, Config>>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(mut, has_one = owner)]
+    pub user_ata_src: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, has_one = owner)]
+    pub user_ata_des: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(constraint = mint_src.key() == user_ata_src.mint @ ErrorCode::InvalidMint, mint::token_program = token_program_src)]
+    pub mint_src: Box<InterfaceAccount<'info, Mint>>,
+    #[account(constraint = mint_des.key() == user_ata_des.mint @ ErrorCode::InvalidMint, mint::token_program = token_program_des)]
+    pub mint_des: Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_program_src: Interface<'info, TokenInterface>,
+    pub token_program_des: Interface<'info, TokenInterface>,
+}
+
+struct Hop<'info> {
+    pool: Account<'info, Pool>,
+    pool_authority: &'info AccountInfo<'info>,
+    authority_bump: u8,
+    vault_src: InterfaceAccount<'info, TokenAccount>,
+    vault_des: InterfaceAccount<'info, TokenAccount>,
+    observations: AccountLoader<'info, Observations>,
+    mint_des: InterfaceAccount<'info, Mint>,
+    token_program_des: Interface<'info, TokenInterface>,
+}
+
+impl<'info> Hop<'info> {
+    // (reserve_in, reserve_out) for this hop's direction
+    fn reserves(&self) -> (u64, u64) {
+        self.pool.reserves_for(self.vault_src.mint)
+    }
+}
+
+// `input_amount` is what leaves the user and `min_output_amount` applies to what reaches the user,
+// transfer fees are taken out of every hop's output before it is priced by the next hop
+pub fn swap_exact_input_route<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SwapRoute<'info>>,
+    input_amount: u64,
+    min_output_amount: u64,
+    deadline: Option<i64>,
+) -> Result<()> {
+    check_deadline(deadline)?;
+
+    let mut hops = load_route(
+        &ctx.accounts.config,
+        ctx.remaining_accounts,
+        ctx.accounts.user_ata_src.mint,
+        ctx.accounts.token_program_src.key(),
+        ctx.accounts.user_ata_des.mint,
+    )?;
+
+    let mut amounts = Vec::with_capacity(hops.len());
+    let mut amount_in =
+        input_amount.safe_sub(transfer_fee(&ctx.accounts.mint_src, input_amount)?)?;
+    for hop in hops.iter() {
+        let amount_out = hop.pool.swap_amount_out(hop.vault_src.mint, amount_in)?;
+        amounts.push((amount_in, amount_out));
+        amount_in = amount_out.safe_sub(transfer_fee(&hop.mint_des, amount_out)?)?;
+    }
+    require!(
+        amount_in >= min_output_amount,
+        ErrorCode::InsufficientOutputAmount,
+    );
+
+    swap_route(&ctx, &mut hops, input_amount, &amounts)
+}
+
+// `output_amount` is what reaches the user and `max_input_amount` bounds what leaves the user, the
+// hops are priced backwards with every transfer fee added on top
+pub fn swap_exact_output_route<'info>(
+    ctx: Context<'_, '_, 'info, 'info, SwapRoute<'info>>,
+    output_amount: u64,
+    max_input_amount: u64,
+    deadline: Option<i64>,
+) -> Result<()> {
+    check_deadline(deadline)?;
+
+    let mut hops = load_route(
+        &ctx.accounts.config,
+        ctx.remaining_accounts,
+        ctx.accounts.user_ata_src.mint,
+        ctx.accounts.token_program_src.key(),
+        ctx.accounts.user_ata_des.mint,
+    )?;
+
+    let mut amounts = vec![(0, 0); hops.len()];
+    let mut amount_received = output_amount;
+    for (i, hop) in hops.iter().enumerate().rev() {
+        let (_, reserve_out) = hop.reserves();
+        let amount_out =
+            amount_received.safe_add(inverse_transfer_fee(&hop.mint_des, amount_received)?)?;
+        require!(amount_out < reserve_out, ErrorCode::InsufficientLiquidity,);
+        let amount_in = hop.pool.swap_amount_in(hop.vault_src.mint, amount_out)?;
+        amounts[i] = (amount_in, amount_out);
+        amount_received = amount_in;
+    }
+    let input_amount = amount_received.safe_add(inverse_transfer_fee(
+        &ctx.accounts.mint_src,
+        amount_received,
+    )?)?;
+    require!(
+        input_amount <= max_input_amount,
+        ErrorCode::InsufficientInputAmount,
+    );
+
+    swap_route(&ctx, &mut hops, input_amount, &amounts)
+}
+
+// deserializes and checks the hops in `accounts`, each hop's input must be the previous one's output
+fn load_route<'info>(
+    config: &Config,
+    accounts: &'info [AccountInfo<'info>],
+    mint_src: Pubkey,
+    token_program_src: Pubkey,
+    mint_des: Pubkey,
+) -> Result<Vec<Hop<'info>>> {
+    require!(
+        !accounts.is_empty()
+            && accounts
+                .chunks_exact(ROUTE_HOP_ACCOUNTS)
+                .remainder()
+                .is_empty(),
+        ErrorCode::InvalidRoute
+    );
+
+    let mut hops: Vec<Hop> = Vec::with_capacity(accounts.len() / ROUTE_HOP_ACCOUNTS);
+    let (mut mint_in, mut token_program_in) = (mint_src, token_program_src);
+    for hop_accounts in accounts.chunks_exact(ROUTE_HOP_ACCOUNTS) {
+        let pool = Account::<Pool>::try_from(&hop_accounts[0])?;
+        check_not_paused(config, &pool)?;
+        // a pool visited twice would be priced on stale reserves
+        require!(
+            hops.iter().all(|hop| hop.pool.key() != pool.key()),
+            ErrorCode::InvalidRoute
+        );
+
+        let (authority, authority_bump) =
+            Pubkey::find_program_address(&[b"authority", pool.key().as_ref()], &crate::ID);
+        require_keys_eq!(hop_accounts[1].key(), authority, ErrorCode::InvalidRoute);
+
+        let mint_out = if mint_in == pool.token0 {
+            pool.token1
+        } else if mint_in == pool.token1 {
+            pool.token0
+        } else {
+            return err!(ErrorCode::InvalidRoute);
+        };
+        let mint = InterfaceAccount::<Mint>::try_from(&hop_accounts[5])?;
+        let token_program = Interface::<TokenInterface>::try_from(&hop_accounts[6])?;
+        require_keys_eq!(mint.key(), mint_out, ErrorCode::InvalidRoute);
+        require_keys_eq!(
+            *hop_accounts[5].owner,
+            token_program.key(),
+            ErrorCode::InvalidRoute
+        );
+
+        require_keys_eq!(
+            hop_accounts[2].key(),
+            vault_address(&authority, &mint_in, &token_program_in),
+            ErrorCode::InvalidVault
+        );
+        require_keys_eq!(
+            hop_accounts[3].key(),
+            vault_address(&authority, &mint_out, &token_program.key()),
+            ErrorCode::InvalidVault
+        );
+
+        let observations = AccountLoader::<Observations>::try_from(&hop_accounts[4])?;
+        require_keys_eq!(
+            observations.load()?.pool,
+            pool.key(),
+            ErrorCode::InvalidRoute
+        );
+
+        token_program_in = token_program.key();
+        hops.push(Hop {
+            pool,
+            pool_authority: &hop_accounts[1],
+            authority_bump,
+            vault_src: InterfaceAccount::<TokenAccount>::try_from(&hop_accounts[2])?,
+            vault_des: InterfaceAccount::<TokenAccount>::try_from(&hop_accounts[3])?,
+            observations,
+            mint_des: mint,
+            token_program_des: token_program,
+        });
+        mint_in = mint_out;
+    }
+    require_keys_eq!(mint_in, mint_des, ErrorCode::InvalidRoute);
+
+    Ok(hops)
+}
+
+// `input_amount` leaves the user, then `amounts[i]` is (arriving in, leaving) hop i's vaults, each
+// hop's output is sent straight into the next hop's vault
+fn swap_route<'info>(
+    ctx: &Context<'_, '_, 'info, 'info, SwapRoute<'info>>,
+    hops: &mut [Hop<'info>],
+    input_amount: u64,
+    amounts: &[(u64, u64)],
+) -> Result<()> {
+    require!(amounts[0].0 > 0, ErrorCode::InsufficientInputAmount);
+    require!(
+        input_amount < ctx.accounts.user_ata_src.amount,
+        ErrorCode::InsufficientUserBalance,
+    );
+
+    // transfer tokens from user to the first vault
+    transfer_checked(
+        CpiContext::new(
+            ctx.accounts.token_program_src.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.user_ata_src.to_account_info(),
+                mint: ctx.accounts.mint_src.to_account_info(),
+                to: hops[0].vault_src.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        ),
+        input_amount,
+        ctx.accounts.mint_src.decimals,
+    )?;
+
+    let timestamp = Clock::get()?.unix_timestamp;
+    for i in 0..hops.len() {
+        let (amount_in, amount_out) = amounts[i];
+        require!(amount_out > 0, ErrorCode::InsufficientOutputAmount);
+
+        let to = match hops.get(i + 1) {
+            Some(next) => next.vault_src.to_account_info(),
+            None => ctx.accounts.user_ata_des.to_account_info(),
+        };
+        let hop = &mut hops[i];
+        let (reserve_in, reserve_out) = hop.reserves();
+        require!(amount_out < reserve_out, ErrorCode::InsufficientLiquidity);
+
+        let pool_key = hop.pool.key();
+        let pool_sign = &[b"authority", pool_key.as_ref(), &[hop.authority_bump]];
+        transfer_checked(
+            CpiContext::new(
+                hop.token_program_des.to_account_info(),
+                TransferChecked {
+                    from: hop.vault_des.to_account_info(),
+                    mint: hop.mint_des.to_account_info(),
+                    to,
+                    authority: hop.pool_authority.to_account_info(),
+                },
+            )
+            .with_signer(&[pool_sign]),
+            amount_out,
+            hop.mint_des.decimals,
+        )?;
+
+        let (reserve0, reserve1) = hop.pool.sorted_reserves(
+            hop.vault_src.mint,
+            reserve_in.safe_add(amount_in)?,
+            reserve_out.safe_sub(amount_out)?,
+        );
+        hop.observations.write(timestamp, &hop.pool)?;
+        hop.pool.update(reserve0, reserve1, timestamp)?;
+
+        emit!(events::Swap {
+            pool: pool_key,
+            user: ctx.accounts.owner.key(),
+            mint_in: hop.vault_src.mint,
+            mint_out: hop.vault_des.mint,
+            amount_in,
+            amount_out,
+            fee_amount: swap_fee(hop.pool.fee, amount_in)?,
+            reserve0,
+            reserve1,
+        });
+
+        hop.pool.exit(&crate::ID)?;
+        hop.observations.exit(&crate::ID)?;
+    }
+
+    Ok(())
+}
@@ -1,8 +1,14 @@
+use crate::error::ErrorCode;
+use crate::events::{FeeChanged, FeeToChanged};
+use crate::math::CheckedMath;
 use crate::state::config::Config;
+use crate::state::FeeTiers;
 use crate::state::Pool;
 use anchor_lang::prelude::*;
-use anchor_spl::token::{mint_to, MintTo};
-use fixed::types::U128F0;
+use anchor_spl::token::Token;
+use anchor_spl::token_interface::{
+    mint_to, transfer_checked, Mint, MintTo, TokenAccount, TransferChecked,
+};
 
 #[derive(Accounts)]
 pub struct SetFeeTo<'info> {
@@ -15,11 +21,18 @@ pub struct SetFeeTo<'info> {
 
 pub fn set_fee_to(ctx: Context<SetFeeTo>, new_fee_to: Pubkey) -> Result<()> {
     let config = &mut ctx.accounts.config;
-    Ok(config.set_fee_to(new_fee_to)?)
+    let old_fee_to = config.fee_to;
+    config.set_fee_to(new_fee_to)?;
+
+    emit!(FeeToChanged {
+        old_fee_to,
+        new_fee_to,
+    });
+    Ok(())
 }
 
 #[derive(Accounts)]
-pub struct SetFee<'info> {
+pub struct SetProtocolFeeShare<'info> {
     #[account(mut)]
     pub owner: Signer<'info>,
     #[account(mut, seeds = [b"config"], bump = config.bump, has_one = owner)]
@@ -27,35 +40,148 @@ pub struct SetFee<'info> {
     pub system_program: Program<'info, System>,
 }
 
-pub fn set_fee(ctx: Context<SetFee>, new_fee: u64) -> Result<()> {
+pub fn set_protocol_fee_share(
+    ctx: Context<SetProtocolFeeShare>,
+    protocol_fee_share: u64,
+) -> Result<()> {
     let config = &mut ctx.accounts.config;
-    Ok(config.set_fee(new_fee)?)
+    config.set_protocol_fee_share(protocol_fee_share)
+}
+
+#[derive(Accounts)]
+pub struct SetFeeTier<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = owner)]
+    pub config: Account<'info, Config>,
+    #[account(mut, seeds = [b"fee_tiers"], bump = fee_tiers.bump)]
+    pub fee_tiers: Account<'info, FeeTiers>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn enable_fee_tier(ctx: Context<SetFeeTier>, fee: u64) -> Result<()> {
+    let fee_tiers = &mut ctx.accounts.fee_tiers;
+    fee_tiers.enable(fee)?;
+
+    emit!(FeeChanged {
+        pool: None,
+        fee,
+        enabled: true,
+    });
+    Ok(())
+}
+
+pub fn disable_fee_tier(ctx: Context<SetFeeTier>, fee: u64) -> Result<()> {
+    let fee_tiers = &mut ctx.accounts.fee_tiers;
+    fee_tiers.disable(fee)?;
+
+    emit!(FeeChanged {
+        pool: None,
+        fee,
+        enabled: false,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SetFee<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = owner)]
+    pub config: Account<'info, Config>,
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+    pub system_program: Program<'info, System>,
+}
+
+// changes the swap fee of one pool, it keeps the address derived from the tier it was created on
+pub fn set_fee(ctx: Context<SetFee>, fee: u64) -> Result<()> {
+    let pool = &mut ctx.accounts.pool;
+    pool.set_fee(fee)?;
+
+    emit!(FeeChanged {
+        pool: Some(pool.key()),
+        fee,
+        enabled: true,
+    });
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ClaimProtocolFees<'info> {
+    pub fee_to: Signer<'info>,
+    #[account(seeds = [b"config"], bump = config.bump, has_one = fee_to)]
+    pub config: Box<Account<'info, Config>>,
+
+    #[account(mut)]
+    pub pool: Box<Account<'info, Pool>>,
+    /// CHECK: authority so 1 acc pass in can derive all other pdas
+    #[account(seeds=[b"authority", pool.key().as_ref()], bump)]
+    pub pool_authority: AccountInfo<'info>,
+    #[account(seeds = [b"lp_mint", pool.key().as_ref()], bump)]
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        mut,
+        associated_token::mint = lp_mint,
+        associated_token::authority = pool_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault_lp: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, token::mint = lp_mint)]
+    pub recipient: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+// sends the protocol fee LP accrued in the pool's LP account to `recipient`
+pub fn claim_protocol_fees(ctx: Context<ClaimProtocolFees>) -> Result<()> {
+    let amount = ctx.accounts.pool.protocol_fees;
+    require!(amount > 0, ErrorCode::InsufficientAmount);
+
+    let pool_key = ctx.accounts.pool.key();
+    let pool_sign = &[b"authority", pool_key.as_ref(), &[ctx.bumps.pool_authority]];
+    transfer_checked(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.vault_lp.to_account_info(),
+                mint: ctx.accounts.lp_mint.to_account_info(),
+                to: ctx.accounts.recipient.to_account_info(),
+                authority: ctx.accounts.pool_authority.to_account_info(),
+            },
+        )
+        .with_signer(&[pool_sign]),
+        amount,
+        ctx.accounts.lp_mint.decimals,
+    )?;
+
+    ctx.accounts.pool.protocol_fees = 0;
+
+    Ok(())
 }
 
+// Mints the protocol's share of the LP fee growth since the last liquidity event to the pool's LP
+// account, where it accrues for `fee_to` to claim. Returns whether the protocol fee is on, when it
+// is off `k_last` is cleared so switching it back on does not charge for the time it was off.
 pub fn mint_fee<'info>(
-    _config: &Config,
-    pool: &Pool,
+    config: &Config,
+    pool: &mut Pool,
     reserve0: u64,
     reserve1: u64,
     lp_supply: u64,
     mint_ctx: CpiContext<'_, '_, '_, 'info, MintTo<'info>>,
-) -> Result<()> {
-    let k_last = pool.k_last;
-
-    if k_last != 0 {
-        let root_k: u128 = U128F0::from_num((reserve0 as u128) * (reserve1 as u128))
-            .sqrt()
-            .to_num::<u128>();
-        let root_k_last = U128F0::from_num(k_last).sqrt().to_num::<u128>();
-        if root_k > root_k_last {
-            let numerator: u128 = (lp_supply as u128) * (root_k - root_k_last);
-            let denominator: u128 = root_k * 5 + root_k_last;
-            let liquidity: u64 = (numerator / denominator) as u64;
-            if liquidity > 0 {
-                mint_to(mint_ctx, liquidity)?;
-            }
-        }
+) -> Result<bool> {
+    if !config.is_protocol_fee_on() {
+        pool.k_last = 0;
+        return Ok(false);
     }
 
-    Ok(())
+    let liquidity =
+        pool.protocol_fee_liquidity(config.protocol_fee_share, reserve0, reserve1, lp_supply)?;
+    if liquidity > 0 {
+        mint_to(mint_ctx, liquidity)?;
+        pool.protocol_fees = pool.protocol_fees.safe_add(liquidity)?;
+    }
+
+    Ok(true)
 }
@@ -1,22 +1,38 @@
-use crate::constant::BASIS_POINTS;
 use crate::error::ErrorCode;
+use crate::events;
+use crate::math::{swap_fee, CheckedMath};
 use crate::state::Config;
+use crate::state::Observations;
+use crate::state::ObservationsAccount;
 use crate::state::Pool;
+use crate::utils::{
+    check_deadline, check_not_paused, inverse_transfer_fee, transfer_fee, vault_address,
+};
 use anchor_lang::prelude::*;
 use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::token::{transfer, Mint, Token, TokenAccount, Transfer};
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
 
 #[derive(Accounts)]
 pub struct Swap<'info> {
-    #[account( seeds = [b"config"], bump = config.bump)]
+    #[account(seeds = [b"config"], bump = config.bump)]
     pub config: Box<Account<'info, Config>>,
 
     #[account(mut)]
     pub owner: Signer<'info>,
-    #[account(mut, has_one = owner)]
-    pub user_ata_src: Box<Account<'info, TokenAccount>>,
-    #[account(mut, has_one = owner)]
-    pub user_ata_des: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        has_one = owner,
+        constraint = user_ata_src.mint == mint_src.key() @ ErrorCode::InvalidUserTokenAccount,
+    )]
+    pub user_ata_src: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        has_one = owner,
+        constraint = user_ata_des.mint == mint_des.key() @ ErrorCode::InvalidUserTokenAccount,
+    )]
+    pub user_ata_des: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut)]
     pub pool: Box<Account<'info, Pool>>,
@@ -24,134 +40,188 @@ pub struct Swap<'info> {
     #[account(seeds=[b"authority", pool.key().as_ref()], bump)]
     pub pool_authority: AccountInfo<'info>,
 
-    #[account(mut, constraint = user_ata_src.mint == vault_src.mint)]
-    pub vault_src: Box<Account<'info, TokenAccount>>,
-    #[account(mut, constraint = user_ata_des.mint == vault_des.mint)]
-    pub vault_des: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = vault_src.key() == vault_address(&pool_authority.key(), &mint_src.key(), &token_program_src.key()) @ ErrorCode::InvalidVault,
+    )]
+    pub vault_src: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = vault_des.key() == vault_address(&pool_authority.key(), &mint_des.key(), &token_program_des.key()) @ ErrorCode::InvalidVault,
+    )]
+    pub vault_des: Box<InterfaceAccount<'info, TokenAccount>>,
+    // one of the pool's tokens for the other
+    #[account(
+        constraint = mint_src.key() == pool.token0 || mint_src.key() == pool.token1 @ ErrorCode::InvalidSwapDirection,
+        mint::token_program = token_program_src,
+    )]
+    pub mint_src: Box<InterfaceAccount<'info, Mint>>,
+    #[account(
+        constraint = mint_des.key() == pool.token0 || mint_des.key() == pool.token1 @ ErrorCode::InvalidSwapDirection,
+        constraint = mint_des.key() != mint_src.key() @ ErrorCode::InvalidSwapDirection,
+        mint::token_program = token_program_des,
+    )]
+    pub mint_des: Box<InterfaceAccount<'info, Mint>>,
     #[account(mut, seeds = [b"lp_mint", pool.key().as_ref()], bump)]
-    pub lp_mint: Box<Account<'info, Mint>>,
+    pub lp_mint: Box<InterfaceAccount<'info, Mint>>,
+    #[account(mut, seeds = [b"observations", pool.key().as_ref()], bump = observations.load()?.bump)]
+    pub observations: AccountLoader<'info, Observations>,
 
     pub associated_token_program: Program<'info, AssociatedToken>,
-    pub token_program: Program<'info, Token>,
+    pub token_program_src: Interface<'info, TokenInterface>,
+    pub token_program_des: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 }
 
+// `input_amount` is what leaves the user, a transfer fee on the input mint is taken out of it
+// before pricing; `min_output_amount` applies to what reaches the user
 pub fn swap_exact_input(
     ctx: Context<Swap>,
     input_amount: u64,
     min_output_amount: u64,
+    deadline: Option<i64>,
 ) -> Result<()> {
-    let amount_out = get_amount_out(
-        &ctx.accounts.config,
+    check_deadline(deadline)?;
+    check_not_paused(&ctx.accounts.config, &ctx.accounts.pool)?;
+
+    let (amount_in, amount_out, amount_received) = exact_input_amounts(
+        &ctx.accounts.pool,
+        &ctx.accounts.mint_src,
+        &ctx.accounts.mint_des,
         input_amount,
-        ctx.accounts.vault_src.amount,
-        ctx.accounts.vault_des.amount,
     )?;
     require!(
-        amount_out >= min_output_amount,
+        amount_received >= min_output_amount,
         ErrorCode::InsufficientOutputAmount,
     );
 
-    swap(ctx, input_amount, amount_out)?;
+    swap(ctx, input_amount, amount_in, amount_out)?;
 
     Ok(())
 }
 
+// `output_amount` is what reaches the user, `max_input_amount` bounds what leaves the user, both
+// including any transfer fees
 pub fn swap_exact_output(
     ctx: Context<Swap>,
     output_amount: u64,
     max_input_amount: u64,
+    deadline: Option<i64>,
 ) -> Result<()> {
-    let amount_in = get_amount_in(
-        &ctx.accounts.config,
+    check_deadline(deadline)?;
+    check_not_paused(&ctx.accounts.config, &ctx.accounts.pool)?;
+
+    let (input_amount, amount_in, amount_out) = exact_output_amounts(
+        &ctx.accounts.pool,
+        &ctx.accounts.mint_src,
+        &ctx.accounts.mint_des,
         output_amount,
-        ctx.accounts.vault_src.amount,
-        ctx.accounts.vault_des.amount,
     )?;
-
     require!(
-        amount_in <= max_input_amount,
+        input_amount <= max_input_amount,
         ErrorCode::InsufficientInputAmount,
     );
 
-    swap(ctx, amount_in, output_amount)?;
+    swap(ctx, input_amount, amount_in, amount_out)?;
 
     Ok(())
 }
 
-fn get_amount_out(
-    config: &Config,
-    amount_in: u64,
-    reserve_in: u64,
-    reserve_out: u64,
-) -> Result<u64> {
-    require!(
-        reserve_in > 0 && reserve_out > 0,
-        ErrorCode::InsufficientLiquidity,
-    );
-    let amount_in_with_fee = amount_in as u128 * (BASIS_POINTS - config.fee) as u128;
-    let numerator = amount_in_with_fee * reserve_out as u128;
-    let denominator = reserve_in as u128 * BASIS_POINTS as u128 + amount_in_with_fee;
-    Ok((numerator / denominator) as u64)
+// (arriving in the vault, leaving the vault, reaching the user) for `input_amount` leaving the user
+pub fn exact_input_amounts(
+    pool: &Pool,
+    mint_src: &InterfaceAccount<Mint>,
+    mint_des: &InterfaceAccount<Mint>,
+    input_amount: u64,
+) -> Result<(u64, u64, u64)> {
+    let amount_in = input_amount.safe_sub(transfer_fee(mint_src, input_amount)?)?;
+    let amount_out = pool.swap_amount_out(mint_src.key(), amount_in)?;
+    let amount_received = amount_out.safe_sub(transfer_fee(mint_des, amount_out)?)?;
+
+    Ok((amount_in, amount_out, amount_received))
 }
 
-fn get_amount_in(
-    config: &Config,
-    amount_out: u64,
-    reserve_in: u64,
-    reserve_out: u64,
-) -> Result<u64> {
-    require!(
-        reserve_in > 0 && reserve_out > 0,
-        ErrorCode::InsufficientLiquidity,
-    );
-    let numerator = reserve_in as u128 * amount_out as u128 * BASIS_POINTS as u128;
-    let denominator =
-        (reserve_out as u128 - amount_out as u128) * (BASIS_POINTS - config.fee) as u128;
-    Ok((numerator / denominator + 1) as u64)
+// (leaving the user, arriving in the vault, leaving the vault) for `output_amount` reaching the user
+pub fn exact_output_amounts(
+    pool: &Pool,
+    mint_src: &InterfaceAccount<Mint>,
+    mint_des: &InterfaceAccount<Mint>,
+    output_amount: u64,
+) -> Result<(u64, u64, u64)> {
+    let (_, reserve_out) = pool.reserves_for(mint_src.key());
+    let amount_out = output_amount.safe_add(inverse_transfer_fee(mint_des, output_amount)?)?;
+    require!(amount_out < reserve_out, ErrorCode::InsufficientLiquidity);
+    let amount_in = pool.swap_amount_in(mint_src.key(), amount_out)?;
+    let input_amount = amount_in.safe_add(inverse_transfer_fee(mint_src, amount_in)?)?;
+
+    Ok((input_amount, amount_in, amount_out))
 }
 
-fn swap(ctx: Context<Swap>, input_amount: u64, output_amount: u64) -> Result<()> {
-    require!(output_amount > 0, ErrorCode::InsufficientOutputAmount,);
-    require!(input_amount > 0, ErrorCode::InsufficientInputAmount,);
-    require!(
-        output_amount < ctx.accounts.vault_des.amount,
-        ErrorCode::InsufficientLiquidity,
-    );
+// `input_amount` leaves the user and `amount_in` of it arrives in the vault, `amount_out` leaves the vault
+fn swap(ctx: Context<Swap>, input_amount: u64, amount_in: u64, amount_out: u64) -> Result<()> {
+    require!(amount_out > 0, ErrorCode::InsufficientOutputAmount,);
+    require!(amount_in > 0, ErrorCode::InsufficientInputAmount,);
+    let (reserve_in, reserve_out) = ctx.accounts.pool.reserves_for(ctx.accounts.vault_src.mint);
+    require!(amount_out < reserve_out, ErrorCode::InsufficientLiquidity,);
     require!(
         input_amount < ctx.accounts.user_ata_src.amount,
         ErrorCode::InsufficientUserBalance,
     );
 
-    let pool: &Box<Account<Pool>> = &ctx.accounts.pool;
+    let pool: &mut Account<Pool> = &mut ctx.accounts.pool;
+    let (reserve0, reserve1) = pool.sorted_reserves(
+        ctx.accounts.vault_src.mint,
+        reserve_in.safe_add(amount_in)?,
+        reserve_out.safe_sub(amount_out)?,
+    );
+    let timestamp = Clock::get()?.unix_timestamp;
+    ctx.accounts.observations.write(timestamp, pool)?;
+    pool.update(reserve0, reserve1, timestamp)?;
+
+    emit!(events::Swap {
+        pool: pool.key(),
+        user: ctx.accounts.owner.key(),
+        mint_in: ctx.accounts.vault_src.mint,
+        mint_out: ctx.accounts.vault_des.mint,
+        amount_in,
+        amount_out,
+        fee_amount: swap_fee(pool.fee, amount_in)?,
+        reserve0,
+        reserve1,
+    });
+
     let pool_key = pool.key();
     let pool_sign = &[b"authority", pool_key.as_ref(), &[ctx.bumps.pool_authority]];
 
     // transfer tokens from user to vault
-    transfer(
+    transfer_checked(
         CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
+            ctx.accounts.token_program_src.to_account_info(),
+            TransferChecked {
                 from: ctx.accounts.user_ata_src.to_account_info(),
+                mint: ctx.accounts.mint_src.to_account_info(),
                 to: ctx.accounts.vault_src.to_account_info(),
                 authority: ctx.accounts.owner.to_account_info(),
             },
         ),
         input_amount,
+        ctx.accounts.mint_src.decimals,
     )?;
 
     // transfer tokens from vault to user
-    transfer(
+    transfer_checked(
         CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
+            ctx.accounts.token_program_des.to_account_info(),
+            TransferChecked {
                 from: ctx.accounts.vault_des.to_account_info(),
+                mint: ctx.accounts.mint_des.to_account_info(),
                 to: ctx.accounts.user_ata_des.to_account_info(),
                 authority: ctx.accounts.pool_authority.to_account_info(),
             },
         )
         .with_signer(&[pool_sign]),
-        output_amount,
+        amount_out,
+        ctx.accounts.mint_des.decimals,
     )?;
 
     Ok(())
@@ -0,0 +1,126 @@
+use crate::error::ErrorCode;
+use crate::events;
+use crate::state::Observations;
+use crate::state::ObservationsAccount;
+use crate::state::Pool;
+use crate::utils::vault_address;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{
+    transfer_checked, Mint, TokenAccount, TokenInterface, TransferChecked,
+};
+
+#[derive(Accounts)]
+pub struct SyncReserves<'info> {
+    #[account(mut)]
+    pub pool: Box<Account<'info, Pool>>,
+    /// CHECK: authority so 1 acc pass in can derive all other pdas
+    #[account(seeds=[b"authority", pool.key().as_ref()], bump)]
+    pub pool_authority: AccountInfo<'info>,
+
+    // the vaults' owner is their token program
+    #[account(
+        constraint = vault0.key() == vault_address(&pool_authority.key(), &pool.token0, vault0.to_account_info().owner) @ ErrorCode::InvalidVault,
+    )]
+    pub vault0: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        constraint = vault1.key() == vault_address(&pool_authority.key(), &pool.token1, vault1.to_account_info().owner) @ ErrorCode::InvalidVault,
+    )]
+    pub vault1: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, seeds = [b"observations", pool.key().as_ref()], bump = observations.load()?.bump)]
+    pub observations: AccountLoader<'info, Observations>,
+}
+
+// adopts the vault balances as the reserves
+pub fn sync(ctx: Context<SyncReserves>) -> Result<()> {
+    let pool: &mut Account<Pool> = &mut ctx.accounts.pool;
+    let timestamp = Clock::get()?.unix_timestamp;
+    ctx.accounts.observations.write(timestamp, pool)?;
+    pool.update(
+        ctx.accounts.vault0.amount,
+        ctx.accounts.vault1.amount,
+        timestamp,
+    )?;
+
+    emit!(events::Sync {
+        pool: pool.key(),
+        reserve0: pool.reserve0,
+        reserve1: pool.reserve1,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SkimReserves<'info> {
+    pub pool: Box<Account<'info, Pool>>,
+    /// CHECK: authority so 1 acc pass in can derive all other pdas
+    #[account(seeds=[b"authority", pool.key().as_ref()], bump)]
+    pub pool_authority: AccountInfo<'info>,
+
+    #[account(
+        mut,
+        constraint = vault0.key() == vault_address(&pool_authority.key(), &pool.token0, &token_program0.key()) @ ErrorCode::InvalidVault,
+    )]
+    pub vault0: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = vault1.key() == vault_address(&pool_authority.key(), &pool.token1, &token_program1.key()) @ ErrorCode::InvalidVault,
+    )]
+    pub vault1: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, constraint = recipient0.mint == pool.token0 @ ErrorCode::InvalidUserTokenAccount)]
+    pub recipient0: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(mut, constraint = recipient1.mint == pool.token1 @ ErrorCode::InvalidUserTokenAccount)]
+    pub recipient1: Box<InterfaceAccount<'info, TokenAccount>>,
+    #[account(constraint = mint0.key() == pool.token0 @ ErrorCode::InvalidMint, mint::token_program = token_program0)]
+    pub mint0: Box<InterfaceAccount<'info, Mint>>,
+    #[account(constraint = mint1.key() == pool.token1 @ ErrorCode::InvalidMint, mint::token_program = token_program1)]
+    pub mint1: Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_program0: Interface<'info, TokenInterface>,
+    pub token_program1: Interface<'info, TokenInterface>,
+}
+
+// sends whatever the vaults hold beyond the reserves to the recipients
+pub fn skim(ctx: Context<SkimReserves>) -> Result<()> {
+    let pool: &Account<Pool> = &ctx.accounts.pool;
+    let excess0 = ctx.accounts.vault0.amount.saturating_sub(pool.reserve0);
+    let excess1 = ctx.accounts.vault1.amount.saturating_sub(pool.reserve1);
+
+    let pool_key = pool.key();
+    let pool_sign = &[b"authority", pool_key.as_ref(), &[ctx.bumps.pool_authority]];
+
+    if excess0 > 0 {
+        transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program0.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault0.to_account_info(),
+                    mint: ctx.accounts.mint0.to_account_info(),
+                    to: ctx.accounts.recipient0.to_account_info(),
+                    authority: ctx.accounts.pool_authority.to_account_info(),
+                },
+            )
+            .with_signer(&[pool_sign]),
+            excess0,
+            ctx.accounts.mint0.decimals,
+        )?;
+    }
+    if excess1 > 0 {
+        transfer_checked(
+            CpiContext::new(
+                ctx.accounts.token_program1.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault1.to_account_info(),
+                    mint: ctx.accounts.mint1.to_account_info(),
+                    to: ctx.accounts.recipient1.to_account_info(),
+                    authority: ctx.accounts.pool_authority.to_account_info(),
+                },
+            )
+            .with_signer(&[pool_sign]),
+            excess1,
+            ctx.accounts.mint1.decimals,
+        )?;
+    }
+
+    Ok(())
+}
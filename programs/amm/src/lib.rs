@@ -1,10 +1,18 @@
 use anchor_lang::prelude::*;
 use instructions::*;
+use state::{CurveParams, Observation};
 
 pub mod constant;
+pub mod constant_product;
+pub mod curve;
 pub mod error;
+pub mod events;
 pub mod instructions;
+pub mod math;
+pub mod stable_swap;
 pub mod state;
+pub mod utils;
+pub mod weighted_product;
 
 declare_id!("4tPXqXq5WiLpHPaJSRhpA1we5GhCpQrK3wpdRZFNoFQS");
 
@@ -16,16 +24,74 @@ pub mod amm {
         instructions::initialize(ctx, fee_to, fee)
     }
 
+    pub fn propose_owner(ctx: Context<ProposeOwner>, new_owner: Pubkey) -> Result<()> {
+        instructions::propose_owner(ctx, new_owner)
+    }
+
+    pub fn accept_owner(ctx: Context<AcceptOwner>) -> Result<()> {
+        instructions::accept_owner(ctx)
+    }
+
+    pub fn renounce_owner(ctx: Context<RenounceOwner>) -> Result<()> {
+        instructions::renounce_owner(ctx)
+    }
+
     pub fn set_fee_to(ctx: Context<SetFeeTo>, new_fee_to: Pubkey) -> Result<()> {
         instructions::set_fee_to(ctx, new_fee_to)
     }
 
-    pub fn set_fee(ctx: Context<SetFee>, new_fee: u64) -> Result<()> {
-        instructions::set_fee(ctx, new_fee)
+    pub fn set_protocol_fee_share(
+        ctx: Context<SetProtocolFeeShare>,
+        protocol_fee_share: u64,
+    ) -> Result<()> {
+        instructions::set_protocol_fee_share(ctx, protocol_fee_share)
+    }
+
+    pub fn claim_protocol_fees(ctx: Context<ClaimProtocolFees>) -> Result<()> {
+        instructions::claim_protocol_fees(ctx)
     }
 
-    pub fn create_pool(ctx: Context<CreatePool>) -> Result<()> {
-        instructions::create_pool(ctx)
+    pub fn enable_fee_tier(ctx: Context<SetFeeTier>, fee: u64) -> Result<()> {
+        instructions::enable_fee_tier(ctx, fee)
+    }
+
+    pub fn disable_fee_tier(ctx: Context<SetFeeTier>, fee: u64) -> Result<()> {
+        instructions::disable_fee_tier(ctx, fee)
+    }
+
+    pub fn set_fee(ctx: Context<SetFee>, fee: u64) -> Result<()> {
+        instructions::set_fee(ctx, fee)
+    }
+
+    pub fn set_paused(ctx: Context<SetPaused>, paused: bool) -> Result<()> {
+        instructions::set_paused(ctx, paused)
+    }
+
+    pub fn set_pool_paused(ctx: Context<SetPoolPaused>, paused: bool) -> Result<()> {
+        instructions::set_pool_paused(ctx, paused)
+    }
+
+    pub fn create_pool(ctx: Context<CreatePool>, fee: u64, curve: CurveParams) -> Result<()> {
+        instructions::create_pool(ctx, fee, curve)
+    }
+
+    pub fn ramp_amp(ctx: Context<RampAmp>, future_amp: u64, future_amp_time: i64) -> Result<()> {
+        instructions::ramp_amp(ctx, future_amp, future_amp_time)
+    }
+
+    pub fn set_pool_creation_restricted(
+        ctx: Context<SetPoolCreation>,
+        restricted: bool,
+    ) -> Result<()> {
+        instructions::set_pool_creation_restricted(ctx, restricted)
+    }
+
+    pub fn set_pool_creation_fee(
+        ctx: Context<SetPoolCreation>,
+        mint: Pubkey,
+        fee: u64,
+    ) -> Result<()> {
+        instructions::set_pool_creation_fee(ctx, mint, fee)
     }
 
     pub fn add_liquidity(
@@ -34,6 +100,7 @@ pub mod amm {
         amount1_desired: u64,
         amount0_min: u64,
         amount1_min: u64,
+        deadline: Option<i64>,
     ) -> Result<()> {
         instructions::add_liquidity(
             ctx,
@@ -41,6 +108,7 @@ pub mod amm {
             amount1_desired,
             amount0_min,
             amount1_min,
+            deadline,
         )
     }
 
@@ -49,23 +117,117 @@ pub mod amm {
         liquidity: u64,
         amount0_min: u64,
         amount1_min: u64,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        instructions::remove_liquidity(ctx, liquidity, amount0_min, amount1_min, deadline)
+    }
+
+    pub fn add_liquidity_single(
+        ctx: Context<SingleLiquidityOperation>,
+        input_amount: u64,
+        min_liquidity: u64,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        instructions::add_liquidity_single(ctx, input_amount, min_liquidity, deadline)
+    }
+
+    pub fn remove_liquidity_single(
+        ctx: Context<SingleLiquidityOperation>,
+        liquidity: u64,
+        min_amount_out: u64,
+        deadline: Option<i64>,
     ) -> Result<()> {
-        instructions::remove_liquidity(ctx, liquidity, amount0_min, amount1_min)
+        instructions::remove_liquidity_single(ctx, liquidity, min_amount_out, deadline)
     }
 
     pub fn swap_exact_input(
         ctx: Context<Swap>,
         input_amount: u64,
         min_output_amount: u64,
+        deadline: Option<i64>,
     ) -> Result<()> {
-        instructions::swap_exact_input(ctx, input_amount, min_output_amount)
+        instructions::swap_exact_input(ctx, input_amount, min_output_amount, deadline)
     }
 
     pub fn swap_exact_output(
         ctx: Context<Swap>,
         output_amount: u64,
         max_input_amount: u64,
+        deadline: Option<i64>,
     ) -> Result<()> {
-        instructions::swap_exact_output(ctx, output_amount, max_input_amount)
+        instructions::swap_exact_output(ctx, output_amount, max_input_amount, deadline)
+    }
+
+    pub fn swap_exact_input_route<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SwapRoute<'info>>,
+        input_amount: u64,
+        min_output_amount: u64,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        instructions::swap_exact_input_route(ctx, input_amount, min_output_amount, deadline)
+    }
+
+    pub fn swap_exact_output_route<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SwapRoute<'info>>,
+        output_amount: u64,
+        max_input_amount: u64,
+        deadline: Option<i64>,
+    ) -> Result<()> {
+        instructions::swap_exact_output_route(ctx, output_amount, max_input_amount, deadline)
+    }
+
+    pub fn sync(ctx: Context<SyncReserves>) -> Result<()> {
+        instructions::sync(ctx)
+    }
+
+    pub fn skim(ctx: Context<SkimReserves>) -> Result<()> {
+        instructions::skim(ctx)
+    }
+
+    pub fn flash_swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, FlashSwap<'info>>,
+        amount0_out: u64,
+        amount1_out: u64,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        instructions::flash_swap(ctx, amount0_out, amount1_out, data)
+    }
+
+    pub fn quote_exact_input(ctx: Context<QuoteSwap>, input_amount: u64) -> Result<u64> {
+        instructions::quote_exact_input(ctx, input_amount)
+    }
+
+    pub fn quote_exact_output(ctx: Context<QuoteSwap>, output_amount: u64) -> Result<u64> {
+        instructions::quote_exact_output(ctx, output_amount)
+    }
+
+    pub fn quote_add_liquidity(
+        ctx: Context<QuoteLiquidity>,
+        amount0_desired: u64,
+        amount1_desired: u64,
+    ) -> Result<LiquidityQuote> {
+        instructions::quote_add_liquidity(ctx, amount0_desired, amount1_desired)
+    }
+
+    pub fn quote_remove_liquidity(
+        ctx: Context<QuoteLiquidity>,
+        liquidity: u64,
+    ) -> Result<LiquidityQuote> {
+        instructions::quote_remove_liquidity(ctx, liquidity)
+    }
+
+    pub fn get_twap(ctx: Context<GetTwap>, start: PriceObservation) -> Result<Twap> {
+        instructions::get_twap(ctx, start)
+    }
+
+    pub fn increase_observation_cardinality(
+        ctx: Context<IncreaseObservationCardinality>,
+        cardinality_next: u16,
+    ) -> Result<()> {
+        instructions::increase_observation_cardinality(ctx, cardinality_next)
+    }
+
+    pub fn consult(ctx: Context<Consult>, seconds_agos: Vec<u32>) -> Result<Vec<Observation>> {
+        instructions::consult(ctx, seconds_agos)
     }
 }
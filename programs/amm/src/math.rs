@@ -0,0 +1,182 @@
+use crate::constant::BASIS_POINTS;
+use crate::error::ErrorCode;
+use anchor_lang::prelude::*;
+use fixed::types::{I64F64, U128F0, U64F64};
+
+pub use u256::U256;
+
+// kept apart from the anchor prelude, whose `Result` the macro would pick up
+#[allow(clippy::assign_op_pattern, clippy::manual_div_ceil)]
+mod u256 {
+    uint::construct_uint! {
+        // intermediate values of the StableSwap invariant, which reach the cube of a sum of reserves
+        pub struct U256(4);
+    }
+}
+
+// Checked arithmetic for amounts and reserves. Overflow, underflow and lossy narrowing surface as
+// `MathOverflow` and a zero divisor as `DivisionByZero`, never as a panic or a wrapped value.
+pub trait CheckedMath: Sized {
+    fn safe_add(self, rhs: Self) -> Result<Self>;
+    fn safe_sub(self, rhs: Self) -> Result<Self>;
+    fn safe_mul(self, rhs: Self) -> Result<Self>;
+    fn safe_div(self, rhs: Self) -> Result<Self>;
+    fn safe_rem(self, rhs: Self) -> Result<Self>;
+}
+
+macro_rules! impl_checked_math {
+    ($($t:ty),*) => {$(
+        impl CheckedMath for $t {
+            fn safe_add(self, rhs: Self) -> Result<Self> {
+                self.checked_add(rhs).ok_or_else(|| error!(ErrorCode::MathOverflow))
+            }
+
+            fn safe_sub(self, rhs: Self) -> Result<Self> {
+                self.checked_sub(rhs).ok_or_else(|| error!(ErrorCode::MathOverflow))
+            }
+
+            fn safe_mul(self, rhs: Self) -> Result<Self> {
+                self.checked_mul(rhs).ok_or_else(|| error!(ErrorCode::MathOverflow))
+            }
+
+            fn safe_div(self, rhs: Self) -> Result<Self> {
+                self.checked_div(rhs).ok_or_else(|| error!(ErrorCode::DivisionByZero))
+            }
+
+            fn safe_rem(self, rhs: Self) -> Result<Self> {
+                self.checked_rem(rhs).ok_or_else(|| error!(ErrorCode::DivisionByZero))
+            }
+        }
+    )*};
+}
+
+impl_checked_math!(u64, u128, U256, U64F64, I64F64);
+
+pub fn to_u64(value: u128) -> Result<u64> {
+    u64::try_from(value).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+pub fn to_u128(value: U256) -> Result<u128> {
+    u128::try_from(value).map_err(|_| error!(ErrorCode::MathOverflow))
+}
+
+// floor of the square root
+pub fn sqrt(value: u128) -> u128 {
+    U128F0::from_num(value).sqrt().to_num::<u128>()
+}
+
+// numerator / denominator as Q64.64 bits, both shifted down first when the numerator would overflow
+// on the way and saturating where the ratio does not fit, as a price of a very imbalanced pool can
+pub fn q64_ratio(numerator: U256, denominator: U256) -> u128 {
+    let shift = numerator.bits().saturating_sub(256 - 64);
+    let (numerator, denominator) = (numerator >> shift, denominator >> shift);
+    if denominator.is_zero() {
+        return u128::MAX;
+    }
+    u128::try_from((numerator << 64) / denominator).unwrap_or(u128::MAX)
+}
+
+// log2 of a positive Q64.64 number: the integer part from its leading bit, the fraction bit by bit
+// from the repeated squares of the mantissa
+pub fn log2(value: U64F64) -> Result<I64F64> {
+    let int = value
+        .checked_int_log2()
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))?;
+    // mantissa within [1, 2)
+    let mut mantissa = if int >= 0 {
+        value >> int as u32
+    } else {
+        value << int.unsigned_abs()
+    };
+    let mut result = I64F64::from_num(int);
+    let mut bit = I64F64::from_bits(1 << 63);
+    for _ in 0..64 {
+        // below 2, so its square fits
+        mantissa = mantissa.safe_mul(mantissa)?;
+        if mantissa >= 2 {
+            mantissa >>= 1;
+            result = result.safe_add(bit)?;
+        }
+        bit >>= 1;
+    }
+
+    Ok(result)
+}
+
+// 2^value in Q64.64, zero below the smallest number it holds
+pub fn exp2(value: I64F64) -> Result<U64F64> {
+    if value < -64 {
+        return Ok(U64F64::ZERO);
+    }
+    require!(value < 64, ErrorCode::MathOverflow);
+    let int = value.floor();
+    // 2^frac = e^(frac·ln 2), whose Taylor series is below a Q64.64 unit after some 20 terms
+    let frac = U64F64::from_bits(value.safe_sub(int)?.to_bits() as u128);
+    let exponent = frac.safe_mul(U64F64::LN_2)?;
+    let (mut result, mut term) = (U64F64::ONE, U64F64::ONE);
+    for k in 1u64.. {
+        term = term.safe_mul(exponent)?.safe_div(U64F64::from_num(k))?;
+        if term == 0 {
+            break;
+        }
+        result = result.safe_add(term)?;
+    }
+
+    // below 2, so shifting by up to 63 fits
+    let int = int.to_num::<i32>();
+    Ok(if int >= 0 {
+        result << int as u32
+    } else {
+        result >> int.unsigned_abs()
+    })
+}
+
+// base^exponent as 2^(exponent·log2 base), accurate to far better than 2^-48 for the weights and
+// reserves of a weighted pool
+pub fn pow(base: U64F64, exponent: U64F64) -> Result<U64F64> {
+    if base == 0 {
+        return Ok(U64F64::ZERO);
+    }
+    let exponent =
+        I64F64::checked_from_num(exponent).ok_or_else(|| error!(ErrorCode::MathOverflow))?;
+    exp2(log2(base)?.safe_mul(exponent)?)
+}
+
+// LP owed to the protocol for the growth from `root_k_last` to `root_k` of an invariant that scales
+// linearly with the reserves, √k or D
+pub fn invariant_growth_liquidity(
+    protocol_fee_share: u64,
+    root_k_last: u128,
+    root_k: u128,
+    lp_supply: u64,
+) -> Result<u64> {
+    if root_k <= root_k_last {
+        return Ok(0);
+    }
+
+    // share φ of the growth: supply * (√k - √k_last) * φ / ((1 - φ) * √k + φ * √k_last)
+    let share = protocol_fee_share as u128;
+    let numerator = (lp_supply as u128).safe_mul(root_k.safe_sub(root_k_last)?)?;
+    let denominator = (BASIS_POINTS as u128)
+        .safe_sub(share)?
+        .safe_mul(root_k)?
+        .safe_add(share.safe_mul(root_k_last)?)?;
+    // split so that `numerator * share` cannot overflow
+    to_u64(
+        numerator.safe_div(denominator)?.safe_mul(share)?.safe_add(
+            numerator
+                .safe_rem(denominator)?
+                .safe_mul(share)?
+                .safe_div(denominator)?,
+        )?,
+    )
+}
+
+// part of `amount_in` kept by the pool
+pub fn swap_fee(fee: u64, amount_in: u64) -> Result<u64> {
+    to_u64(
+        (amount_in as u128)
+            .safe_mul(fee as u128)?
+            .safe_div(BASIS_POINTS as u128)?,
+    )
+}
@@ -0,0 +1,228 @@
+// StableSwap invariant of a two-token pool, as in Curve's pools:
+//
+//   A·n·(x + y) + D = A·n·D + D³ / (n²·x·y), n = 2
+//
+// D is the total the reserves would add up to at balance and A the amplification coefficient: the
+// higher A, the longer the curve stays close to the constant sum x + y = D around balance before
+// falling back to the constant product. D and y are solved for with Newton's method in 256 bits.
+use crate::constant::BASIS_POINTS;
+use crate::constant_product;
+use crate::error::ErrorCode;
+use crate::math::{q64_ratio, to_u128, to_u64, CheckedMath, U256};
+use anchor_lang::prelude::*;
+
+const N_COINS: u64 = 2;
+// Newton's method converges within a few dozen iterations even for the most imbalanced reserves
+const MAX_ITERATIONS: usize = 255;
+
+// D of the reserves `x` and `y`, zero for an empty pool
+pub fn compute_d(amp: u64, x: u64, y: u64) -> Result<u128> {
+    if x == 0 && y == 0 {
+        return Ok(0);
+    }
+    require!(x > 0 && y > 0, ErrorCode::InsufficientLiquidity);
+
+    let n = U256::from(N_COINS);
+    let (x, y) = (U256::from(x), U256::from(y));
+    let sum = x.safe_add(y)?;
+    let n_squared_xy = n.safe_mul(n)?.safe_mul(x)?.safe_mul(y)?;
+    let ann = U256::from(amp).safe_mul(n)?;
+    let mut d = sum;
+    for _ in 0..MAX_ITERATIONS {
+        // D³ / (n²·x·y) in a single division, truncating on the way makes the iteration cycle
+        let d_p = d.safe_mul(d)?.safe_mul(d)?.safe_div(n_squared_xy)?;
+        let d_prev = d;
+        // D = (A·n·S + n·D_P)·D / ((A·n - 1)·D + (n + 1)·D_P)
+        let numerator = ann.safe_mul(sum)?.safe_add(d_p.safe_mul(n)?)?.safe_mul(d)?;
+        let denominator = ann
+            .safe_sub(U256::one())?
+            .safe_mul(d)?
+            .safe_add(n.safe_add(U256::one())?.safe_mul(d_p)?)?;
+        d = numerator.safe_div(denominator)?;
+        if abs_diff(d, d_prev) <= U256::one() {
+            return to_u128(d);
+        }
+    }
+
+    err!(ErrorCode::InvariantNotConverged)
+}
+
+// the reserve of one token that keeps D unchanged when the other token's reserve is `x`
+pub fn compute_y(amp: u64, x: u64, d: u128) -> Result<u64> {
+    require!(x > 0, ErrorCode::InsufficientLiquidity);
+
+    let n = U256::from(N_COINS);
+    let (x, d) = (U256::from(x), U256::from(d));
+    let ann = U256::from(amp).safe_mul(n)?;
+    // y² + (b - D)·y = c with b = x + D / (A·n) and c = D³ / (n²·x·A·n)
+    let b = x.safe_add(d.safe_div(ann)?)?;
+    let c = d
+        .safe_mul(d)?
+        .safe_mul(d)?
+        .safe_div(n.safe_mul(n)?.safe_mul(x)?.safe_mul(ann)?)?;
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let y_prev = y;
+        // y = (y² + c) / (2·y + b - D)
+        y = y
+            .safe_mul(y)?
+            .safe_add(c)?
+            .safe_div(y.safe_mul(n)?.safe_add(b)?.safe_sub(d)?)?;
+        if abs_diff(y, y_prev) <= U256::one() {
+            return to_u64(to_u128(y)?);
+        }
+    }
+
+    err!(ErrorCode::InvariantNotConverged)
+}
+
+// As with the constant product the fee is taken out of the input before it is priced. The reserve
+// solved for is only exact to a unit, so both directions give the pool one unit of it.
+pub fn get_amount_out(
+    amp: u64,
+    fee: u64,
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+) -> Result<u64> {
+    require!(
+        reserve_in > 0 && reserve_out > 0,
+        ErrorCode::InsufficientLiquidity,
+    );
+    let amount_in_after_fee = to_u64(
+        (amount_in as u128)
+            .safe_mul(BASIS_POINTS.safe_sub(fee)? as u128)?
+            .safe_div(BASIS_POINTS as u128)?,
+    )?;
+    let d = compute_d(amp, reserve_in, reserve_out)?;
+    let reserve_out_after = compute_y(amp, reserve_in.safe_add(amount_in_after_fee)?, d)?;
+
+    Ok(reserve_out
+        .saturating_sub(reserve_out_after)
+        .saturating_sub(1))
+}
+
+pub fn get_amount_in(
+    amp: u64,
+    fee: u64,
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+) -> Result<u64> {
+    require!(
+        reserve_in > 0 && reserve_out > 0,
+        ErrorCode::InsufficientLiquidity,
+    );
+    let d = compute_d(amp, reserve_in, reserve_out)?;
+    let reserve_in_after = compute_y(amp, reserve_out.safe_sub(amount_out)?, d)?;
+    let amount_in_after_fee = reserve_in_after.saturating_sub(reserve_in).safe_add(1)?;
+
+    // gross up by the fee, rounding up
+    let fee_complement = BASIS_POINTS.safe_sub(fee)? as u128;
+    to_u64(
+        (amount_in_after_fee as u128)
+            .safe_mul(BASIS_POINTS as u128)?
+            .safe_add(fee_complement.safe_sub(1)?)?
+            .safe_div(fee_complement)?,
+    )
+}
+
+// Part of `amount_in` to swap so that the rest and the output are in the ratio of the reserves after
+// the swap. Those reserves lie on the line x = r_in + γ·s, y = r_out·(r_in + s) / (r_in + a), and
+// on the curve of the D the swap keeps, so D is computed once and Newton's method walks s along the
+// line to where it crosses the curve. A·n·(x + y) + D - A·n·D - D³ / (n²·x·y) rises and bends down
+// along the line, so from the constant product's zap amount Newton's method overshoots at most once
+// and then approaches the crossing from below, but for the rounding of x and y.
+pub fn get_zap_amount(
+    amp: u64,
+    fee: u64,
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+) -> Result<u64> {
+    require!(
+        reserve_in > 0 && reserve_out > 0,
+        ErrorCode::InsufficientLiquidity,
+    );
+    let n = U256::from(N_COINS);
+    let d = U256::from(compute_d(amp, reserve_in, reserve_out)?);
+    let d_cubed = d.safe_mul(d)?.safe_mul(d)?;
+    let ann = U256::from(amp).safe_mul(n)?;
+    let ann_d = ann.safe_mul(d)?;
+    let basis_points = U256::from(BASIS_POINTS);
+    let fee_complement = U256::from(BASIS_POINTS.safe_sub(fee)?);
+    let total_in = U256::from(reserve_in).safe_add(U256::from(amount_in))?;
+    // slopes of x and y along the line, times B·(r_in + a)
+    let scale = basis_points.safe_mul(total_in)?;
+    let dx = fee_complement.safe_mul(total_in)?;
+    let dy = basis_points.safe_mul(U256::from(reserve_out))?;
+
+    let mut zap_amount = constant_product::get_zap_amount(fee, amount_in, reserve_in)?;
+    // the largest amount known to fall short of the curve
+    let mut below = 0;
+    for _ in 0..MAX_ITERATIONS {
+        let swapped = U256::from(zap_amount);
+        let x = U256::from(reserve_in)
+            .safe_add(swapped.safe_mul(fee_complement)?.safe_div(basis_points)?)?;
+        // rounded up, y stays positive
+        let y = U256::from(reserve_out)
+            .safe_mul(U256::from(reserve_in).safe_add(swapped)?)?
+            .safe_add(total_in.safe_sub(U256::one())?)?
+            .safe_div(total_in)?;
+        let d_p = d_cubed.safe_div(n.safe_mul(n)?.safe_mul(x)?.safe_mul(y)?)?;
+        let lhs = ann.safe_mul(x.safe_add(y)?)?.safe_add(d)?;
+        let rhs = ann_d.safe_add(d_p)?;
+        let slope = ann
+            .safe_mul(dx.safe_add(dy)?)?
+            .safe_add(d_p.safe_mul(dx)?.safe_div(x)?)?
+            .safe_add(d_p.safe_mul(dy)?.safe_div(y)?)?;
+        if lhs <= rhs {
+            // short of the curve, Newton's step only passes it by rounding
+            let step = rhs.safe_sub(lhs)?.safe_mul(scale)?.safe_div(slope)?;
+            let step = to_u64(to_u128(step.min(U256::from(amount_in)))?)?;
+            if step == 0 || zap_amount == amount_in {
+                return Ok(zap_amount);
+            }
+            below = zap_amount;
+            zap_amount = zap_amount.saturating_add(step).min(amount_in);
+        } else {
+            // past the curve, the step rounded up lands short of it or on what is known to be
+            let step = lhs
+                .safe_sub(rhs)?
+                .safe_mul(scale)?
+                .safe_add(slope.safe_sub(U256::one())?)?
+                .safe_div(slope)?;
+            let step = to_u64(to_u128(step.min(U256::from(amount_in)))?)?;
+            if zap_amount.saturating_sub(step) <= below {
+                return Ok(below);
+            }
+            zap_amount -= step;
+        }
+    }
+
+    err!(ErrorCode::InvariantNotConverged)
+}
+
+fn abs_diff(a: U256, b: U256) -> U256 {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+// spot prices of token0 in token1 and of token1 in token0, Q64.64 fixed point: the ratio of the
+// invariant's partial derivatives, (4·A·n·x·y + D³/x) / (4·A·n·x·y + D³/y) for token0
+pub fn spot_prices(amp: u64, x: u64, y: u64) -> Result<(u128, u128)> {
+    let d = U256::from(compute_d(amp, x, y)?);
+    let (x, y) = (U256::from(x), U256::from(y));
+    let ann = U256::from(amp).safe_mul(U256::from(N_COINS))?;
+    let d_cubed = d.safe_mul(d)?.safe_mul(d)?;
+    let common = ann.safe_mul(U256::from(4))?.safe_mul(x)?.safe_mul(y)?;
+    let numerator = common.safe_add(d_cubed.safe_div(x)?)?;
+    let denominator = common.safe_add(d_cubed.safe_div(y)?)?;
+    Ok((
+        q64_ratio(numerator, denominator),
+        q64_ratio(denominator, numerator),
+    ))
+}
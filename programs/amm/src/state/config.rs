@@ -1,4 +1,4 @@
-use crate::constant::BASIS_POINTS;
+use crate::constant::{DEFAULT_PROTOCOL_FEE_SHARE, MAX_PROTOCOL_FEE_SHARE};
 use crate::error::ErrorCode;
 use anchor_lang::prelude::*;
 
@@ -7,26 +7,53 @@ use anchor_lang::prelude::*;
 pub struct Config {
     pub bump: u8,
     pub owner: Pubkey,
+    // proposed by the owner, becomes the owner once it accepts, unset when nothing is proposed
+    pub pending_owner: Pubkey,
+    // receiver of the protocol fee, unset (the default pubkey) switches the protocol fee off
     pub fee_to: Pubkey,
-    pub fee: u64,
+    // share of the LP fee growth minted to `fee_to`, in basis points
+    pub protocol_fee_share: u64,
+    // only the owner can create pools while set
+    pub pool_creation_restricted: bool,
+    // charged to anyone but the owner creating a pool and sent to `fee_to`, in lamports when
+    // `pool_creation_fee_mint` is unset and in tokens of that mint otherwise
+    pub pool_creation_fee: u64,
+    pub pool_creation_fee_mint: Pubkey,
+    // halts swaps and deposits in every pool, withdrawals to both tokens stay open
+    pub paused: bool,
 }
 
 impl Config {
-    pub fn initialize(&mut self, bump: u8, owner: Pubkey, fee_to: Pubkey, fee: u64) -> Result<()> {
-        require!(fee < BASIS_POINTS, ErrorCode::InvalidFee);
-
+    pub fn initialize(&mut self, bump: u8, owner: Pubkey, fee_to: Pubkey) -> Result<()> {
         self.bump = bump;
         self.owner = owner;
+        self.pending_owner = Pubkey::default();
         self.fee_to = fee_to;
-        self.fee = fee;
+        self.protocol_fee_share = DEFAULT_PROTOCOL_FEE_SHARE;
+        self.pool_creation_restricted = false;
+        self.pool_creation_fee = 0;
+        self.pool_creation_fee_mint = Pubkey::default();
+        self.paused = false;
+
+        Ok(())
+    }
 
+    // proposing the default pubkey withdraws a pending proposal
+    pub fn propose_owner(&mut self, pending_owner: Pubkey) -> Result<()> {
+        self.pending_owner = pending_owner;
         Ok(())
     }
 
-    pub fn set_fee(&mut self, fee: u64) -> Result<()> {
-        require!(fee < BASIS_POINTS, ErrorCode::InvalidFee);
+    pub fn accept_owner(&mut self) -> Result<()> {
+        self.owner = self.pending_owner;
+        self.pending_owner = Pubkey::default();
+        Ok(())
+    }
 
-        self.fee = fee;
+    // leaves the config without an owner for good, every owner-only instruction is disabled
+    pub fn renounce_owner(&mut self) -> Result<()> {
+        self.owner = Pubkey::default();
+        self.pending_owner = Pubkey::default();
         Ok(())
     }
 
@@ -34,4 +61,34 @@ impl Config {
         self.fee_to = fee_to;
         Ok(())
     }
+
+    pub fn set_protocol_fee_share(&mut self, protocol_fee_share: u64) -> Result<()> {
+        require!(
+            protocol_fee_share <= MAX_PROTOCOL_FEE_SHARE,
+            ErrorCode::InvalidProtocolFeeShare
+        );
+
+        self.protocol_fee_share = protocol_fee_share;
+        Ok(())
+    }
+
+    pub fn set_pool_creation_restricted(&mut self, restricted: bool) -> Result<()> {
+        self.pool_creation_restricted = restricted;
+        Ok(())
+    }
+
+    pub fn set_pool_creation_fee(&mut self, mint: Pubkey, fee: u64) -> Result<()> {
+        self.pool_creation_fee_mint = mint;
+        self.pool_creation_fee = fee;
+        Ok(())
+    }
+
+    pub fn set_paused(&mut self, paused: bool) -> Result<()> {
+        self.paused = paused;
+        Ok(())
+    }
+
+    pub fn is_protocol_fee_on(&self) -> bool {
+        self.fee_to != Pubkey::default() && self.protocol_fee_share > 0
+    }
 }
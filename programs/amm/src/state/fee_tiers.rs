@@ -0,0 +1,40 @@
+use crate::constant::{BASIS_POINTS, MAX_FEE_TIERS};
+use crate::error::ErrorCode;
+use anchor_lang::prelude::*;
+
+// swap fees, in basis points, that new pools may be created with
+#[account]
+#[derive(InitSpace)]
+pub struct FeeTiers {
+    pub bump: u8,
+    #[max_len(MAX_FEE_TIERS)]
+    pub fees: Vec<u64>,
+}
+
+impl FeeTiers {
+    pub fn initialize(&mut self, bump: u8) {
+        self.bump = bump;
+        self.fees = Vec::new();
+    }
+
+    pub fn is_enabled(&self, fee: u64) -> bool {
+        self.fees.contains(&fee)
+    }
+
+    pub fn enable(&mut self, fee: u64) -> Result<()> {
+        require!(fee < BASIS_POINTS, ErrorCode::InvalidFee);
+        require!(!self.is_enabled(fee), ErrorCode::FeeTierAlreadyEnabled);
+        require!(self.fees.len() < MAX_FEE_TIERS, ErrorCode::TooManyFeeTiers);
+
+        self.fees.push(fee);
+        Ok(())
+    }
+
+    // pools already created with `fee` keep it, only new pools are affected
+    pub fn disable(&mut self, fee: u64) -> Result<()> {
+        require!(self.is_enabled(fee), ErrorCode::FeeTierNotEnabled);
+
+        self.fees.retain(|enabled| *enabled != fee);
+        Ok(())
+    }
+}
@@ -1,5 +1,9 @@
 pub mod config;
+pub mod fee_tiers;
+pub mod observations;
 pub mod pool;
 
 pub use config::*;
+pub use fee_tiers::*;
+pub use observations::*;
 pub use pool::*;
@@ -0,0 +1,296 @@
+use crate::constant::MAX_OBSERVATION_CARDINALITY;
+use crate::error::ErrorCode;
+use crate::state::Pool;
+use anchor_lang::prelude::*;
+use fixed::types::U128F0;
+use std::mem;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Observation {
+    pub timestamp: i64,
+    // Q64.64 price accumulators, as on `Pool`
+    pub price0_cumulative: u128,
+    pub price1_cumulative: u128,
+    // sqrt(reserve0 * reserve1) accumulated per second
+    pub liquidity_cumulative: u128,
+    pub initialized: bool,
+}
+
+impl Observation {
+    // the observation as it would be at `timestamp` given the pool's reserves held since `self`
+    pub fn transform(&self, timestamp: i64, pool: &Pool) -> Result<Observation> {
+        let time_elapsed = timestamp.saturating_sub(self.timestamp) as u128;
+        let mut next = Observation {
+            timestamp,
+            initialized: true,
+            ..*self
+        };
+
+        if let Some((price0, price1)) = pool.spot_prices()? {
+            let liquidity = U128F0::from_num(pool.reserve0 as u128 * pool.reserve1 as u128)
+                .sqrt()
+                .to_num::<u128>();
+            next.price0_cumulative = next
+                .price0_cumulative
+                .wrapping_add(price0.wrapping_mul(time_elapsed));
+            next.price1_cumulative = next
+                .price1_cumulative
+                .wrapping_add(price1.wrapping_mul(time_elapsed));
+            next.liquidity_cumulative = next
+                .liquidity_cumulative
+                .wrapping_add(liquidity.wrapping_mul(time_elapsed));
+        }
+        Ok(next)
+    }
+
+    // linear interpolation between two observations, `timestamp` must lie between them
+    fn interpolate(&self, after: &Observation, timestamp: i64) -> Observation {
+        let span = (after.timestamp - self.timestamp) as u128;
+        let elapsed = (timestamp - self.timestamp) as u128;
+        let lerp = |before: u128, after: u128| {
+            before.wrapping_add(after.wrapping_sub(before) / span * elapsed)
+        };
+
+        Observation {
+            timestamp,
+            price0_cumulative: lerp(self.price0_cumulative, after.price0_cumulative),
+            price1_cumulative: lerp(self.price1_cumulative, after.price1_cumulative),
+            liquidity_cumulative: lerp(self.liquidity_cumulative, after.liquidity_cumulative),
+            initialized: true,
+        }
+    }
+}
+
+// `Observation` as stored in the ring buffer, plain old data so that the buffer is read and written
+// in place
+#[zero_copy]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ObservationSlot {
+    pub price0_cumulative: u128,
+    pub price1_cumulative: u128,
+    pub liquidity_cumulative: u128,
+    pub timestamp: i64,
+    // a bool is not plain old data
+    pub initialized: u8,
+    pub padding: [u8; 7],
+}
+
+impl From<Observation> for ObservationSlot {
+    fn from(observation: Observation) -> Self {
+        ObservationSlot {
+            price0_cumulative: observation.price0_cumulative,
+            price1_cumulative: observation.price1_cumulative,
+            liquidity_cumulative: observation.liquidity_cumulative,
+            timestamp: observation.timestamp,
+            initialized: observation.initialized as u8,
+            padding: [0; 7],
+        }
+    }
+}
+
+impl From<ObservationSlot> for Observation {
+    fn from(slot: ObservationSlot) -> Self {
+        Observation {
+            timestamp: slot.timestamp,
+            price0_cumulative: slot.price0_cumulative,
+            price1_cumulative: slot.price1_cumulative,
+            liquidity_cumulative: slot.liquidity_cumulative,
+            initialized: slot.initialized != 0,
+        }
+    }
+}
+
+// Header of a pool's observation ring buffer, its `cardinality_next` slots following it in the
+// account data. The account is never deserialized onto the heap, however far it grows.
+#[account(zero_copy)]
+pub struct Observations {
+    pub pool: Pubkey,
+    // slot of the most recent observation
+    pub index: u16,
+    // number of slots in use, grows to `cardinality_next` once the buffer wraps
+    pub cardinality: u16,
+    pub cardinality_next: u16,
+    pub bump: u8,
+    pub padding: [u8; 1],
+}
+
+impl Observations {
+    pub fn space(cardinality: u16) -> usize {
+        8 + mem::size_of::<Observations>()
+            + cardinality as usize * mem::size_of::<ObservationSlot>()
+    }
+
+    pub fn initialize(
+        &mut self,
+        slots: &mut [ObservationSlot],
+        bump: u8,
+        pool: Pubkey,
+        timestamp: i64,
+    ) {
+        self.bump = bump;
+        self.pool = pool;
+        self.index = 0;
+        self.cardinality = 1;
+        self.cardinality_next = 1;
+        slots[0] = Observation {
+            timestamp,
+            initialized: true,
+            ..Default::default()
+        }
+        .into();
+    }
+
+    // records the pool's reserves held since the last observation, at most once per second
+    pub fn write(
+        &mut self,
+        slots: &mut [ObservationSlot],
+        timestamp: i64,
+        pool: &Pool,
+    ) -> Result<()> {
+        let last = Observation::from(slots[self.index as usize]);
+        if last.timestamp == timestamp {
+            return Ok(());
+        }
+
+        if self.cardinality_next > self.cardinality && self.index == self.cardinality - 1 {
+            self.cardinality = self.cardinality_next;
+        }
+        self.index = (self.index + 1) % self.cardinality;
+        slots[self.index as usize] = last.transform(timestamp, pool)?.into();
+        Ok(())
+    }
+
+    // the account must already be reallocated, with zeroed slots, to hold `cardinality_next`
+    pub fn grow(&mut self, cardinality_next: u16) -> Result<()> {
+        require!(
+            cardinality_next > self.cardinality_next
+                && cardinality_next <= MAX_OBSERVATION_CARDINALITY,
+            ErrorCode::InvalidObservationCardinality
+        );
+
+        self.cardinality_next = cardinality_next;
+        Ok(())
+    }
+
+    // cumulative values at `target`, interpolated between the two surrounding observations or
+    // extrapolated from the newest one using the pool's current reserves
+    pub fn observe_single(
+        &self,
+        slots: &[ObservationSlot],
+        target: i64,
+        pool: &Pool,
+    ) -> Result<Observation> {
+        let newest = Observation::from(slots[self.index as usize]);
+        if target >= newest.timestamp {
+            return newest.transform(target, pool);
+        }
+
+        // the slot after the newest is the oldest, unless the buffer has not wrapped since it grew
+        let mut oldest_index = (self.index + 1) % self.cardinality;
+        if slots[oldest_index as usize].initialized == 0 {
+            oldest_index = 0;
+        }
+        let oldest = Observation::from(slots[oldest_index as usize]);
+        require!(target >= oldest.timestamp, ErrorCode::ObservationTooOld);
+        if target == oldest.timestamp {
+            return Ok(oldest);
+        }
+
+        // binary search over the ring, ordered from oldest to newest
+        let cardinality = self.cardinality as usize;
+        let at = |i: usize| Observation::from(slots[(oldest_index as usize + i) % cardinality]);
+        let (mut low, mut high) = (
+            0usize,
+            (self.index as usize + cardinality - oldest_index as usize) % cardinality,
+        );
+        while high - low > 1 {
+            let mid = (low + high) / 2;
+            if at(mid).timestamp <= target {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+
+        let (before, after) = (at(low), at(high));
+        if target == before.timestamp {
+            return Ok(before);
+        }
+        Ok(before.interpolate(&after, target))
+    }
+
+    pub fn observe(
+        &self,
+        slots: &[ObservationSlot],
+        timestamp: i64,
+        seconds_agos: &[u32],
+        pool: &Pool,
+    ) -> Result<Vec<Observation>> {
+        seconds_agos
+            .iter()
+            .map(|seconds_ago| self.observe_single(slots, timestamp - *seconds_ago as i64, pool))
+            .collect()
+    }
+}
+
+// The ring buffer operations on an observations account, borrowing the header and the slots from
+// its data. The discriminator is checked when the account is loaded and written when it exits.
+pub trait ObservationsAccount {
+    fn initialize(&self, bump: u8, pool: Pubkey, timestamp: i64) -> Result<()>;
+    fn write(&self, timestamp: i64, pool: &Pool) -> Result<()>;
+    fn grow(&self, cardinality_next: u16) -> Result<()>;
+    fn observe(
+        &self,
+        timestamp: i64,
+        seconds_agos: &[u32],
+        pool: &Pool,
+    ) -> Result<Vec<Observation>>;
+}
+
+fn split(data: &[u8]) -> (&Observations, &[ObservationSlot]) {
+    let (header, slots) = data[8..].split_at(mem::size_of::<Observations>());
+    let len = slots.len() / mem::size_of::<ObservationSlot>() * mem::size_of::<ObservationSlot>();
+    (
+        bytemuck::from_bytes(header),
+        bytemuck::cast_slice(&slots[..len]),
+    )
+}
+
+fn split_mut(data: &mut [u8]) -> (&mut Observations, &mut [ObservationSlot]) {
+    let (header, slots) = data[8..].split_at_mut(mem::size_of::<Observations>());
+    let len = slots.len() / mem::size_of::<ObservationSlot>() * mem::size_of::<ObservationSlot>();
+    (
+        bytemuck::from_bytes_mut(header),
+        bytemuck::cast_slice_mut(&mut slots[..len]),
+    )
+}
+
+impl ObservationsAccount for AccountLoader<'_, Observations> {
+    fn initialize(&self, bump: u8, pool: Pubkey, timestamp: i64) -> Result<()> {
+        let mut data = self.as_ref().try_borrow_mut_data()?;
+        let (observations, slots) = split_mut(&mut data);
+        observations.initialize(slots, bump, pool, timestamp);
+        Ok(())
+    }
+
+    fn write(&self, timestamp: i64, pool: &Pool) -> Result<()> {
+        let mut data = self.as_ref().try_borrow_mut_data()?;
+        let (observations, slots) = split_mut(&mut data);
+        observations.write(slots, timestamp, pool)
+    }
+
+    fn grow(&self, cardinality_next: u16) -> Result<()> {
+        self.load_mut()?.grow(cardinality_next)
+    }
+
+    fn observe(
+        &self,
+        timestamp: i64,
+        seconds_agos: &[u32],
+        pool: &Pool,
+    ) -> Result<Vec<Observation>> {
+        let data = self.as_ref().try_borrow_data()?;
+        let (observations, slots) = split(&data);
+        observations.observe(slots, timestamp, seconds_agos, pool)
+    }
+}
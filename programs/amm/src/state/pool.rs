@@ -1,27 +1,306 @@
+use crate::constant::{
+    BASIS_POINTS, MAX_AMP, MAX_AMP_CHANGE, MIN_AMP, MIN_RAMP_DURATION, MIN_WEIGHT,
+};
+use crate::curve::{ConstantProduct, Curve, StableSwap, WeightedProduct};
 use crate::error::ErrorCode;
 use anchor_lang::prelude::*;
 
+// invariant a pool prices swaps and deposits on
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq, InitSpace)]
+pub enum CurveType {
+    // x·y = k
+    ConstantProduct,
+    // Curve's StableSwap, for pairs pegged to each other
+    Stable,
+    // Balancer's x^w0·y^w1 = V, for pools holding the tokens in other proportions than 50/50
+    Weighted,
+}
+
+// curve of a new pool and its parameters, argument of `create_pool`
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CurveParams {
+    ConstantProduct,
+    Stable { amp: u64 },
+    // weights in basis points, adding up to `BASIS_POINTS`
+    Weighted { weight0: u64, weight1: u64 },
+}
+
+impl CurveParams {
+    // the curve's part of the pool address, so that pools of several curves, and weighted pools of
+    // several weights, coexist for a mint pair and fee tier, the amplification ramps and is left out
+    pub fn seed(&self) -> Vec<u8> {
+        match *self {
+            CurveParams::ConstantProduct => vec![0],
+            CurveParams::Stable { .. } => vec![1],
+            CurveParams::Weighted { weight0, .. } => [&[2], &weight0.to_le_bytes()[..]].concat(),
+        }
+    }
+}
+
 #[account]
 #[derive(InitSpace)] // automatically calculate the space required for the struct
 pub struct Pool {
     pub token0: Pubkey,
     pub token1: Pubkey,
+    // swap fee in basis points, the fee tier the pool was created on unless the owner changed it
+    pub fee: u64,
+    pub curve_type: CurveType,
+    // StableSwap amplification coefficient, moving linearly from `initial_amp` at `initial_amp_time`
+    // to `future_amp` at `future_amp_time`, zero for other curves
+    pub initial_amp: u64,
+    pub future_amp: u64,
+    pub initial_amp_time: i64,
+    pub future_amp_time: i64,
+    // weights of token0 and token1 in a weighted pool in basis points, zero for other curves
+    pub weight0: u64,
+    pub weight1: u64,
+    // halts swaps and deposits in this pool, withdrawals to both tokens stay open
+    pub paused: bool,
+    // k, or D or V for a stable or weighted pool, as of the last liquidity operation while the
+    // protocol fee was on
     pub k_last: u128,
+    // reserves as of that liquidity operation, a stable pool's D_last is recomputed from them at the
+    // current amplification so that a ramp is not taken for fee growth
+    pub reserve0_last: u64,
+    pub reserve1_last: u64,
+    // protocol fee LP minted to the pool's LP account and not yet claimed by `fee_to`
+    pub protocol_fees: u64,
+    // balances accounted for by the pool, tokens sent to the vaults directly are not included
+    pub reserve0: u64,
+    pub reserve1: u64,
+    // time-weighted price accumulators, Q64.64 fixed point, wrap on overflow
+    pub price0_cumulative_last: u128,
+    pub price1_cumulative_last: u128,
+    pub block_timestamp_last: i64,
 }
 
 impl Pool {
-    pub fn initialize(&mut self, token0: Pubkey, token1: Pubkey) -> Result<()> {
+    pub fn initialize(
+        &mut self,
+        token0: Pubkey,
+        token1: Pubkey,
+        fee: u64,
+        curve: CurveParams,
+        timestamp: i64,
+    ) -> Result<()> {
         require!(
             token0.to_string() < token1.to_string(),
             ErrorCode::InvalidMintOrder
         );
         self.token0 = token0;
         self.token1 = token1;
+        self.fee = fee;
+        let (curve_type, amp, weight0, weight1) = match curve {
+            CurveParams::ConstantProduct => (CurveType::ConstantProduct, 0, 0, 0),
+            CurveParams::Stable { amp } => {
+                require!(
+                    (MIN_AMP..=MAX_AMP).contains(&amp),
+                    ErrorCode::InvalidAmplification
+                );
+                (CurveType::Stable, amp, 0, 0)
+            }
+            CurveParams::Weighted { weight0, weight1 } => {
+                require!(
+                    weight0 >= MIN_WEIGHT
+                        && weight1 >= MIN_WEIGHT
+                        && weight0.checked_add(weight1) == Some(BASIS_POINTS),
+                    ErrorCode::InvalidWeights
+                );
+                (CurveType::Weighted, 0, weight0, weight1)
+            }
+        };
+        self.curve_type = curve_type;
+        self.initial_amp = amp;
+        self.future_amp = amp;
+        self.initial_amp_time = timestamp;
+        self.future_amp_time = timestamp;
+        self.weight0 = weight0;
+        self.weight1 = weight1;
+        self.paused = false;
         self.k_last = 0;
+        self.reserve0_last = 0;
+        self.reserve1_last = 0;
+        self.protocol_fees = 0;
+        self.reserve0 = 0;
+        self.reserve1 = 0;
+        self.price0_cumulative_last = 0;
+        self.price1_cumulative_last = 0;
+        self.block_timestamp_last = 0;
+        Ok(())
+    }
+
+    pub fn set_paused(&mut self, paused: bool) -> Result<()> {
+        self.paused = paused;
+        Ok(())
+    }
+
+    pub fn set_fee(&mut self, fee: u64) -> Result<()> {
+        require!(fee < BASIS_POINTS, ErrorCode::InvalidFee);
+        self.fee = fee;
+        Ok(())
+    }
+
+    // amplification coefficient at `timestamp`
+    pub fn amp(&self, timestamp: i64) -> u64 {
+        // a pool that never ramped has nothing to interpolate, whatever the clock says
+        let duration = self.future_amp_time.saturating_sub(self.initial_amp_time) as i128;
+        if timestamp >= self.future_amp_time || duration <= 0 {
+            return self.future_amp;
+        }
+        let elapsed = timestamp.saturating_sub(self.initial_amp_time).max(0) as i128;
+        let (initial, future) = (self.initial_amp as i128, self.future_amp as i128);
+        (initial + (future - initial) * elapsed / duration) as u64
+    }
+
+    fn current_amp(&self) -> Result<u64> {
+        Ok(self.amp(Clock::get()?.unix_timestamp))
+    }
+
+    // moves the amplification coefficient from its current value to `future_amp` by `future_amp_time`
+    pub fn ramp_amp(
+        &mut self,
+        future_amp: u64,
+        future_amp_time: i64,
+        timestamp: i64,
+    ) -> Result<()> {
+        require!(
+            self.curve_type == CurveType::Stable,
+            ErrorCode::InvalidCurve
+        );
+        require!(
+            (MIN_AMP..=MAX_AMP).contains(&future_amp),
+            ErrorCode::InvalidAmplification
+        );
+        require!(
+            timestamp >= self.initial_amp_time.saturating_add(MIN_RAMP_DURATION)
+                && future_amp_time >= timestamp.saturating_add(MIN_RAMP_DURATION),
+            ErrorCode::InvalidAmplificationRamp
+        );
+        let amp = self.amp(timestamp);
+        require!(
+            future_amp <= amp * MAX_AMP_CHANGE && future_amp * MAX_AMP_CHANGE >= amp,
+            ErrorCode::InvalidAmplificationRamp
+        );
+
+        self.initial_amp = amp;
+        self.initial_amp_time = timestamp;
+        self.future_amp = future_amp;
+        self.future_amp_time = future_amp_time;
         Ok(())
     }
 
-    pub fn update_k_last(&mut self, reserve0: u64, reserve1: u64) {
-        self.k_last = reserve0 as u128 * reserve1 as u128;
+    // the pool's curve with its parameters as of now
+    pub fn curve(&self) -> Result<Box<dyn Curve>> {
+        Ok(match self.curve_type {
+            CurveType::ConstantProduct => Box::new(ConstantProduct { fee: self.fee }),
+            CurveType::Stable => Box::new(StableSwap {
+                fee: self.fee,
+                amp: self.current_amp()?,
+            }),
+            CurveType::Weighted => Box::new(WeightedProduct {
+                fee: self.fee,
+                weight0: self.weight0,
+                weight1: self.weight1,
+            }),
+        })
+    }
+
+    // output of the pool's curve for `amount_in` of `mint_in` arriving in its vault
+    pub fn swap_amount_out(&self, mint_in: Pubkey, amount_in: u64) -> Result<u64> {
+        self.curve()?.swap_exact_input(
+            mint_in == self.token0,
+            amount_in,
+            self.reserve0,
+            self.reserve1,
+        )
+    }
+
+    // input of `mint_in` the pool's curve asks to arrive in its vault for `amount_out`
+    pub fn swap_amount_in(&self, mint_in: Pubkey, amount_out: u64) -> Result<u64> {
+        self.curve()?.swap_exact_output(
+            mint_in == self.token0,
+            amount_out,
+            self.reserve0,
+            self.reserve1,
+        )
+    }
+
+    // LP owed to the protocol for the growth of the curve's invariant since `k_last`
+    pub fn protocol_fee_liquidity(
+        &self,
+        protocol_fee_share: u64,
+        reserve0: u64,
+        reserve1: u64,
+        lp_supply: u64,
+    ) -> Result<u64> {
+        let curve = self.curve()?;
+        // D moves with the amplification, so D_last is recomputed at the current one
+        let k_last = if self.curve_type == CurveType::Stable && self.k_last != 0 {
+            curve.k_last(self.reserve0_last, self.reserve1_last)?
+        } else {
+            self.k_last
+        };
+        curve.protocol_fee_liquidity(protocol_fee_share, k_last, reserve0, reserve1, lp_supply)
+    }
+
+    pub fn update_k_last(&mut self, reserve0: u64, reserve1: u64) -> Result<()> {
+        self.k_last = self.curve()?.k_last(reserve0, reserve1)?;
+        self.reserve0_last = reserve0;
+        self.reserve1_last = reserve1;
+        Ok(())
+    }
+
+    // spot prices of the reserves on the pool's curve, none while either reserve is empty
+    pub fn spot_prices(&self) -> Result<Option<(u128, u128)>> {
+        if self.reserve0 == 0 || self.reserve1 == 0 {
+            return Ok(None);
+        }
+        self.curve()?
+            .spot_prices(self.reserve0, self.reserve1)
+            .map(Some)
+    }
+
+    // reserves as (reserve of `mint`, reserve of the other token)
+    pub fn reserves_for(&self, mint: Pubkey) -> (u64, u64) {
+        self.sorted_reserves(mint, self.reserve0, self.reserve1)
+    }
+
+    // orders the balances of a vault pair as (reserve0, reserve1), given the mint of the first vault
+    pub fn sorted_reserves(&self, mint_a: Pubkey, amount_a: u64, amount_b: u64) -> (u64, u64) {
+        if mint_a == self.token0 {
+            (amount_a, amount_b)
+        } else {
+            (amount_b, amount_a)
+        }
+    }
+
+    // cumulative prices as they would be at `timestamp` if the reserves were left untouched
+    pub fn current_cumulative_prices(&self, timestamp: i64) -> Result<(u128, u128)> {
+        let time_elapsed = timestamp.saturating_sub(self.block_timestamp_last);
+        let prices = if time_elapsed > 0 {
+            self.spot_prices()?
+        } else {
+            None
+        };
+        let Some((price0, price1)) = prices else {
+            return Ok((self.price0_cumulative_last, self.price1_cumulative_last));
+        };
+
+        Ok((
+            self.price0_cumulative_last
+                .wrapping_add(price0.wrapping_mul(time_elapsed as u128)),
+            self.price1_cumulative_last
+                .wrapping_add(price1.wrapping_mul(time_elapsed as u128)),
+        ))
+    }
+
+    // accumulates prices for the reserves held so far, then moves to the new reserves
+    pub fn update(&mut self, reserve0: u64, reserve1: u64, timestamp: i64) -> Result<()> {
+        (self.price0_cumulative_last, self.price1_cumulative_last) =
+            self.current_cumulative_prices(timestamp)?;
+        self.block_timestamp_last = timestamp;
+        self.reserve0 = reserve0;
+        self.reserve1 = reserve1;
+        Ok(())
     }
 }
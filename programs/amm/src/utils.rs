@@ -0,0 +1,86 @@
+use crate::error::ErrorCode;
+use crate::state::{Config, Pool};
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use anchor_spl::token_interface::spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use anchor_spl::token_interface::spl_token_2022::extension::{
+    BaseStateWithExtensions, ExtensionType, StateWithExtensions,
+};
+use anchor_spl::token_interface::spl_token_2022::state::Mint as MintState;
+use anchor_spl::token_interface::{Mint, Token2022};
+
+// Token-2022 extensions that do not change how tokens move in and out of the vaults, apart from
+// the transfer fee which every amount calculation accounts for
+const SUPPORTED_MINT_EXTENSIONS: [ExtensionType; 3] = [
+    ExtensionType::TransferFeeConfig,
+    ExtensionType::MetadataPointer,
+    ExtensionType::TokenMetadata,
+];
+
+pub fn is_supported_mint(mint: &InterfaceAccount<Mint>) -> Result<bool> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != Token2022::id() {
+        return Ok(true);
+    }
+
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<MintState>::unpack(&mint_data)?;
+    Ok(mint_state
+        .get_extension_types()?
+        .iter()
+        .all(|extension| SUPPORTED_MINT_EXTENSIONS.contains(extension)))
+}
+
+fn transfer_fee_config(mint: &InterfaceAccount<Mint>) -> Result<Option<TransferFeeConfig>> {
+    let mint_info = mint.to_account_info();
+    if *mint_info.owner != Token2022::id() {
+        return Ok(None);
+    }
+
+    let mint_data = mint_info.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<MintState>::unpack(&mint_data)?;
+    Ok(mint_state
+        .get_extension::<TransferFeeConfig>()
+        .ok()
+        .copied())
+}
+
+// fee withheld from `amount` when it is transferred, so `amount - fee` arrives
+pub fn transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    match transfer_fee_config(mint)? {
+        Some(config) => Ok(config
+            .calculate_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(ErrorCode::TransferFeeCalculationFailed)?),
+        None => Ok(0),
+    }
+}
+
+// fee to add on top of `amount` so that `amount` arrives after the transfer
+pub fn inverse_transfer_fee(mint: &InterfaceAccount<Mint>, amount: u64) -> Result<u64> {
+    match transfer_fee_config(mint)? {
+        Some(config) => Ok(config
+            .calculate_inverse_epoch_fee(Clock::get()?.epoch, amount)
+            .ok_or(ErrorCode::TransferFeeCalculationFailed)?),
+        None => Ok(0),
+    }
+}
+
+// a pool's vault for `mint` is always the pool authority's associated token account
+pub fn vault_address(pool_authority: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    get_associated_token_address_with_program_id(pool_authority, mint, token_program)
+}
+
+// rejects the transaction once the cluster time is past `deadline`, no deadline never expires
+pub fn check_deadline(deadline: Option<i64>) -> Result<()> {
+    if let Some(deadline) = deadline {
+        require!(Clock::get()?.unix_timestamp <= deadline, ErrorCode::Expired);
+    }
+    Ok(())
+}
+
+// swaps and deposits stop while the protocol or the pool is paused, withdrawals never do but for
+// `remove_liquidity_single`, whose swap stops with the others
+pub fn check_not_paused(config: &Config, pool: &Pool) -> Result<()> {
+    require!(!config.paused && !pool.paused, ErrorCode::Paused);
+    Ok(())
+}
@@ -0,0 +1,198 @@
+// Weighted product invariant of a two-token pool, as in Balancer's weighted pools:
+//
+//   V = x^w0 · y^w1, w0 + w1 = 1
+//
+// The pool holds the value of its reserves split w0 to w1 between the tokens, an 80/20 pool four
+// times as much value of token0 as of token1, and equal weights give back the constant product with
+// V = √k. Powers are taken in Q64.64 fixed point, accurate to within 2^-55 of their value and a few
+// units of the format, so the power a swap is priced on is rounded up by more than that in the
+// pool's favour.
+use crate::constant::BASIS_POINTS;
+use crate::error::ErrorCode;
+use crate::math::{exp2, log2, pow, q64_ratio, to_u64, CheckedMath, U256};
+use anchor_lang::prelude::*;
+use fixed::types::{I64F64, U64F64};
+
+// slack on a computed power, 2^-48 of its value and 2^-60 on top
+const POW_ERROR_SHIFT: u32 = 48;
+const POW_ERROR: U64F64 = U64F64::from_bits(1 << 4);
+// Newton's method for the zap amount is within a unit after a few steps for the fee tiers in use,
+// stopping short of the root only swaps a little less
+const MAX_ZAP_ITERATIONS: usize = 8;
+
+fn round_up(value: U64F64) -> Result<U64F64> {
+    value
+        .safe_add(value >> POW_ERROR_SHIFT)?
+        .safe_add(POW_ERROR)
+}
+
+// V of the reserves `x` and `y` for weights in basis points, zero for an empty pool
+pub fn compute_invariant(weight0: u64, weight1: u64, x: u64, y: u64) -> Result<u128> {
+    if x == 0 && y == 0 {
+        return Ok(0);
+    }
+    require!(x > 0 && y > 0, ErrorCode::InsufficientLiquidity);
+
+    let basis_points = I64F64::from_num(BASIS_POINTS);
+    let w0 = I64F64::from_num(weight0).safe_div(basis_points)?;
+    let w1 = I64F64::from_num(weight1).safe_div(basis_points)?;
+    // x^w0 · y^w1 = 2^(w0·log2 x + w1·log2 y)
+    let exponent = w0
+        .safe_mul(log2(U64F64::from_num(x))?)?
+        .safe_add(w1.safe_mul(log2(U64F64::from_num(y))?)?)?;
+    Ok(exp2(exponent)?.to_num::<u128>())
+}
+
+// whether V of the balances is at least V of the reserves. V grows with each reserve, so only a
+// trade of one token for the other needs the powers, and then with their slack on both sides.
+pub fn invariant_holds(
+    weight0: u64,
+    weight1: u64,
+    balance0: u64,
+    balance1: u64,
+    reserve0: u64,
+    reserve1: u64,
+) -> Result<bool> {
+    if balance0 >= reserve0 && balance1 >= reserve1 {
+        return Ok(true);
+    }
+    if balance0 == 0 || balance1 == 0 {
+        return Ok(false);
+    }
+    let v = compute_invariant(weight0, weight1, balance0, balance1)?;
+    let v_last = compute_invariant(weight0, weight1, reserve0, reserve1)?;
+    Ok(v.safe_sub(v >> POW_ERROR_SHIFT)?
+        >= v_last.safe_add(v_last >> POW_ERROR_SHIFT)?.safe_add(1)?)
+}
+
+// out = reserve_out · (1 - (reserve_in / (reserve_in + amount_in))^(w_in / w_out)), the fee taken
+// out of the input first as with the other curves
+pub fn get_amount_out(
+    weight_in: u64,
+    weight_out: u64,
+    fee: u64,
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+) -> Result<u64> {
+    require!(
+        reserve_in > 0 && reserve_out > 0,
+        ErrorCode::InsufficientLiquidity,
+    );
+    let amount_in_after_fee = to_u64(
+        (amount_in as u128)
+            .safe_mul(BASIS_POINTS.safe_sub(fee)? as u128)?
+            .safe_div(BASIS_POINTS as u128)?,
+    )?;
+    // the base rounded up as well, its last unit weighs heavily on the power of a small base
+    let base = U64F64::from_num(reserve_in)
+        .safe_div(U64F64::from_num(reserve_in.safe_add(amount_in_after_fee)?))?
+        .safe_add(U64F64::DELTA)?;
+    let exponent = U64F64::from_num(weight_in).safe_div(U64F64::from_num(weight_out))?;
+    let ratio = round_up(pow(base, exponent)?)?;
+    if ratio >= 1 {
+        return Ok(0);
+    }
+
+    Ok(U64F64::from_num(reserve_out)
+        .safe_mul(U64F64::ONE.safe_sub(ratio)?)?
+        .to_num::<u64>())
+}
+
+// in = reserve_in · ((reserve_out / (reserve_out - amount_out))^(w_out / w_in) - 1), grossed up by
+// the fee
+pub fn get_amount_in(
+    weight_in: u64,
+    weight_out: u64,
+    fee: u64,
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+) -> Result<u64> {
+    // asks for enough more than `amount_out` to cover the slack `get_amount_out` takes on the same
+    // trade, so that paying the input buys at least `amount_out`
+    let amount_out = amount_out
+        .safe_add(reserve_out >> (POW_ERROR_SHIFT - 1))?
+        .safe_add(2)?;
+    require!(
+        reserve_in > 0 && reserve_out > amount_out,
+        ErrorCode::InsufficientLiquidity,
+    );
+    let base = U64F64::from_num(reserve_out)
+        .safe_div(U64F64::from_num(reserve_out.safe_sub(amount_out)?))?
+        .safe_add(U64F64::DELTA)?;
+    let exponent = U64F64::from_num(weight_out).safe_div(U64F64::from_num(weight_in))?;
+    let ratio = round_up(pow(base, exponent)?)?;
+    let amount_in_after_fee = U64F64::from_num(reserve_in)
+        .safe_mul(ratio.safe_sub(U64F64::ONE)?)?
+        .checked_ceil()
+        .ok_or_else(|| error!(ErrorCode::MathOverflow))?
+        .to_num::<u64>();
+
+    // gross up by the fee, rounding up
+    let fee_complement = BASIS_POINTS.safe_sub(fee)? as u128;
+    to_u64(
+        (amount_in_after_fee as u128)
+            .safe_mul(BASIS_POINTS as u128)?
+            .safe_add(fee_complement.safe_sub(1)?)?
+            .safe_div(fee_complement)?,
+    )
+}
+
+// Part of `amount_in` to swap so that the rest and the output are in the ratio of the reserves after
+// the swap. Without a fee that is Balancer's single-token join, s = r_in·((1 + a / r_in)^w_out - 1).
+// The fee only lets γ·s of the swap into its reserves, and t = s / r_in then solves
+//
+//   (1 + a / r_in)·(1 + γ·t)^(-w_in / w_out) - (1 + t) = 0
+//
+// whose left side is convex and falling, so Newton's method from the fee-free t approaches the root
+// from below.
+pub fn get_zap_amount(
+    weight_in: u64,
+    weight_out: u64,
+    fee: u64,
+    amount_in: u64,
+    reserve_in: u64,
+) -> Result<u64> {
+    require!(reserve_in > 0, ErrorCode::InsufficientLiquidity);
+    let reserve = U64F64::from_num(reserve_in);
+    let growth = U64F64::ONE.safe_add(U64F64::from_num(amount_in).safe_div(reserve)?)?;
+    let basis_points = U64F64::from_num(BASIS_POINTS);
+    let fee_complement = U64F64::from_num(BASIS_POINTS.safe_sub(fee)?).safe_div(basis_points)?;
+    let exponent = U64F64::from_num(weight_in).safe_div(U64F64::from_num(weight_out))?;
+    let mut t = pow(growth, U64F64::from_num(weight_out).safe_div(basis_points)?)?
+        .saturating_sub(U64F64::ONE);
+    for _ in 0..MAX_ZAP_ITERATIONS {
+        let x = U64F64::ONE.safe_add(fee_complement.safe_mul(t)?)?;
+        let target = growth.safe_mul(pow(U64F64::ONE.safe_div(x)?, exponent)?)?;
+        let rest = U64F64::ONE.safe_add(t)?;
+        if target <= rest {
+            break;
+        }
+        let slope = exponent
+            .safe_mul(fee_complement)?
+            .safe_mul(target)?
+            .safe_div(x)?
+            .safe_add(U64F64::ONE)?;
+        let step = target.safe_sub(rest)?.safe_div(slope)?;
+        t = t.safe_add(step)?;
+        if step.safe_mul(reserve)? < U64F64::ONE {
+            break;
+        }
+    }
+
+    Ok(t.safe_mul(reserve)?.to_num::<u64>().min(amount_in))
+}
+
+// spot prices of token0 in token1 and of token1 in token0, Q64.64 fixed point: the reserves priced
+// by their weights, r1·w0 / (r0·w1) for token0
+pub fn spot_prices(
+    weight0: u64,
+    weight1: u64,
+    reserve0: u64,
+    reserve1: u64,
+) -> Result<(u128, u128)> {
+    let value0 = U256::from(reserve0).safe_mul(U256::from(weight1))?;
+    let value1 = U256::from(reserve1).safe_mul(U256::from(weight0))?;
+    Ok((q64_ratio(value1, value0), q64_ratio(value0, value1)))
+}
@@ -0,0 +1,452 @@
+// Invariants of the pool math over random reserves and amounts. Reserves and amounts stay below
+// 2^48 so that the products checked here fit in a u128.
+use amm::constant::{BASIS_POINTS, MINIMUM_LIQUIDITY};
+use amm::constant::{MAX_AMP, MIN_AMP, MIN_WEIGHT};
+use amm::constant_product::{
+    calculate_liquidity, calculate_liquidity_amounts, calculate_removed_amounts, get_amount_in,
+    get_amount_out, protocol_fee_liquidity, quote,
+};
+use amm::curve::{ConstantProduct, Curve, StableSwap, WeightedProduct};
+use amm::math::{pow, sqrt, U256};
+use amm::stable_swap;
+use amm::weighted_product;
+use anchor_lang::Result;
+use fixed::types::U64F64;
+use proptest::prelude::*;
+
+const MAX_AMOUNT: u64 = 1 << 48;
+
+fn amount() -> impl Strategy<Value = u64> {
+    1..MAX_AMOUNT
+}
+
+fn fee() -> impl Strategy<Value = u64> {
+    0..BASIS_POINTS
+}
+
+fn amp() -> impl Strategy<Value = u64> {
+    MIN_AMP..=MAX_AMP
+}
+
+fn weight() -> impl Strategy<Value = u64> {
+    MIN_WEIGHT..=BASIS_POINTS - MIN_WEIGHT
+}
+
+proptest! {
+    // the fee-adjusted product never decreases, so neither does k
+    #[test]
+    fn swap_does_not_decrease_k(
+        fee in fee(),
+        amount_in in amount(),
+        reserve_in in amount(),
+        reserve_out in amount(),
+    ) {
+        let amount_out = get_amount_out(fee, amount_in, reserve_in, reserve_out).unwrap();
+        prop_assert!(amount_out < reserve_out);
+
+        let (amount_in, reserve_in, reserve_out, amount_out) = (
+            amount_in as u128,
+            reserve_in as u128,
+            reserve_out as u128,
+            amount_out as u128,
+        );
+        let bp = BASIS_POINTS as u128;
+        let balance_in_adjusted = reserve_in * bp + amount_in * (bp - fee as u128);
+        let k = reserve_in * reserve_out;
+        prop_assert!(balance_in_adjusted * (reserve_out - amount_out) >= k * bp);
+        prop_assert!((reserve_in + amount_in) * (reserve_out - amount_out) >= k);
+    }
+
+    // paying what `get_amount_in` asks for always buys at least the requested amount
+    #[test]
+    fn amount_in_covers_amount_out(
+        fee in fee(),
+        reserve_in in amount(),
+        reserve_out in 2..MAX_AMOUNT,
+        amount_out in amount(),
+    ) {
+        let amount_out = amount_out % (reserve_out - 1) + 1;
+        let amount_in = get_amount_in(fee, amount_out, reserve_in, reserve_out);
+        // the input may not fit in a u64 for a near-complete drain
+        prop_assume!(amount_in.is_ok());
+        let amount_in = amount_in.unwrap();
+
+        let bought = get_amount_out(fee, amount_in, reserve_in, reserve_out).unwrap();
+        prop_assert!(bought >= amount_out);
+    }
+
+    // the quoted amount is the floor of the exact ratio
+    #[test]
+    fn quote_rounds_down(amount0 in amount(), reserve0 in amount(), reserve1 in amount()) {
+        let amount1 = quote(amount0, reserve0, reserve1).unwrap() as u128;
+        let (amount0, reserve0, reserve1) = (amount0 as u128, reserve0 as u128, reserve1 as u128);
+
+        prop_assert!(amount1 * reserve0 <= amount0 * reserve1);
+        prop_assert!((amount1 + 1) * reserve0 > amount0 * reserve1);
+    }
+
+    // a deposit never takes more than desired and never more of either token than the pool price
+    // allows
+    #[test]
+    fn liquidity_amounts_follow_pool_price(
+        reserve0 in amount(),
+        reserve1 in amount(),
+        amount0_desired in amount(),
+        amount1_desired in amount(),
+    ) {
+        let amounts =
+            calculate_liquidity_amounts(reserve0, reserve1, amount0_desired, amount1_desired, 0, 0);
+        prop_assume!(amounts.is_ok());
+        let (amount0, amount1) = amounts.unwrap();
+
+        prop_assert!(amount0 <= amount0_desired && amount1 <= amount1_desired);
+        prop_assert!(amount0 == amount0_desired || amount1 == amount1_desired);
+        // the other amount is the floor of the pool price applied to the one taken in full
+        let (reserve0, reserve1) = (reserve0 as i128, reserve1 as i128);
+        let excess0 = amount0 as i128 * reserve1 - amount1 as i128 * reserve0;
+        prop_assert!(
+            (0..reserve0).contains(&excess0) || (-reserve1 + 1..=0).contains(&excess0)
+        );
+    }
+
+    // the first deposit is minted the geometric mean minus the locked liquidity
+    #[test]
+    fn first_deposit_locks_minimum_liquidity(amount0 in amount(), amount1 in amount()) {
+        let liquidity = calculate_liquidity(amount0, amount1, 0, 0, 0);
+        let root_k = sqrt(amount0 as u128 * amount1 as u128) as u64;
+        if root_k <= MINIMUM_LIQUIDITY {
+            prop_assert!(liquidity.is_err());
+        } else {
+            prop_assert_eq!(liquidity.unwrap(), root_k - MINIMUM_LIQUIDITY);
+        }
+    }
+
+    // burning never pays out more than the burnt share of the reserves
+    #[test]
+    fn removed_amounts_within_share(
+        lp_supply in amount(),
+        liquidity in amount(),
+        reserve0 in amount(),
+        reserve1 in amount(),
+    ) {
+        let liquidity = liquidity % lp_supply + 1;
+        let amounts = calculate_removed_amounts(liquidity, lp_supply, reserve0, reserve1, 0, 0);
+        prop_assume!(amounts.is_ok());
+        let (amount0, amount1) = amounts.unwrap();
+
+        prop_assert!(amount0 <= reserve0 && amount1 <= reserve1);
+        let (liquidity, lp_supply) = (liquidity as u128, lp_supply as u128);
+        prop_assert!(amount0 as u128 * lp_supply <= liquidity * reserve0 as u128);
+        prop_assert!(amount1 as u128 * lp_supply <= liquidity * reserve1 as u128);
+    }
+
+    // adding liquidity and removing it straight away returns at most what was deposited
+    #[test]
+    fn add_remove_round_trip_does_not_extract_value(
+        reserve0 in amount(),
+        reserve1 in amount(),
+        lp_supply in amount(),
+        amount0_desired in amount(),
+        amount1_desired in amount(),
+    ) {
+        let amounts =
+            calculate_liquidity_amounts(reserve0, reserve1, amount0_desired, amount1_desired, 0, 0);
+        prop_assume!(amounts.is_ok());
+        let (amount0, amount1) = amounts.unwrap();
+        let liquidity = calculate_liquidity(amount0, amount1, reserve0, reserve1, lp_supply);
+        prop_assume!(liquidity.is_ok());
+        let liquidity = liquidity.unwrap();
+
+        let removed = calculate_removed_amounts(
+            liquidity,
+            lp_supply + liquidity,
+            reserve0 + amount0,
+            reserve1 + amount1,
+            0,
+            0,
+        );
+        prop_assume!(removed.is_ok());
+        let (removed0, removed1) = removed.unwrap();
+
+        prop_assert!(removed0 <= amount0 && removed1 <= amount1);
+    }
+
+    // the protocol is minted at most its share of the growth of √k and nothing without growth
+    #[test]
+    fn protocol_fee_within_share_of_growth(
+        protocol_fee_share in 1..BASIS_POINTS,
+        reserve0 in amount(),
+        reserve1 in amount(),
+        last0 in amount(),
+        last1 in amount(),
+        lp_supply in amount(),
+    ) {
+        let k_last = (last0 % reserve0 + 1) as u128 * (last1 % reserve1 + 1) as u128;
+        let liquidity =
+            protocol_fee_liquidity(protocol_fee_share, k_last, reserve0, reserve1, lp_supply)
+                .unwrap() as u128;
+
+        let root_k = sqrt(reserve0 as u128 * reserve1 as u128);
+        let root_k_last = sqrt(k_last);
+        if root_k <= root_k_last {
+            prop_assert_eq!(liquidity, 0);
+        } else {
+            // liquidity / (supply + liquidity) * √k <= φ * (√k - √k_last)
+            prop_assert!(
+                liquidity * root_k * BASIS_POINTS as u128
+                    <= protocol_fee_share as u128
+                        * (root_k - root_k_last)
+                        * (lp_supply as u128 + liquidity)
+            );
+        }
+    }
+
+    // a stable swap never decreases D
+    #[test]
+    fn stable_swap_does_not_decrease_d(
+        amp in amp(),
+        fee in fee(),
+        amount_in in amount(),
+        reserve_in in amount(),
+        reserve_out in amount(),
+    ) {
+        let amount_out =
+            stable_swap::get_amount_out(amp, fee, amount_in, reserve_in, reserve_out);
+        // pools far from balance may not converge or overflow
+        prop_assume!(amount_out.is_ok());
+        let amount_out = amount_out.unwrap();
+        prop_assert!(amount_out < reserve_out);
+
+        let d = stable_swap::compute_d(amp, reserve_in, reserve_out).unwrap();
+        let d_after =
+            stable_swap::compute_d(amp, reserve_in + amount_in, reserve_out - amount_out);
+        prop_assert!(d_after.unwrap() >= d);
+    }
+
+    // paying what the stable `get_amount_in` asks for always buys at least the requested amount
+    #[test]
+    fn stable_amount_in_covers_amount_out(
+        amp in amp(),
+        fee in fee(),
+        reserve_in in amount(),
+        reserve_out in 2..MAX_AMOUNT,
+        amount_out in amount(),
+    ) {
+        let amount_out = amount_out % (reserve_out - 1) + 1;
+        let amount_in = stable_swap::get_amount_in(amp, fee, amount_out, reserve_in, reserve_out);
+        prop_assume!(amount_in.is_ok());
+        let amount_in = amount_in.unwrap();
+
+        let bought = stable_swap::get_amount_out(amp, fee, amount_in, reserve_in, reserve_out);
+        prop_assume!(bought.is_ok());
+        prop_assert!(bought.unwrap() >= amount_out);
+    }
+
+    // D lies between the constant product's 2·√k and the constant sum
+    #[test]
+    fn stable_d_between_product_and_sum(amp in amp(), x in amount(), y in amount()) {
+        let d = stable_swap::compute_d(amp, x, y);
+        prop_assume!(d.is_ok());
+        let d = d.unwrap();
+
+        prop_assert!(d <= x as u128 + y as u128);
+        prop_assert!(d + 1 >= 2 * sqrt(x as u128 * y as u128));
+    }
+
+    // the fixed-point power agrees with the float one to far better than the slack swaps allow for
+    #[test]
+    fn pow_matches_float(
+        numerator in 1..u32::MAX,
+        denominator in 1..u32::MAX,
+        weight_in in weight(),
+        weight_out in weight(),
+    ) {
+        let base = U64F64::from_num(numerator) / U64F64::from_num(denominator);
+        let exponent = U64F64::from_num(weight_in) / U64F64::from_num(weight_out);
+        let expected = base.to_num::<f64>().powf(exponent.to_num::<f64>());
+        // the float result is only representable within Q64.64
+        prop_assume!(expected < 1e18);
+
+        let result = pow(base, exponent).unwrap().to_num::<f64>();
+        prop_assert!((result - expected).abs() <= expected * 1e-12 + 1e-18);
+    }
+
+    // with equal weights the curve is the constant product, priced a hair in the pool's favour
+    #[test]
+    fn weighted_equal_weights_match_constant_product(
+        fee in fee(),
+        amount_in in amount(),
+        reserve_in in amount(),
+        reserve_out in amount(),
+    ) {
+        let weight = BASIS_POINTS / 2;
+        let amount_out = weighted_product::get_amount_out(
+            weight, weight, fee, amount_in, reserve_in, reserve_out,
+        ).unwrap();
+        // the weighted curve takes the fee out of the input before pricing it, rounding it down
+        let amount_in_after_fee = amount_in * (BASIS_POINTS - fee) / BASIS_POINTS;
+        let expected = get_amount_out(0, amount_in_after_fee, reserve_in, reserve_out).unwrap();
+
+        prop_assert!(amount_out <= expected);
+        prop_assert!(expected - amount_out <= (reserve_out >> 46) + 2);
+        let invariant =
+            weighted_product::compute_invariant(weight, weight, reserve_in, reserve_out).unwrap();
+        prop_assert!(invariant.abs_diff(sqrt(reserve_in as u128 * reserve_out as u128)) <= 1);
+    }
+
+    // paying what the weighted `get_amount_in` asks for always buys at least the requested amount
+    #[test]
+    fn weighted_amount_in_covers_amount_out(
+        weight_in in weight(),
+        fee in fee(),
+        reserve_in in amount(),
+        reserve_out in 8..MAX_AMOUNT,
+        amount_out in amount(),
+    ) {
+        let weight_out = BASIS_POINTS - weight_in;
+        // up to an eighth of the reserve, beyond which the input rarely fits in a u64 at the most
+        // lopsided weights
+        let amount_out = amount_out % (reserve_out / 8) + 1;
+        let amount_in = weighted_product::get_amount_in(
+            weight_in, weight_out, fee, amount_out, reserve_in, reserve_out,
+        );
+        // the input may not fit in a u64 for a near-complete drain
+        prop_assume!(amount_in.is_ok());
+        let amount_in = amount_in.unwrap();
+
+        let bought = weighted_product::get_amount_out(
+            weight_in, weight_out, fee, amount_in, reserve_in, reserve_out,
+        );
+        prop_assume!(bought.is_ok());
+        prop_assert!(bought.unwrap() >= amount_out);
+    }
+
+    // no trade on any curve beats its spot price, the curves being convex
+    #[test]
+    fn curve_trade_never_beats_spot_price(
+        kind in 0..3u8,
+        amp in amp(),
+        weight0 in weight(),
+        amount_in in amount(),
+        reserve0 in amount(),
+        reserve1 in amount(),
+    ) {
+        let curve = curve(kind, 0, amp, weight0);
+        let amount_out = curve.swap_exact_input(true, amount_in, reserve0, reserve1);
+        let prices = curve.spot_prices(reserve0, reserve1);
+        // stable pools far from balance may not converge or overflow
+        prop_assume!(amount_out.is_ok() && prices.is_ok());
+        let (price0, price1) = prices.unwrap();
+
+        // the Q64.64 price rounds down by a unit and the stable output is exact to one
+        let bound = ((U256::from(amount_in) * U256::from(price0 + 1)) >> 64) + U256::from(1);
+        prop_assert!(U256::from(amount_out.unwrap()) <= bound);
+        // the two prices are each other's inverse
+        let product = (U256::from(price0) * U256::from(price1)) >> 64;
+        let one = U256::from(1u128 << 64);
+        prop_assert!(product <= one);
+        prop_assert!(one - product <= (U256::from(price0.max(price1)) >> 63) + U256::from(1));
+    }
+
+    // each curve's zap amount, solved for directly, lands where a bisection does
+    #[test]
+    fn zap_amount_matches_bisection(
+        kind in 0..3u8,
+        fee in fee(),
+        amp in amp(),
+        weight0 in weight(),
+        amount_in in amount(),
+        reserve0 in amount(),
+        reserve1 in amount(),
+    ) {
+        let direct = curve(kind, fee, amp, weight0);
+        let bisected = Bisected(curve(kind, fee, amp, weight0));
+        let expected = bisected.zap_amount(true, amount_in, reserve0, reserve1);
+        prop_assume!(expected.is_ok());
+        let expected = expected.unwrap();
+        let zap_amount = direct.zap_amount(true, amount_in, reserve0, reserve1).unwrap();
+
+        // the same to a few units of the input, or to a couple of the output where the output is
+        // too coarse to tell inputs apart, as under a high fee
+        let amount_out = direct.swap_exact_input(true, zap_amount, reserve0, reserve1).unwrap();
+        let expected_out = direct.swap_exact_input(true, expected, reserve0, reserve1).unwrap();
+        prop_assert!(
+            zap_amount.abs_diff(expected) <= 8 || amount_out.abs_diff(expected_out) <= 2
+        );
+    }
+}
+
+// a curve of each kind: 0 the constant product, 1 StableSwap and 2 weighted
+fn curve(kind: u8, fee: u64, amp: u64, weight0: u64) -> Box<dyn Curve> {
+    match kind {
+        0 => Box::new(ConstantProduct { fee }),
+        1 => Box::new(StableSwap { fee, amp }),
+        _ => Box::new(WeightedProduct {
+            fee,
+            weight0,
+            weight1: BASIS_POINTS - weight0,
+        }),
+    }
+}
+
+// a curve whose zap amount is found by bisection: the rest falls and the output grows with the
+// part swapped
+struct Bisected(Box<dyn Curve>);
+
+impl Curve for Bisected {
+    fn swap_exact_input(
+        &self,
+        zero_for_one: bool,
+        amount_in: u64,
+        r0: u64,
+        r1: u64,
+    ) -> Result<u64> {
+        self.0.swap_exact_input(zero_for_one, amount_in, r0, r1)
+    }
+
+    fn swap_exact_output(
+        &self,
+        zero_for_one: bool,
+        amount_out: u64,
+        r0: u64,
+        r1: u64,
+    ) -> Result<u64> {
+        self.0.swap_exact_output(zero_for_one, amount_out, r0, r1)
+    }
+
+    fn invariant(&self, reserve0: u64, reserve1: u64) -> Result<u128> {
+        self.0.invariant(reserve0, reserve1)
+    }
+
+    fn spot_prices(&self, reserve0: u64, reserve1: u64) -> Result<(u128, u128)> {
+        self.0.spot_prices(reserve0, reserve1)
+    }
+
+    fn zap_amount(
+        &self,
+        zero_for_one: bool,
+        amount_in: u64,
+        reserve0: u64,
+        reserve1: u64,
+    ) -> Result<u64> {
+        let (reserve_in, reserve_out) = if zero_for_one {
+            (reserve0, reserve1)
+        } else {
+            (reserve1, reserve0)
+        };
+        let (mut low, mut high) = (0, amount_in);
+        while high - low > 1 {
+            let mid = low + (high - low) / 2;
+            let amount_out = self.swap_exact_input(zero_for_one, mid, reserve0, reserve1)?;
+            // (amount_in - mid) / (reserve_in + mid) against amount_out / (reserve_out - amount_out)
+            let rest = U256::from(amount_in - mid) * U256::from(reserve_out - amount_out);
+            let swapped = U256::from(amount_out) * (U256::from(reserve_in) + U256::from(mid));
+            if rest > swapped {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        Ok(low)
+    }
+}
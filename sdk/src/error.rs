@@ -0,0 +1,26 @@
+use std::fmt;
+
+// mirrors the program's error codes that the math can raise
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    InsufficientAmount,
+    InsufficientReserves,
+    InsufficientLiquidityMinted,
+    InsufficientLiquidityBurned,
+    InsufficientLiquidity,
+    InvalidMint,
+    InvalidAccountData,
+    MathOverflow,
+    DivisionByZero,
+    InvariantNotConverged,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
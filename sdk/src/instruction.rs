@@ -0,0 +1,747 @@
+// Builders for every program instruction. Accounts are listed in the order of the program's
+// account structs, PDAs and vaults are derived from the keys passed in.
+use crate::error::{Error, Result};
+use crate::pda::{self, ASSOCIATED_TOKEN_PROGRAM_ID, TOKEN_PROGRAM_ID};
+use crate::state::{CurveParams, PriceObservation};
+use crate::ID;
+use borsh::BorshSerialize;
+use solana_program::hash::hash;
+use solana_program::instruction::{AccountMeta, Instruction};
+use solana_program::pubkey::Pubkey;
+use solana_program::{system_program, sysvar};
+
+// the keys identifying a pool, everything else about it is derived
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolKeys {
+    pub pool: Pubkey,
+    pub token0: Pubkey,
+    pub token1: Pubkey,
+    pub token_program0: Pubkey,
+    pub token_program1: Pubkey,
+}
+
+impl PoolKeys {
+    pub fn new(
+        token0: Pubkey,
+        token1: Pubkey,
+        fee: u64,
+        curve: &CurveParams,
+        token_program0: Pubkey,
+        token_program1: Pubkey,
+    ) -> Self {
+        Self {
+            pool: pda::pool(&token0, &token1, fee, curve),
+            token0,
+            token1,
+            token_program0,
+            token_program1,
+        }
+    }
+
+    pub fn authority(&self) -> Pubkey {
+        pda::authority(&self.pool)
+    }
+
+    pub fn vault0(&self) -> Pubkey {
+        pda::vault(&self.pool, &self.token0, &self.token_program0)
+    }
+
+    pub fn vault1(&self) -> Pubkey {
+        pda::vault(&self.pool, &self.token1, &self.token_program1)
+    }
+
+    pub fn lp_mint(&self) -> Pubkey {
+        pda::lp_mint(&self.pool)
+    }
+
+    pub fn vault_lp(&self) -> Pubkey {
+        pda::vault(&self.pool, &self.lp_mint(), &TOKEN_PROGRAM_ID)
+    }
+
+    pub fn observations(&self) -> Pubkey {
+        pda::observations(&self.pool)
+    }
+
+    // ((mint, token program) in, (mint, token program) out) when swapping `mint_in`
+    fn direction(&self, mint_in: &Pubkey) -> Result<((Pubkey, Pubkey), (Pubkey, Pubkey))> {
+        let side0 = (self.token0, self.token_program0);
+        let side1 = (self.token1, self.token_program1);
+        if *mint_in == self.token0 {
+            Ok((side0, side1))
+        } else if *mint_in == self.token1 {
+            Ok((side1, side0))
+        } else {
+            Err(Error::InvalidMint)
+        }
+    }
+}
+
+// where the creation fee goes when one is charged, see `Config.pool_creation_fee`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CreationFee {
+    Lamports {
+        fee_to: Pubkey,
+    },
+    Token {
+        fee_to: Pubkey,
+        mint: Pubkey,
+        token_program: Pubkey,
+    },
+}
+
+fn instruction<T: BorshSerialize>(name: &str, args: &T, accounts: Vec<AccountMeta>) -> Instruction {
+    let mut data = hash(format!("global:{name}").as_bytes()).to_bytes()[..8].to_vec();
+    // writing into a Vec cannot fail
+    args.serialize(&mut data).unwrap();
+
+    Instruction {
+        program_id: ID,
+        accounts,
+        data,
+    }
+}
+
+// an optional account left out is passed as the program id
+fn optional(key: Option<Pubkey>, is_writable: bool) -> AccountMeta {
+    match key {
+        Some(key) if is_writable => AccountMeta::new(key, false),
+        Some(key) => AccountMeta::new_readonly(key, false),
+        None => AccountMeta::new_readonly(ID, false),
+    }
+}
+
+// accounts of the owner-only instructions that only touch the config
+fn config_admin(owner: Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(owner, true),
+        AccountMeta::new(pda::config(), false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ]
+}
+
+pub fn initialize(owner: Pubkey, fee_to: Pubkey, fee: u64) -> Instruction {
+    instruction(
+        "initialize",
+        &(fee_to, fee),
+        vec![
+            AccountMeta::new(owner, true),
+            AccountMeta::new(pda::config(), false),
+            AccountMeta::new(pda::fee_tiers(), false),
+            AccountMeta::new_readonly(system_program::ID, false),
+            AccountMeta::new_readonly(sysvar::rent::ID, false),
+        ],
+    )
+}
+
+pub fn propose_owner(owner: Pubkey, new_owner: Pubkey) -> Instruction {
+    instruction("propose_owner", &new_owner, config_admin(owner))
+}
+
+pub fn accept_owner(pending_owner: Pubkey) -> Instruction {
+    instruction("accept_owner", &(), config_admin(pending_owner))
+}
+
+pub fn renounce_owner(owner: Pubkey) -> Instruction {
+    instruction("renounce_owner", &(), config_admin(owner))
+}
+
+pub fn set_fee_to(owner: Pubkey, new_fee_to: Pubkey) -> Instruction {
+    instruction("set_fee_to", &new_fee_to, config_admin(owner))
+}
+
+pub fn set_protocol_fee_share(owner: Pubkey, protocol_fee_share: u64) -> Instruction {
+    instruction(
+        "set_protocol_fee_share",
+        &protocol_fee_share,
+        config_admin(owner),
+    )
+}
+
+pub fn claim_protocol_fees(fee_to: Pubkey, pool: &PoolKeys, recipient: Pubkey) -> Instruction {
+    instruction(
+        "claim_protocol_fees",
+        &(),
+        vec![
+            AccountMeta::new_readonly(fee_to, true),
+            AccountMeta::new_readonly(pda::config(), false),
+            AccountMeta::new(pool.pool, false),
+            AccountMeta::new_readonly(pool.authority(), false),
+            AccountMeta::new_readonly(pool.lp_mint(), false),
+            AccountMeta::new(pool.vault_lp(), false),
+            AccountMeta::new(recipient, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        ],
+    )
+}
+
+fn fee_tier_accounts(owner: Pubkey) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new(owner, true),
+        AccountMeta::new_readonly(pda::config(), false),
+        AccountMeta::new(pda::fee_tiers(), false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ]
+}
+
+pub fn enable_fee_tier(owner: Pubkey, fee: u64) -> Instruction {
+    instruction("enable_fee_tier", &fee, fee_tier_accounts(owner))
+}
+
+pub fn disable_fee_tier(owner: Pubkey, fee: u64) -> Instruction {
+    instruction("disable_fee_tier", &fee, fee_tier_accounts(owner))
+}
+
+pub fn set_fee(owner: Pubkey, pool: Pubkey, fee: u64) -> Instruction {
+    instruction(
+        "set_fee",
+        &fee,
+        vec![
+            AccountMeta::new(owner, true),
+            AccountMeta::new_readonly(pda::config(), false),
+            AccountMeta::new(pool, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+pub fn set_paused(owner: Pubkey, paused: bool) -> Instruction {
+    instruction("set_paused", &paused, config_admin(owner))
+}
+
+pub fn set_pool_paused(owner: Pubkey, pool: Pubkey, paused: bool) -> Instruction {
+    instruction(
+        "set_pool_paused",
+        &paused,
+        vec![
+            AccountMeta::new(owner, true),
+            AccountMeta::new_readonly(pda::config(), false),
+            AccountMeta::new(pool, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+// `fee` is the pool's fee tier, `creation_fee` is needed when the config charges the payer for it
+pub fn create_pool(
+    payer: Pubkey,
+    pool: &PoolKeys,
+    fee: u64,
+    curve: CurveParams,
+    creation_fee: Option<CreationFee>,
+) -> Instruction {
+    let (fee_to, fee_mint, payer_fee_ata, fee_to_ata, fee_token_program) = match creation_fee {
+        None => (None, None, None, None, None),
+        Some(CreationFee::Lamports { fee_to }) => (Some(fee_to), None, None, None, None),
+        Some(CreationFee::Token {
+            fee_to,
+            mint,
+            token_program,
+        }) => (
+            None,
+            Some(mint),
+            Some(pda::associated_token_address(&payer, &mint, &token_program)),
+            Some(pda::associated_token_address(
+                &fee_to,
+                &mint,
+                &token_program,
+            )),
+            Some(token_program),
+        ),
+    };
+
+    instruction(
+        "create_pool",
+        &(fee, curve),
+        vec![
+            AccountMeta::new_readonly(pool.token0, false),
+            AccountMeta::new_readonly(pool.token1, false),
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(pda::config(), false),
+            AccountMeta::new_readonly(pda::fee_tiers(), false),
+            AccountMeta::new(pool.pool, false),
+            AccountMeta::new_readonly(pool.authority(), false),
+            AccountMeta::new_readonly(pool.vault0(), false),
+            AccountMeta::new_readonly(pool.vault1(), false),
+            AccountMeta::new(pool.lp_mint(), false),
+            AccountMeta::new(pool.observations(), false),
+            optional(fee_to, true),
+            optional(fee_mint, false),
+            optional(payer_fee_ata, true),
+            optional(fee_to_ata, true),
+            optional(fee_token_program, false),
+            AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+            AccountMeta::new_readonly(pool.token_program0, false),
+            AccountMeta::new_readonly(pool.token_program1, false),
+            AccountMeta::new_readonly(sysvar::rent::ID, false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+pub fn ramp_amp(owner: Pubkey, pool: Pubkey, future_amp: u64, future_amp_time: i64) -> Instruction {
+    instruction(
+        "ramp_amp",
+        &(future_amp, future_amp_time),
+        vec![
+            AccountMeta::new_readonly(owner, true),
+            AccountMeta::new_readonly(pda::config(), false),
+            AccountMeta::new(pool, false),
+        ],
+    )
+}
+
+pub fn set_pool_creation_restricted(owner: Pubkey, restricted: bool) -> Instruction {
+    instruction(
+        "set_pool_creation_restricted",
+        &restricted,
+        config_admin(owner),
+    )
+}
+
+// `mint` is the default pubkey for a fee in lamports
+pub fn set_pool_creation_fee(owner: Pubkey, mint: Pubkey, fee: u64) -> Instruction {
+    instruction("set_pool_creation_fee", &(mint, fee), config_admin(owner))
+}
+
+// the owner's associated token accounts are used for both tokens and the LP
+fn liquidity_accounts(owner: Pubkey, pool: &PoolKeys) -> Vec<AccountMeta> {
+    let lp_mint = pool.lp_mint();
+    vec![
+        AccountMeta::new_readonly(pda::config(), false),
+        AccountMeta::new(owner, true),
+        AccountMeta::new(
+            pda::associated_token_address(&owner, &pool.token0, &pool.token_program0),
+            false,
+        ),
+        AccountMeta::new(
+            pda::associated_token_address(&owner, &pool.token1, &pool.token_program1),
+            false,
+        ),
+        AccountMeta::new(
+            pda::associated_token_address(&owner, &lp_mint, &TOKEN_PROGRAM_ID),
+            false,
+        ),
+        AccountMeta::new(pool.pool, false),
+        AccountMeta::new_readonly(pool.authority(), false),
+        AccountMeta::new(pool.vault_lp(), false),
+        AccountMeta::new(pool.vault0(), false),
+        AccountMeta::new(pool.vault1(), false),
+        AccountMeta::new_readonly(pool.token0, false),
+        AccountMeta::new_readonly(pool.token1, false),
+        AccountMeta::new(lp_mint, false),
+        AccountMeta::new(pool.observations(), false),
+        AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID, false),
+        AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        AccountMeta::new_readonly(pool.token_program0, false),
+        AccountMeta::new_readonly(pool.token_program1, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ]
+}
+
+pub fn add_liquidity(
+    owner: Pubkey,
+    pool: &PoolKeys,
+    amount0_desired: u64,
+    amount1_desired: u64,
+    amount0_min: u64,
+    amount1_min: u64,
+    deadline: Option<i64>,
+) -> Instruction {
+    instruction(
+        "add_liquidity",
+        &(
+            amount0_desired,
+            amount1_desired,
+            amount0_min,
+            amount1_min,
+            deadline,
+        ),
+        liquidity_accounts(owner, pool),
+    )
+}
+
+pub fn remove_liquidity(
+    owner: Pubkey,
+    pool: &PoolKeys,
+    liquidity: u64,
+    amount0_min: u64,
+    amount1_min: u64,
+    deadline: Option<i64>,
+) -> Instruction {
+    instruction(
+        "remove_liquidity",
+        &(liquidity, amount0_min, amount1_min, deadline),
+        liquidity_accounts(owner, pool),
+    )
+}
+
+// the owner's associated token accounts are used for `mint` and the LP
+fn single_liquidity_accounts(
+    owner: Pubkey,
+    pool: &PoolKeys,
+    mint: &Pubkey,
+) -> Result<Vec<AccountMeta>> {
+    let ((mint, token_program), _) = pool.direction(mint)?;
+    let lp_mint = pool.lp_mint();
+    Ok(vec![
+        AccountMeta::new_readonly(pda::config(), false),
+        AccountMeta::new(owner, true),
+        AccountMeta::new(
+            pda::associated_token_address(&owner, &mint, &token_program),
+            false,
+        ),
+        AccountMeta::new(
+            pda::associated_token_address(&owner, &lp_mint, &TOKEN_PROGRAM_ID),
+            false,
+        ),
+        AccountMeta::new(pool.pool, false),
+        AccountMeta::new_readonly(pool.authority(), false),
+        AccountMeta::new(pool.vault_lp(), false),
+        AccountMeta::new(pool.vault0(), false),
+        AccountMeta::new(pool.vault1(), false),
+        AccountMeta::new_readonly(pool.token0, false),
+        AccountMeta::new_readonly(pool.token1, false),
+        AccountMeta::new(lp_mint, false),
+        AccountMeta::new(pool.observations(), false),
+        AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID, false),
+        AccountMeta::new_readonly(TOKEN_PROGRAM_ID, false),
+        AccountMeta::new_readonly(pool.token_program0, false),
+        AccountMeta::new_readonly(pool.token_program1, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ])
+}
+
+pub fn add_liquidity_single(
+    owner: Pubkey,
+    pool: &PoolKeys,
+    mint_in: Pubkey,
+    input_amount: u64,
+    min_liquidity: u64,
+    deadline: Option<i64>,
+) -> Result<Instruction> {
+    Ok(instruction(
+        "add_liquidity_single",
+        &(input_amount, min_liquidity, deadline),
+        single_liquidity_accounts(owner, pool, &mint_in)?,
+    ))
+}
+
+pub fn remove_liquidity_single(
+    owner: Pubkey,
+    pool: &PoolKeys,
+    mint_out: Pubkey,
+    liquidity: u64,
+    min_amount_out: u64,
+    deadline: Option<i64>,
+) -> Result<Instruction> {
+    Ok(instruction(
+        "remove_liquidity_single",
+        &(liquidity, min_amount_out, deadline),
+        single_liquidity_accounts(owner, pool, &mint_out)?,
+    ))
+}
+
+// swaps `mint_src` for the pool's other token between the owner's associated token accounts
+fn swap_accounts(owner: Pubkey, pool: &PoolKeys, mint_src: &Pubkey) -> Result<Vec<AccountMeta>> {
+    let ((mint_src, token_program_src), (mint_des, token_program_des)) =
+        pool.direction(mint_src)?;
+    Ok(vec![
+        AccountMeta::new_readonly(pda::config(), false),
+        AccountMeta::new(owner, true),
+        AccountMeta::new(
+            pda::associated_token_address(&owner, &mint_src, &token_program_src),
+            false,
+        ),
+        AccountMeta::new(
+            pda::associated_token_address(&owner, &mint_des, &token_program_des),
+            false,
+        ),
+        AccountMeta::new(pool.pool, false),
+        AccountMeta::new_readonly(pool.authority(), false),
+        AccountMeta::new(pda::vault(&pool.pool, &mint_src, &token_program_src), false),
+        AccountMeta::new(pda::vault(&pool.pool, &mint_des, &token_program_des), false),
+        AccountMeta::new_readonly(mint_src, false),
+        AccountMeta::new_readonly(mint_des, false),
+        AccountMeta::new(pool.lp_mint(), false),
+        AccountMeta::new(pool.observations(), false),
+        AccountMeta::new_readonly(ASSOCIATED_TOKEN_PROGRAM_ID, false),
+        AccountMeta::new_readonly(token_program_src, false),
+        AccountMeta::new_readonly(token_program_des, false),
+        AccountMeta::new_readonly(system_program::ID, false),
+    ])
+}
+
+pub fn swap_exact_input(
+    owner: Pubkey,
+    pool: &PoolKeys,
+    mint_src: Pubkey,
+    input_amount: u64,
+    min_output_amount: u64,
+    deadline: Option<i64>,
+) -> Result<Instruction> {
+    Ok(instruction(
+        "swap_exact_input",
+        &(input_amount, min_output_amount, deadline),
+        swap_accounts(owner, pool, &mint_src)?,
+    ))
+}
+
+pub fn swap_exact_output(
+    owner: Pubkey,
+    pool: &PoolKeys,
+    mint_src: Pubkey,
+    output_amount: u64,
+    max_input_amount: u64,
+    deadline: Option<i64>,
+) -> Result<Instruction> {
+    Ok(instruction(
+        "swap_exact_output",
+        &(output_amount, max_input_amount, deadline),
+        swap_accounts(owner, pool, &mint_src)?,
+    ))
+}
+
+// `route` lists the pools from `mint_src` onwards, each hop's accounts follow the fixed ones
+fn route_accounts(
+    owner: Pubkey,
+    route: &[PoolKeys],
+    mint_src: &Pubkey,
+) -> Result<Vec<AccountMeta>> {
+    let mut hops = Vec::with_capacity(route.len() * 7);
+    let mut mint_in = *mint_src;
+    let mut src = None;
+    let mut des = None;
+    for pool in route {
+        let ((mint, token_program_in), (mint_out, token_program_out)) = pool.direction(&mint_in)?;
+        src.get_or_insert((mint, token_program_in));
+        des = Some((mint_out, token_program_out));
+        hops.extend([
+            AccountMeta::new(pool.pool, false),
+            AccountMeta::new_readonly(pool.authority(), false),
+            AccountMeta::new(pda::vault(&pool.pool, &mint, &token_program_in), false),
+            AccountMeta::new(pda::vault(&pool.pool, &mint_out, &token_program_out), false),
+            AccountMeta::new(pool.observations(), false),
+            AccountMeta::new_readonly(mint_out, false),
+            AccountMeta::new_readonly(token_program_out, false),
+        ]);
+        mint_in = mint_out;
+    }
+    let (Some((mint_src, token_program_src)), Some((mint_des, token_program_des))) = (src, des)
+    else {
+        return Err(Error::InvalidMint);
+    };
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(pda::config(), false),
+        AccountMeta::new(owner, true),
+        AccountMeta::new(
+            pda::associated_token_address(&owner, &mint_src, &token_program_src),
+            false,
+        ),
+        AccountMeta::new(
+            pda::associated_token_address(&owner, &mint_des, &token_program_des),
+            false,
+        ),
+        AccountMeta::new_readonly(mint_src, false),
+        AccountMeta::new_readonly(mint_des, false),
+        AccountMeta::new_readonly(token_program_src, false),
+        AccountMeta::new_readonly(token_program_des, false),
+    ];
+    accounts.extend(hops);
+    Ok(accounts)
+}
+
+pub fn swap_exact_input_route(
+    owner: Pubkey,
+    route: &[PoolKeys],
+    mint_src: Pubkey,
+    input_amount: u64,
+    min_output_amount: u64,
+    deadline: Option<i64>,
+) -> Result<Instruction> {
+    Ok(instruction(
+        "swap_exact_input_route",
+        &(input_amount, min_output_amount, deadline),
+        route_accounts(owner, route, &mint_src)?,
+    ))
+}
+
+pub fn swap_exact_output_route(
+    owner: Pubkey,
+    route: &[PoolKeys],
+    mint_src: Pubkey,
+    output_amount: u64,
+    max_input_amount: u64,
+    deadline: Option<i64>,
+) -> Result<Instruction> {
+    Ok(instruction(
+        "swap_exact_output_route",
+        &(output_amount, max_input_amount, deadline),
+        route_accounts(owner, route, &mint_src)?,
+    ))
+}
+
+pub fn sync(pool: &PoolKeys) -> Instruction {
+    instruction(
+        "sync",
+        &(),
+        vec![
+            AccountMeta::new(pool.pool, false),
+            AccountMeta::new_readonly(pool.authority(), false),
+            AccountMeta::new_readonly(pool.vault0(), false),
+            AccountMeta::new_readonly(pool.vault1(), false),
+            AccountMeta::new(pool.observations(), false),
+        ],
+    )
+}
+
+pub fn skim(pool: &PoolKeys, recipient0: Pubkey, recipient1: Pubkey) -> Instruction {
+    instruction(
+        "skim",
+        &(),
+        vec![
+            AccountMeta::new_readonly(pool.pool, false),
+            AccountMeta::new_readonly(pool.authority(), false),
+            AccountMeta::new(pool.vault0(), false),
+            AccountMeta::new(pool.vault1(), false),
+            AccountMeta::new(recipient0, false),
+            AccountMeta::new(recipient1, false),
+            AccountMeta::new_readonly(pool.token0, false),
+            AccountMeta::new_readonly(pool.token1, false),
+            AccountMeta::new_readonly(pool.token_program0, false),
+            AccountMeta::new_readonly(pool.token_program1, false),
+        ],
+    )
+}
+
+// the accounts `callback_program` needs are appended to the returned instruction's accounts
+pub fn flash_swap(
+    owner: Pubkey,
+    pool: &PoolKeys,
+    receivers: (Pubkey, Pubkey),
+    amount0_out: u64,
+    amount1_out: u64,
+    callback_program: Pubkey,
+    data: Vec<u8>,
+) -> Instruction {
+    instruction(
+        "flash_swap",
+        &(amount0_out, amount1_out, data),
+        vec![
+            AccountMeta::new_readonly(pda::config(), false),
+            AccountMeta::new_readonly(owner, true),
+            AccountMeta::new(receivers.0, false),
+            AccountMeta::new(receivers.1, false),
+            AccountMeta::new(pool.pool, false),
+            AccountMeta::new_readonly(pool.authority(), false),
+            AccountMeta::new(pool.vault0(), false),
+            AccountMeta::new(pool.vault1(), false),
+            AccountMeta::new(pool.observations(), false),
+            AccountMeta::new_readonly(pool.token0, false),
+            AccountMeta::new_readonly(pool.token1, false),
+            AccountMeta::new_readonly(callback_program, false),
+            AccountMeta::new_readonly(pool.token_program0, false),
+            AccountMeta::new_readonly(pool.token_program1, false),
+        ],
+    )
+}
+
+fn quote_swap_accounts(pool: &PoolKeys, mint_src: &Pubkey) -> Result<Vec<AccountMeta>> {
+    let ((mint_src, _), (mint_des, _)) = pool.direction(mint_src)?;
+    Ok(vec![
+        AccountMeta::new_readonly(pool.pool, false),
+        AccountMeta::new_readonly(mint_src, false),
+        AccountMeta::new_readonly(mint_des, false),
+    ])
+}
+
+pub fn quote_exact_input(
+    pool: &PoolKeys,
+    mint_src: Pubkey,
+    input_amount: u64,
+) -> Result<Instruction> {
+    Ok(instruction(
+        "quote_exact_input",
+        &input_amount,
+        quote_swap_accounts(pool, &mint_src)?,
+    ))
+}
+
+pub fn quote_exact_output(
+    pool: &PoolKeys,
+    mint_src: Pubkey,
+    output_amount: u64,
+) -> Result<Instruction> {
+    Ok(instruction(
+        "quote_exact_output",
+        &output_amount,
+        quote_swap_accounts(pool, &mint_src)?,
+    ))
+}
+
+fn quote_liquidity_accounts(pool: &PoolKeys) -> Vec<AccountMeta> {
+    vec![
+        AccountMeta::new_readonly(pda::config(), false),
+        AccountMeta::new_readonly(pool.pool, false),
+        AccountMeta::new_readonly(pool.token0, false),
+        AccountMeta::new_readonly(pool.token1, false),
+        AccountMeta::new_readonly(pool.lp_mint(), false),
+    ]
+}
+
+pub fn quote_add_liquidity(
+    pool: &PoolKeys,
+    amount0_desired: u64,
+    amount1_desired: u64,
+) -> Instruction {
+    instruction(
+        "quote_add_liquidity",
+        &(amount0_desired, amount1_desired),
+        quote_liquidity_accounts(pool),
+    )
+}
+
+pub fn quote_remove_liquidity(pool: &PoolKeys, liquidity: u64) -> Instruction {
+    instruction(
+        "quote_remove_liquidity",
+        &liquidity,
+        quote_liquidity_accounts(pool),
+    )
+}
+
+pub fn get_twap(pool: Pubkey, start: PriceObservation) -> Instruction {
+    instruction(
+        "get_twap",
+        &start,
+        vec![AccountMeta::new_readonly(pool, false)],
+    )
+}
+
+pub fn increase_observation_cardinality(
+    payer: Pubkey,
+    pool: Pubkey,
+    cardinality_next: u16,
+) -> Instruction {
+    instruction(
+        "increase_observation_cardinality",
+        &cardinality_next,
+        vec![
+            AccountMeta::new(payer, true),
+            AccountMeta::new_readonly(pool, false),
+            AccountMeta::new(pda::observations(&pool), false),
+            AccountMeta::new_readonly(system_program::ID, false),
+        ],
+    )
+}
+
+pub fn consult(pool: Pubkey, seconds_agos: Vec<u32>) -> Instruction {
+    instruction(
+        "consult",
+        &seconds_agos,
+        vec![
+            AccountMeta::new_readonly(pool, false),
+            AccountMeta::new_readonly(pda::observations(&pool), false),
+        ],
+    )
+}
@@ -0,0 +1,19 @@
+// Off-chain counterpart of the amm program: the pool math as pure functions, PDA derivation and
+// instruction builders, without depending on the program crate itself.
+use solana_program::declare_id;
+
+pub mod error;
+pub mod instruction;
+pub mod math;
+pub mod pda;
+pub mod stable_swap;
+pub mod state;
+pub mod weighted_product;
+
+pub use error::Error;
+pub use state::PoolState;
+
+declare_id!("4tPXqXq5WiLpHPaJSRhpA1we5GhCpQrK3wpdRZFNoFQS");
+
+pub const BASIS_POINTS: u64 = 10000;
+pub const MINIMUM_LIQUIDITY: u64 = 1000;
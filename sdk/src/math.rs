@@ -0,0 +1,295 @@
+// Integer math of the program, kept operation for operation identical so that results match the
+// chain to the last unit.
+use crate::error::{Error, Result};
+use crate::stable_swap::U256;
+use crate::{BASIS_POINTS, MINIMUM_LIQUIDITY};
+use fixed::types::{I64F64, U128F0, U64F64};
+use std::cmp::min;
+
+fn add(a: u128, b: u128) -> Result<u128> {
+    a.checked_add(b).ok_or(Error::MathOverflow)
+}
+
+fn sub(a: u128, b: u128) -> Result<u128> {
+    a.checked_sub(b).ok_or(Error::MathOverflow)
+}
+
+fn mul(a: u128, b: u128) -> Result<u128> {
+    a.checked_mul(b).ok_or(Error::MathOverflow)
+}
+
+fn div(a: u128, b: u128) -> Result<u128> {
+    a.checked_div(b).ok_or(Error::DivisionByZero)
+}
+
+fn to_u64(value: u128) -> Result<u64> {
+    u64::try_from(value).map_err(|_| Error::MathOverflow)
+}
+
+pub fn sqrt(value: u128) -> u128 {
+    U128F0::from_num(value).sqrt().to_num::<u128>()
+}
+
+pub fn get_amount_out(fee: u64, amount_in: u64, reserve_in: u64, reserve_out: u64) -> Result<u64> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(Error::InsufficientLiquidity);
+    }
+    let amount_in_with_fee = mul(amount_in as u128, sub(BASIS_POINTS as u128, fee as u128)?)?;
+    let numerator = mul(amount_in_with_fee, reserve_out as u128)?;
+    let denominator = add(
+        mul(reserve_in as u128, BASIS_POINTS as u128)?,
+        amount_in_with_fee,
+    )?;
+    to_u64(div(numerator, denominator)?)
+}
+
+pub fn get_amount_in(fee: u64, amount_out: u64, reserve_in: u64, reserve_out: u64) -> Result<u64> {
+    if reserve_in == 0 || reserve_out == 0 || amount_out >= reserve_out {
+        return Err(Error::InsufficientLiquidity);
+    }
+    let numerator = mul(
+        mul(reserve_in as u128, amount_out as u128)?,
+        BASIS_POINTS as u128,
+    )?;
+    let denominator = mul(
+        sub(reserve_out as u128, amount_out as u128)?,
+        sub(BASIS_POINTS as u128, fee as u128)?,
+    )?;
+    to_u64(add(div(numerator, denominator)?, 1)?)
+}
+
+// part of `amount_in` a single-sided deposit swaps for the other token, the program's
+// `constant_product::get_zap_amount`
+pub fn get_zap_amount(fee: u64, amount_in: u64, reserve_in: u64) -> Result<u64> {
+    if reserve_in == 0 {
+        return Err(Error::InsufficientLiquidity);
+    }
+    // below 2^160 for any u64 amounts, no step can overflow
+    let fee_complement = U256::from(BASIS_POINTS.checked_sub(fee).ok_or(Error::MathOverflow)?);
+    let reserve_in = U256::from(reserve_in);
+    let b = (U256::from(BASIS_POINTS) + fee_complement) * reserve_in;
+    let discriminant =
+        b * b + U256::from(4 * BASIS_POINTS) * fee_complement * reserve_in * U256::from(amount_in);
+    let zap_amount = (discriminant.integer_sqrt() - b)
+        .checked_div(U256::from(2) * fee_complement)
+        .ok_or(Error::DivisionByZero)?;
+    to_u64(u128::try_from(zap_amount).map_err(|_| Error::MathOverflow)?)
+}
+
+// part of `amount_in` kept by the pool
+pub fn swap_fee(fee: u64, amount_in: u64) -> Result<u64> {
+    to_u64(div(
+        mul(amount_in as u128, fee as u128)?,
+        BASIS_POINTS as u128,
+    )?)
+}
+
+// given some amount of an asset and pair reserves, returns an equivalent amount of the other asset
+pub fn quote(amount0: u64, reserve0: u64, reserve1: u64) -> Result<u64> {
+    if amount0 == 0 {
+        return Err(Error::InsufficientAmount);
+    }
+    if reserve0 == 0 || reserve1 == 0 {
+        return Err(Error::InsufficientReserves);
+    }
+
+    to_u64(div(
+        mul(amount0 as u128, reserve1 as u128)?,
+        reserve0 as u128,
+    )?)
+}
+
+pub fn calculate_liquidity_amounts(
+    reserve0: u64,
+    reserve1: u64,
+    amount0_desired: u64,
+    amount1_desired: u64,
+    amount0_min: u64,
+    amount1_min: u64,
+) -> Result<(u64, u64)> {
+    if reserve0 == 0 && reserve1 == 0 {
+        return Ok((amount0_desired, amount1_desired));
+    }
+
+    let amount1_optimal = quote(amount0_desired, reserve0, reserve1)?;
+    if amount1_optimal <= amount1_desired {
+        if amount1_optimal < amount1_min {
+            return Err(Error::InsufficientAmount);
+        }
+        return Ok((amount0_desired, amount1_optimal));
+    }
+
+    let amount0_optimal = quote(amount1_desired, reserve1, reserve0)?;
+    if amount0_optimal > amount0_desired || amount0_optimal < amount0_min {
+        return Err(Error::InsufficientAmount);
+    }
+    Ok((amount0_optimal, amount1_desired))
+}
+
+// LP minted to the depositor of `amount0` and `amount1`, the first deposit locks MINIMUM_LIQUIDITY
+// of its share
+pub fn calculate_liquidity(
+    amount0: u64,
+    amount1: u64,
+    reserve0: u64,
+    reserve1: u64,
+    lp_supply: u64,
+) -> Result<u64> {
+    let liquidity = if lp_supply == 0 {
+        let liquidity = to_u64(sqrt(mul(amount0 as u128, amount1 as u128)?))?;
+        if liquidity <= MINIMUM_LIQUIDITY {
+            return Err(Error::InsufficientLiquidityMinted);
+        }
+        liquidity - MINIMUM_LIQUIDITY
+    } else {
+        to_u64(min(
+            div(mul(amount0 as u128, lp_supply as u128)?, reserve0 as u128)?,
+            div(mul(amount1 as u128, lp_supply as u128)?, reserve1 as u128)?,
+        ))?
+    };
+    if liquidity == 0 {
+        return Err(Error::InsufficientLiquidityMinted);
+    }
+
+    Ok(liquidity)
+}
+
+pub fn calculate_removed_amounts(
+    liquidity: u64,
+    lp_supply: u64,
+    reserve0: u64,
+    reserve1: u64,
+    amount0_min: u64,
+    amount1_min: u64,
+) -> Result<(u64, u64)> {
+    let amount0 = to_u64(div(
+        mul(liquidity as u128, reserve0 as u128)?,
+        lp_supply as u128,
+    )?)?;
+    let amount1 = to_u64(div(
+        mul(liquidity as u128, reserve1 as u128)?,
+        lp_supply as u128,
+    )?)?;
+
+    if amount0 < amount0_min || amount1 < amount1_min {
+        return Err(Error::InsufficientAmount);
+    }
+    if amount0 == 0 || amount1 == 0 {
+        return Err(Error::InsufficientLiquidityBurned);
+    }
+
+    Ok((amount0, amount1))
+}
+
+// LP minted to the protocol by the next liquidity operation, `protocol_fee_share` is 0 when the
+// protocol fee is off
+pub fn mint_fee(
+    protocol_fee_share: u64,
+    k_last: u128,
+    reserve0: u64,
+    reserve1: u64,
+    lp_supply: u64,
+) -> Result<u64> {
+    if protocol_fee_share == 0 || k_last == 0 {
+        return Ok(0);
+    }
+
+    let root_k = sqrt(mul(reserve0 as u128, reserve1 as u128)?);
+    invariant_growth_liquidity(protocol_fee_share, sqrt(k_last), root_k, lp_supply)
+}
+
+// LP minted to the protocol for the growth from `root_k_last` to `root_k` of an invariant that
+// scales linearly with the reserves, √k or D
+pub fn invariant_growth_liquidity(
+    protocol_fee_share: u64,
+    root_k_last: u128,
+    root_k: u128,
+    lp_supply: u64,
+) -> Result<u64> {
+    if root_k <= root_k_last {
+        return Ok(0);
+    }
+
+    // share φ of the growth: supply * (√k - √k_last) * φ / ((1 - φ) * √k + φ * √k_last)
+    let share = protocol_fee_share as u128;
+    let numerator = mul(lp_supply as u128, root_k - root_k_last)?;
+    let denominator = add(
+        mul(sub(BASIS_POINTS as u128, share)?, root_k)?,
+        mul(share, root_k_last)?,
+    )?;
+    let remainder = numerator
+        .checked_rem(denominator)
+        .ok_or(Error::DivisionByZero)?;
+    // split so that `numerator * share` cannot overflow
+    to_u64(add(
+        mul(div(numerator, denominator)?, share)?,
+        div(mul(remainder, share)?, denominator)?,
+    )?)
+}
+
+// the program's `log2` of a positive Q64.64 number
+pub fn log2(value: U64F64) -> Result<I64F64> {
+    let int = value.checked_int_log2().ok_or(Error::MathOverflow)?;
+    let mut mantissa = if int >= 0 {
+        value >> int as u32
+    } else {
+        value << int.unsigned_abs()
+    };
+    let mut result = I64F64::from_num(int);
+    let mut bit = I64F64::from_bits(1 << 63);
+    for _ in 0..64 {
+        mantissa = mantissa.checked_mul(mantissa).ok_or(Error::MathOverflow)?;
+        if mantissa >= 2 {
+            mantissa >>= 1;
+            result = result.checked_add(bit).ok_or(Error::MathOverflow)?;
+        }
+        bit >>= 1;
+    }
+
+    Ok(result)
+}
+
+// the program's `exp2`, 2^value in Q64.64
+pub fn exp2(value: I64F64) -> Result<U64F64> {
+    if value < -64 {
+        return Ok(U64F64::ZERO);
+    }
+    if value >= 64 {
+        return Err(Error::MathOverflow);
+    }
+    let int = value.floor();
+    let frac = U64F64::from_bits((value - int).to_bits() as u128);
+    let exponent = frac.checked_mul(U64F64::LN_2).ok_or(Error::MathOverflow)?;
+    let (mut result, mut term) = (U64F64::ONE, U64F64::ONE);
+    for k in 1u64.. {
+        term = term
+            .checked_mul(exponent)
+            .ok_or(Error::MathOverflow)?
+            .checked_div(U64F64::from_num(k))
+            .ok_or(Error::DivisionByZero)?;
+        if term == 0 {
+            break;
+        }
+        result = result.checked_add(term).ok_or(Error::MathOverflow)?;
+    }
+
+    let int = int.to_num::<i32>();
+    Ok(if int >= 0 {
+        result << int as u32
+    } else {
+        result >> int.unsigned_abs()
+    })
+}
+
+// the program's `pow`, base^exponent as 2^(exponent·log2 base)
+pub fn pow(base: U64F64, exponent: U64F64) -> Result<U64F64> {
+    if base == 0 {
+        return Ok(U64F64::ZERO);
+    }
+    let exponent = I64F64::checked_from_num(exponent).ok_or(Error::MathOverflow)?;
+    exp2(
+        log2(base)?
+            .checked_mul(exponent)
+            .ok_or(Error::MathOverflow)?,
+    )
+}
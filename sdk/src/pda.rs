@@ -0,0 +1,57 @@
+use crate::state::CurveParams;
+use crate::ID;
+use solana_program::pubkey;
+use solana_program::pubkey::Pubkey;
+
+pub const TOKEN_PROGRAM_ID: Pubkey = pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022_PROGRAM_ID: Pubkey = pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+pub fn config() -> Pubkey {
+    Pubkey::find_program_address(&[b"config"], &ID).0
+}
+
+pub fn fee_tiers() -> Pubkey {
+    Pubkey::find_program_address(&[b"fee_tiers"], &ID).0
+}
+
+// `token0` and `token1` in pool order, one pool per mint pair, fee tier and curve
+pub fn pool(token0: &Pubkey, token1: &Pubkey, fee: u64, curve: &CurveParams) -> Pubkey {
+    Pubkey::find_program_address(
+        &[
+            b"pool",
+            token0.as_ref(),
+            token1.as_ref(),
+            &fee.to_le_bytes(),
+            &curve.seed(),
+        ],
+        &ID,
+    )
+    .0
+}
+
+pub fn authority(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"authority", pool.as_ref()], &ID).0
+}
+
+pub fn lp_mint(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"lp_mint", pool.as_ref()], &ID).0
+}
+
+pub fn observations(pool: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"observations", pool.as_ref()], &ID).0
+}
+
+pub fn associated_token_address(owner: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[owner.as_ref(), token_program.as_ref(), mint.as_ref()],
+        &ASSOCIATED_TOKEN_PROGRAM_ID,
+    )
+    .0
+}
+
+// the pool authority's token account holding `mint`
+pub fn vault(pool: &Pubkey, mint: &Pubkey, token_program: &Pubkey) -> Pubkey {
+    associated_token_address(&authority(pool), mint, token_program)
+}
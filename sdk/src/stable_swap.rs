@@ -0,0 +1,238 @@
+// StableSwap math of the program's stable pools, kept operation for operation identical to
+// `amm::stable_swap`:
+//
+//   A·n·(x + y) + D = A·n·D + D³ / (n²·x·y), n = 2
+use crate::error::{Error, Result};
+use crate::math;
+use crate::BASIS_POINTS;
+
+pub use u256::U256;
+
+#[allow(clippy::assign_op_pattern, clippy::manual_div_ceil)]
+mod u256 {
+    uint::construct_uint! {
+        pub struct U256(4);
+    }
+}
+
+const N_COINS: u64 = 2;
+const MAX_ITERATIONS: usize = 255;
+
+fn add(a: U256, b: U256) -> Result<U256> {
+    a.checked_add(b).ok_or(Error::MathOverflow)
+}
+
+fn sub(a: U256, b: U256) -> Result<U256> {
+    a.checked_sub(b).ok_or(Error::MathOverflow)
+}
+
+fn mul(a: U256, b: U256) -> Result<U256> {
+    a.checked_mul(b).ok_or(Error::MathOverflow)
+}
+
+fn div(a: U256, b: U256) -> Result<U256> {
+    a.checked_div(b).ok_or(Error::DivisionByZero)
+}
+
+fn to_u128(value: U256) -> Result<u128> {
+    u128::try_from(value).map_err(|_| Error::MathOverflow)
+}
+
+fn to_u64(value: u128) -> Result<u64> {
+    u64::try_from(value).map_err(|_| Error::MathOverflow)
+}
+
+fn abs_diff(a: U256, b: U256) -> U256 {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+// D of the reserves `x` and `y`, zero for an empty pool
+pub fn compute_d(amp: u64, x: u64, y: u64) -> Result<u128> {
+    if x == 0 && y == 0 {
+        return Ok(0);
+    }
+    if x == 0 || y == 0 {
+        return Err(Error::InsufficientLiquidity);
+    }
+
+    let n = U256::from(N_COINS);
+    let (x, y) = (U256::from(x), U256::from(y));
+    let sum = add(x, y)?;
+    let n_squared_xy = mul(mul(mul(n, n)?, x)?, y)?;
+    let ann = mul(U256::from(amp), n)?;
+    let mut d = sum;
+    for _ in 0..MAX_ITERATIONS {
+        // D³ / (n²·x·y) in a single division, as the program does
+        let d_p = div(mul(mul(d, d)?, d)?, n_squared_xy)?;
+        let d_prev = d;
+        let numerator = mul(add(mul(ann, sum)?, mul(d_p, n)?)?, d)?;
+        let denominator = add(
+            mul(sub(ann, U256::one())?, d)?,
+            mul(add(n, U256::one())?, d_p)?,
+        )?;
+        d = div(numerator, denominator)?;
+        if abs_diff(d, d_prev) <= U256::one() {
+            return to_u128(d);
+        }
+    }
+
+    Err(Error::InvariantNotConverged)
+}
+
+// the reserve of one token that keeps D unchanged when the other token's reserve is `x`
+pub fn compute_y(amp: u64, x: u64, d: u128) -> Result<u64> {
+    if x == 0 {
+        return Err(Error::InsufficientLiquidity);
+    }
+
+    let n = U256::from(N_COINS);
+    let (x, d) = (U256::from(x), U256::from(d));
+    let ann = mul(U256::from(amp), n)?;
+    let b = add(x, div(d, ann)?)?;
+    let c = div(mul(mul(d, d)?, d)?, mul(mul(mul(n, n)?, x)?, ann)?)?;
+    let mut y = d;
+    for _ in 0..MAX_ITERATIONS {
+        let y_prev = y;
+        y = div(add(mul(y, y)?, c)?, sub(add(mul(y, n)?, b)?, d)?)?;
+        if abs_diff(y, y_prev) <= U256::one() {
+            return to_u64(to_u128(y)?);
+        }
+    }
+
+    Err(Error::InvariantNotConverged)
+}
+
+pub fn get_amount_out(
+    amp: u64,
+    fee: u64,
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+) -> Result<u64> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(Error::InsufficientLiquidity);
+    }
+    let fee_complement = BASIS_POINTS.checked_sub(fee).ok_or(Error::MathOverflow)?;
+    let amount_in_after_fee = to_u64(
+        (amount_in as u128)
+            .checked_mul(fee_complement as u128)
+            .ok_or(Error::MathOverflow)?
+            / BASIS_POINTS as u128,
+    )?;
+    let d = compute_d(amp, reserve_in, reserve_out)?;
+    let reserve_in_after = reserve_in
+        .checked_add(amount_in_after_fee)
+        .ok_or(Error::MathOverflow)?;
+    let reserve_out_after = compute_y(amp, reserve_in_after, d)?;
+
+    Ok(reserve_out
+        .saturating_sub(reserve_out_after)
+        .saturating_sub(1))
+}
+
+pub fn get_amount_in(
+    amp: u64,
+    fee: u64,
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+) -> Result<u64> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(Error::InsufficientLiquidity);
+    }
+    let d = compute_d(amp, reserve_in, reserve_out)?;
+    let reserve_out_after = reserve_out
+        .checked_sub(amount_out)
+        .ok_or(Error::MathOverflow)?;
+    let reserve_in_after = compute_y(amp, reserve_out_after, d)?;
+    let amount_in_after_fee = reserve_in_after.saturating_sub(reserve_in) as u128 + 1;
+
+    // gross up by the fee, rounding up
+    let fee_complement = BASIS_POINTS.checked_sub(fee).ok_or(Error::MathOverflow)? as u128;
+    let numerator = amount_in_after_fee
+        .checked_mul(BASIS_POINTS as u128)
+        .and_then(|n| n.checked_add(fee_complement.checked_sub(1)?))
+        .ok_or(Error::MathOverflow)?;
+    to_u64(
+        numerator
+            .checked_div(fee_complement)
+            .ok_or(Error::DivisionByZero)?,
+    )
+}
+
+// part of `amount_in` a single-sided deposit swaps for the other token, walked along the line of
+// the reserves the swap may end on to where it crosses the curve of D
+pub fn get_zap_amount(
+    amp: u64,
+    fee: u64,
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+) -> Result<u64> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(Error::InsufficientLiquidity);
+    }
+    let n = U256::from(N_COINS);
+    let d = U256::from(compute_d(amp, reserve_in, reserve_out)?);
+    let d_cubed = mul(mul(d, d)?, d)?;
+    let ann = mul(U256::from(amp), n)?;
+    let ann_d = mul(ann, d)?;
+    let basis_points = U256::from(BASIS_POINTS);
+    let fee_complement = U256::from(BASIS_POINTS.checked_sub(fee).ok_or(Error::MathOverflow)?);
+    let total_in = add(U256::from(reserve_in), U256::from(amount_in))?;
+    let scale = mul(basis_points, total_in)?;
+    let dx = mul(fee_complement, total_in)?;
+    let dy = mul(basis_points, U256::from(reserve_out))?;
+
+    let mut zap_amount = math::get_zap_amount(fee, amount_in, reserve_in)?;
+    let mut below = 0;
+    for _ in 0..MAX_ITERATIONS {
+        let swapped = U256::from(zap_amount);
+        let x = add(
+            U256::from(reserve_in),
+            div(mul(swapped, fee_complement)?, basis_points)?,
+        )?;
+        let y = div(
+            add(
+                mul(
+                    U256::from(reserve_out),
+                    add(U256::from(reserve_in), swapped)?,
+                )?,
+                sub(total_in, U256::one())?,
+            )?,
+            total_in,
+        )?;
+        let d_p = div(d_cubed, mul(mul(mul(n, n)?, x)?, y)?)?;
+        let lhs = add(mul(ann, add(x, y)?)?, d)?;
+        let rhs = add(ann_d, d_p)?;
+        let slope = add(
+            add(mul(ann, add(dx, dy)?)?, div(mul(d_p, dx)?, x)?)?,
+            div(mul(d_p, dy)?, y)?,
+        )?;
+        if lhs <= rhs {
+            let step = div(mul(sub(rhs, lhs)?, scale)?, slope)?;
+            let step = to_u64(to_u128(step.min(U256::from(amount_in)))?)?;
+            if step == 0 || zap_amount == amount_in {
+                return Ok(zap_amount);
+            }
+            below = zap_amount;
+            zap_amount = zap_amount.saturating_add(step).min(amount_in);
+        } else {
+            let step = div(
+                add(mul(sub(lhs, rhs)?, scale)?, sub(slope, U256::one())?)?,
+                slope,
+            )?;
+            let step = to_u64(to_u128(step.min(U256::from(amount_in)))?)?;
+            if zap_amount.saturating_sub(step) <= below {
+                return Ok(below);
+            }
+            zap_amount -= step;
+        }
+    }
+
+    Err(Error::InvariantNotConverged)
+}
@@ -0,0 +1,412 @@
+use crate::error::{Error, Result};
+use crate::math;
+use crate::stable_swap;
+use crate::weighted_product;
+use crate::MINIMUM_LIQUIDITY;
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_program::hash::hash;
+use solana_program::pubkey::Pubkey;
+
+// mirrors the program's `CurveType`
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CurveType {
+    #[default]
+    ConstantProduct,
+    Stable,
+    Weighted,
+}
+
+// argument of `create_pool`, mirrors the program's `CurveParams`
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CurveParams {
+    ConstantProduct,
+    Stable { amp: u64 },
+    // weights in basis points, adding up to `BASIS_POINTS`
+    Weighted { weight0: u64, weight1: u64 },
+}
+
+impl CurveParams {
+    // the program's `CurveParams::seed`
+    pub fn seed(&self) -> Vec<u8> {
+        match *self {
+            CurveParams::ConstantProduct => vec![0],
+            CurveParams::Stable { .. } => vec![1],
+            CurveParams::Weighted { weight0, .. } => [&[2], &weight0.to_le_bytes()[..]].concat(),
+        }
+    }
+}
+
+// what the pool math needs from a pool account and its LP mint
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolState {
+    pub token0: Pubkey,
+    pub token1: Pubkey,
+    // swap fee in basis points
+    pub fee: u64,
+    pub curve_type: CurveType,
+    // amplification coefficient of a stable pool, zero for other curves
+    pub amp: u64,
+    // weights of a weighted pool in basis points, zero for other curves
+    pub weight0: u64,
+    pub weight1: u64,
+    // k, or D or V for a stable or weighted pool
+    pub k_last: u128,
+    // reserves `k_last` was taken of, a stable pool's D_last is recomputed from them at `amp`
+    pub reserve0_last: u64,
+    pub reserve1_last: u64,
+    pub reserve0: u64,
+    pub reserve1: u64,
+    pub lp_supply: u64,
+}
+
+// layout of the program's `Pool` account after its discriminator
+#[derive(BorshDeserialize)]
+struct PoolAccount {
+    token0: Pubkey,
+    token1: Pubkey,
+    fee: u64,
+    curve_type: CurveType,
+    initial_amp: u64,
+    future_amp: u64,
+    initial_amp_time: i64,
+    future_amp_time: i64,
+    weight0: u64,
+    weight1: u64,
+    _paused: bool,
+    k_last: u128,
+    reserve0_last: u64,
+    reserve1_last: u64,
+    _protocol_fees: u64,
+    reserve0: u64,
+    reserve1: u64,
+    _price0_cumulative_last: u128,
+    _price1_cumulative_last: u128,
+    _block_timestamp_last: i64,
+}
+
+impl PoolAccount {
+    // the program's `Pool::amp`
+    fn amp(&self, timestamp: i64) -> u64 {
+        // a pool that never ramped has nothing to interpolate, whatever the clock says
+        let duration = self.future_amp_time.saturating_sub(self.initial_amp_time) as i128;
+        if timestamp >= self.future_amp_time || duration <= 0 {
+            return self.future_amp;
+        }
+        let elapsed = timestamp.saturating_sub(self.initial_amp_time).max(0) as i128;
+        let (initial, future) = (self.initial_amp as i128, self.future_amp as i128);
+        (initial + (future - initial) * elapsed / duration) as u64
+    }
+}
+
+impl PoolState {
+    // decodes the raw data of a pool account, `lp_supply` is read from the pool's LP mint and
+    // `timestamp` is the cluster time the amplification of a ramping stable pool is taken at
+    pub fn from_account_data(data: &[u8], lp_supply: u64, timestamp: i64) -> Result<Self> {
+        if data.len() < 8 || data[..8] != account_discriminator("Pool") {
+            return Err(Error::InvalidAccountData);
+        }
+        let pool =
+            PoolAccount::deserialize(&mut &data[8..]).map_err(|_| Error::InvalidAccountData)?;
+
+        Ok(Self {
+            token0: pool.token0,
+            token1: pool.token1,
+            fee: pool.fee,
+            curve_type: pool.curve_type,
+            amp: if pool.curve_type == CurveType::Stable {
+                pool.amp(timestamp)
+            } else {
+                0
+            },
+            weight0: pool.weight0,
+            weight1: pool.weight1,
+            k_last: pool.k_last,
+            reserve0_last: pool.reserve0_last,
+            reserve1_last: pool.reserve1_last,
+            reserve0: pool.reserve0,
+            reserve1: pool.reserve1,
+            lp_supply,
+        })
+    }
+
+    // (reserve_in, reserve_out) when swapping `mint_in` for the other token
+    pub fn reserves_for(&self, mint_in: Pubkey) -> Result<(u64, u64)> {
+        if mint_in == self.token0 {
+            Ok((self.reserve0, self.reserve1))
+        } else if mint_in == self.token1 {
+            Ok((self.reserve1, self.reserve0))
+        } else {
+            Err(Error::InvalidMint)
+        }
+    }
+
+    // (weight of `mint_in`, weight of the other token) of a weighted pool
+    fn weights_for(&self, mint_in: Pubkey) -> (u64, u64) {
+        if mint_in == self.token0 {
+            (self.weight0, self.weight1)
+        } else {
+            (self.weight1, self.weight0)
+        }
+    }
+
+    // √k, D or V of the reserves, which all grow linearly with them
+    fn invariant(&self, reserve0: u64, reserve1: u64) -> Result<u128> {
+        match self.curve_type {
+            CurveType::ConstantProduct => Ok(math::sqrt(
+                (reserve0 as u128)
+                    .checked_mul(reserve1 as u128)
+                    .ok_or(Error::MathOverflow)?,
+            )),
+            CurveType::Stable => stable_swap::compute_d(self.amp, reserve0, reserve1),
+            CurveType::Weighted => {
+                weighted_product::compute_invariant(self.weight0, self.weight1, reserve0, reserve1)
+            }
+        }
+    }
+
+    pub fn get_amount_out(&self, mint_in: Pubkey, amount_in: u64) -> Result<u64> {
+        let (reserve_in, reserve_out) = self.reserves_for(mint_in)?;
+        match self.curve_type {
+            CurveType::ConstantProduct => {
+                math::get_amount_out(self.fee, amount_in, reserve_in, reserve_out)
+            }
+            CurveType::Stable => {
+                stable_swap::get_amount_out(self.amp, self.fee, amount_in, reserve_in, reserve_out)
+            }
+            CurveType::Weighted => {
+                let (weight_in, weight_out) = self.weights_for(mint_in);
+                weighted_product::get_amount_out(
+                    weight_in,
+                    weight_out,
+                    self.fee,
+                    amount_in,
+                    reserve_in,
+                    reserve_out,
+                )
+            }
+        }
+    }
+
+    pub fn get_amount_in(&self, mint_in: Pubkey, amount_out: u64) -> Result<u64> {
+        let (reserve_in, reserve_out) = self.reserves_for(mint_in)?;
+        match self.curve_type {
+            CurveType::ConstantProduct => {
+                math::get_amount_in(self.fee, amount_out, reserve_in, reserve_out)
+            }
+            CurveType::Stable => {
+                stable_swap::get_amount_in(self.amp, self.fee, amount_out, reserve_in, reserve_out)
+            }
+            CurveType::Weighted => {
+                let (weight_in, weight_out) = self.weights_for(mint_in);
+                weighted_product::get_amount_in(
+                    weight_in,
+                    weight_out,
+                    self.fee,
+                    amount_out,
+                    reserve_in,
+                    reserve_out,
+                )
+            }
+        }
+    }
+
+    // LP the protocol is minted ahead of the next liquidity operation
+    pub fn mint_fee(&self, protocol_fee_share: u64) -> Result<u64> {
+        match self.curve_type {
+            CurveType::ConstantProduct => math::mint_fee(
+                protocol_fee_share,
+                self.k_last,
+                self.reserve0,
+                self.reserve1,
+                self.lp_supply,
+            ),
+            CurveType::Stable | CurveType::Weighted => {
+                if protocol_fee_share == 0 || self.k_last == 0 {
+                    return Ok(0);
+                }
+                // the program's D_last, at the same amplification as D
+                let k_last = if self.curve_type == CurveType::Stable {
+                    self.invariant(self.reserve0_last, self.reserve1_last)?
+                } else {
+                    self.k_last
+                };
+                let invariant = self.invariant(self.reserve0, self.reserve1)?;
+                math::invariant_growth_liquidity(
+                    protocol_fee_share,
+                    k_last,
+                    invariant,
+                    self.lp_supply,
+                )
+            }
+        }
+    }
+
+    fn lp_supply_after_fee(&self, protocol_fee_share: u64) -> Result<u64> {
+        self.lp_supply
+            .checked_add(self.mint_fee(protocol_fee_share)?)
+            .ok_or(Error::MathOverflow)
+    }
+
+    // (amount0, amount1, liquidity) of a deposit in pool token order, amounts as they arrive in
+    // the vaults
+    pub fn add_liquidity(
+        &self,
+        amount0_desired: u64,
+        amount1_desired: u64,
+        protocol_fee_share: u64,
+    ) -> Result<(u64, u64, u64)> {
+        let (amount0, amount1) = math::calculate_liquidity_amounts(
+            self.reserve0,
+            self.reserve1,
+            amount0_desired,
+            amount1_desired,
+            0,
+            0,
+        )?;
+        let lp_supply = self.lp_supply_after_fee(protocol_fee_share)?;
+        let liquidity = if self.curve_type == CurveType::ConstantProduct || lp_supply > 0 {
+            math::calculate_liquidity(amount0, amount1, self.reserve0, self.reserve1, lp_supply)?
+        } else {
+            // the first deposit of a stable or weighted pool is minted D or V rather than √k
+            let invariant = self.invariant(amount0, amount1)?;
+            let invariant = u64::try_from(invariant).map_err(|_| Error::MathOverflow)?;
+            if invariant <= MINIMUM_LIQUIDITY {
+                return Err(Error::InsufficientLiquidityMinted);
+            }
+            invariant - MINIMUM_LIQUIDITY
+        };
+
+        Ok((amount0, amount1, liquidity))
+    }
+
+    // part of `amount_in` of `mint_in` a single-sided deposit swaps, the program's `Curve::zap_amount`
+    pub fn zap_amount(&self, mint_in: Pubkey, amount_in: u64) -> Result<u64> {
+        let (reserve_in, reserve_out) = self.reserves_for(mint_in)?;
+        match self.curve_type {
+            CurveType::ConstantProduct => math::get_zap_amount(self.fee, amount_in, reserve_in),
+            CurveType::Stable => {
+                stable_swap::get_zap_amount(self.amp, self.fee, amount_in, reserve_in, reserve_out)
+            }
+            CurveType::Weighted => {
+                let (weight_in, weight_out) = self.weights_for(mint_in);
+                weighted_product::get_zap_amount(
+                    weight_in, weight_out, self.fee, amount_in, reserve_in,
+                )
+            }
+        }
+    }
+
+    // (part swapped, liquidity) of a single-sided deposit of `amount_in` of `mint_in`, as it
+    // arrives in the vault
+    pub fn add_liquidity_single(
+        &self,
+        mint_in: Pubkey,
+        amount_in: u64,
+        protocol_fee_share: u64,
+    ) -> Result<(u64, u64)> {
+        if self.lp_supply == 0 {
+            return Err(Error::InsufficientLiquidity);
+        }
+        let swap_amount = self.zap_amount(mint_in, amount_in)?;
+        let swap_amount_out = self.get_amount_out(mint_in, swap_amount)?;
+        let (reserve_in, reserve_out) = self.reserves_for(mint_in)?;
+        let reserve_in = reserve_in
+            .checked_add(swap_amount)
+            .ok_or(Error::MathOverflow)?;
+        let reserve_out = reserve_out
+            .checked_sub(swap_amount_out)
+            .ok_or(Error::MathOverflow)?;
+        let rest = amount_in - swap_amount;
+        // the deposit is priced on the reserves after the swap, the protocol fee included
+        let ((amount0, reserve0), (amount1, reserve1)) = if mint_in == self.token0 {
+            ((rest, reserve_in), (swap_amount_out, reserve_out))
+        } else {
+            ((swap_amount_out, reserve_out), (rest, reserve_in))
+        };
+        let swapped = PoolState {
+            reserve0,
+            reserve1,
+            ..*self
+        };
+        let liquidity = math::calculate_liquidity(
+            amount0,
+            amount1,
+            reserve0,
+            reserve1,
+            swapped.lp_supply_after_fee(protocol_fee_share)?,
+        )?;
+
+        Ok((swap_amount, liquidity))
+    }
+
+    // (amount0, amount1) leaving the vaults for burning `liquidity`
+    pub fn remove_liquidity(&self, liquidity: u64, protocol_fee_share: u64) -> Result<(u64, u64)> {
+        let lp_supply = self.lp_supply_after_fee(protocol_fee_share)?;
+        math::calculate_removed_amounts(liquidity, lp_supply, self.reserve0, self.reserve1, 0, 0)
+    }
+
+    // (part swapped, amount of `mint_out` leaving its vault) for burning `liquidity` into a single
+    // token, the other token's share being swapped for it on the reserves after the burn
+    pub fn remove_liquidity_single(
+        &self,
+        mint_out: Pubkey,
+        liquidity: u64,
+        protocol_fee_share: u64,
+    ) -> Result<(u64, u64)> {
+        let (amount0, amount1) = self.remove_liquidity(liquidity, protocol_fee_share)?;
+        let burned = PoolState {
+            reserve0: self
+                .reserve0
+                .checked_sub(amount0)
+                .ok_or(Error::MathOverflow)?,
+            reserve1: self
+                .reserve1
+                .checked_sub(amount1)
+                .ok_or(Error::MathOverflow)?,
+            ..*self
+        };
+        let (mint_in, amount, swap_amount) = if mint_out == self.token0 {
+            (self.token1, amount0, amount1)
+        } else if mint_out == self.token1 {
+            (self.token0, amount1, amount0)
+        } else {
+            return Err(Error::InvalidMint);
+        };
+        let swap_amount_out = burned.get_amount_out(mint_in, swap_amount)?;
+        let amount_out = amount
+            .checked_add(swap_amount_out)
+            .ok_or(Error::MathOverflow)?;
+
+        Ok((swap_amount, amount_out))
+    }
+}
+
+pub fn account_discriminator(name: &str) -> [u8; 8] {
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash(format!("account:{name}").as_bytes()).to_bytes()[..8]);
+    discriminator
+}
+
+// argument of `get_twap`
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PriceObservation {
+    pub timestamp: i64,
+    pub price0_cumulative: u128,
+    pub price1_cumulative: u128,
+}
+
+// return data of `get_twap`
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Twap {
+    pub price0_average: u128,
+    pub price1_average: u128,
+    pub observation: PriceObservation,
+}
+
+// return data of `quote_add_liquidity` and `quote_remove_liquidity`
+#[derive(BorshSerialize, BorshDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LiquidityQuote {
+    pub amount0: u64,
+    pub amount1: u64,
+    pub liquidity: u64,
+}
@@ -0,0 +1,177 @@
+// Weighted product math of the program's weighted pools, kept operation for operation identical to
+// `amm::weighted_product`:
+//
+//   V = x^w0 · y^w1, w0 + w1 = 1
+use crate::error::{Error, Result};
+use crate::math::{exp2, log2, pow};
+use crate::BASIS_POINTS;
+use fixed::types::{I64F64, U64F64};
+
+const POW_ERROR_SHIFT: u32 = 48;
+const POW_ERROR: U64F64 = U64F64::from_bits(1 << 4);
+const MAX_ZAP_ITERATIONS: usize = 8;
+
+fn add(a: U64F64, b: U64F64) -> Result<U64F64> {
+    a.checked_add(b).ok_or(Error::MathOverflow)
+}
+
+fn sub(a: U64F64, b: U64F64) -> Result<U64F64> {
+    a.checked_sub(b).ok_or(Error::MathOverflow)
+}
+
+fn mul(a: U64F64, b: U64F64) -> Result<U64F64> {
+    a.checked_mul(b).ok_or(Error::MathOverflow)
+}
+
+fn div(a: U64F64, b: U64F64) -> Result<U64F64> {
+    a.checked_div(b).ok_or(Error::DivisionByZero)
+}
+
+fn to_u64(value: u128) -> Result<u64> {
+    u64::try_from(value).map_err(|_| Error::MathOverflow)
+}
+
+fn round_up(value: U64F64) -> Result<U64F64> {
+    add(add(value, value >> POW_ERROR_SHIFT)?, POW_ERROR)
+}
+
+// V of the reserves `x` and `y` for weights in basis points, zero for an empty pool
+pub fn compute_invariant(weight0: u64, weight1: u64, x: u64, y: u64) -> Result<u128> {
+    if x == 0 && y == 0 {
+        return Ok(0);
+    }
+    if x == 0 || y == 0 {
+        return Err(Error::InsufficientLiquidity);
+    }
+
+    let basis_points = I64F64::from_num(BASIS_POINTS);
+    let w0 = I64F64::from_num(weight0) / basis_points;
+    let w1 = I64F64::from_num(weight1) / basis_points;
+    let exponent = w0
+        .checked_mul(log2(U64F64::from_num(x))?)
+        .zip(w1.checked_mul(log2(U64F64::from_num(y))?))
+        .and_then(|(a, b)| a.checked_add(b))
+        .ok_or(Error::MathOverflow)?;
+    Ok(exp2(exponent)?.to_num::<u128>())
+}
+
+pub fn get_amount_out(
+    weight_in: u64,
+    weight_out: u64,
+    fee: u64,
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+) -> Result<u64> {
+    if reserve_in == 0 || reserve_out == 0 {
+        return Err(Error::InsufficientLiquidity);
+    }
+    let fee_complement = BASIS_POINTS.checked_sub(fee).ok_or(Error::MathOverflow)?;
+    let amount_in_after_fee = to_u64(
+        (amount_in as u128)
+            .checked_mul(fee_complement as u128)
+            .ok_or(Error::MathOverflow)?
+            / BASIS_POINTS as u128,
+    )?;
+    let reserve_in_after = reserve_in
+        .checked_add(amount_in_after_fee)
+        .ok_or(Error::MathOverflow)?;
+    let base = add(
+        div(
+            U64F64::from_num(reserve_in),
+            U64F64::from_num(reserve_in_after),
+        )?,
+        U64F64::DELTA,
+    )?;
+    let exponent = div(U64F64::from_num(weight_in), U64F64::from_num(weight_out))?;
+    let ratio = round_up(pow(base, exponent)?)?;
+    if ratio >= 1 {
+        return Ok(0);
+    }
+
+    Ok(mul(U64F64::from_num(reserve_out), sub(U64F64::ONE, ratio)?)?.to_num::<u64>())
+}
+
+pub fn get_amount_in(
+    weight_in: u64,
+    weight_out: u64,
+    fee: u64,
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+) -> Result<u64> {
+    let amount_out = amount_out
+        .checked_add(reserve_out >> (POW_ERROR_SHIFT - 1))
+        .and_then(|amount| amount.checked_add(2))
+        .ok_or(Error::MathOverflow)?;
+    if reserve_in == 0 || reserve_out <= amount_out {
+        return Err(Error::InsufficientLiquidity);
+    }
+    let base = add(
+        div(
+            U64F64::from_num(reserve_out),
+            U64F64::from_num(reserve_out - amount_out),
+        )?,
+        U64F64::DELTA,
+    )?;
+    let exponent = div(U64F64::from_num(weight_out), U64F64::from_num(weight_in))?;
+    let ratio = round_up(pow(base, exponent)?)?;
+    let amount_in_after_fee = mul(U64F64::from_num(reserve_in), sub(ratio, U64F64::ONE)?)?
+        .checked_ceil()
+        .ok_or(Error::MathOverflow)?
+        .to_num::<u64>();
+
+    let fee_complement = BASIS_POINTS.checked_sub(fee).ok_or(Error::MathOverflow)? as u128;
+    let numerator = (amount_in_after_fee as u128)
+        .checked_mul(BASIS_POINTS as u128)
+        .and_then(|n| n.checked_add(fee_complement.checked_sub(1)?))
+        .ok_or(Error::MathOverflow)?;
+    to_u64(
+        numerator
+            .checked_div(fee_complement)
+            .ok_or(Error::DivisionByZero)?,
+    )
+}
+
+// part of `amount_in` a single-sided deposit swaps for the other token, Newton's method from the
+// closed form without a fee
+pub fn get_zap_amount(
+    weight_in: u64,
+    weight_out: u64,
+    fee: u64,
+    amount_in: u64,
+    reserve_in: u64,
+) -> Result<u64> {
+    if reserve_in == 0 {
+        return Err(Error::InsufficientLiquidity);
+    }
+    let reserve = U64F64::from_num(reserve_in);
+    let growth = add(U64F64::ONE, div(U64F64::from_num(amount_in), reserve)?)?;
+    let basis_points = U64F64::from_num(BASIS_POINTS);
+    let fee_complement = div(
+        U64F64::from_num(BASIS_POINTS.checked_sub(fee).ok_or(Error::MathOverflow)?),
+        basis_points,
+    )?;
+    let exponent = div(U64F64::from_num(weight_in), U64F64::from_num(weight_out))?;
+    let mut t =
+        pow(growth, div(U64F64::from_num(weight_out), basis_points)?)?.saturating_sub(U64F64::ONE);
+    for _ in 0..MAX_ZAP_ITERATIONS {
+        let x = add(U64F64::ONE, mul(fee_complement, t)?)?;
+        let target = mul(growth, pow(div(U64F64::ONE, x)?, exponent)?)?;
+        let rest = add(U64F64::ONE, t)?;
+        if target <= rest {
+            break;
+        }
+        let slope = add(
+            div(mul(mul(exponent, fee_complement)?, target)?, x)?,
+            U64F64::ONE,
+        )?;
+        let step = div(sub(target, rest)?, slope)?;
+        t = add(t, step)?;
+        if mul(step, reserve)? < U64F64::ONE {
+            break;
+        }
+    }
+
+    Ok(mul(t, reserve)?.to_num::<u64>().min(amount_in))
+}
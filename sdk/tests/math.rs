@@ -0,0 +1,426 @@
+// The SDK's math against the program's over random reserves and amounts: both must return the
+// same value, or both fail, for a client to predict what the program does.
+use amm::constant::{MAX_AMP, MIN_AMP, MIN_WEIGHT};
+use amm::curve::{ConstantProduct, Curve, StableSwap, WeightedProduct};
+use amm::state::Pool;
+use amm_sdk::state::CurveType;
+use amm_sdk::{PoolState, BASIS_POINTS};
+use anchor_lang::AccountSerialize;
+use fixed::types::{I64F64, U64F64};
+use proptest::prelude::*;
+use solana_program::pubkey::Pubkey;
+
+const MAX_AMOUNT: u64 = 1 << 48;
+
+fn amount() -> impl Strategy<Value = u64> {
+    1..MAX_AMOUNT
+}
+
+fn fee() -> impl Strategy<Value = u64> {
+    0..BASIS_POINTS
+}
+
+fn amp() -> impl Strategy<Value = u64> {
+    MIN_AMP..=MAX_AMP
+}
+
+fn weight() -> impl Strategy<Value = u64> {
+    MIN_WEIGHT..=BASIS_POINTS - MIN_WEIGHT
+}
+
+proptest! {
+    #[test]
+    fn constant_product_matches_program(
+        fee in fee(),
+        amount in amount(),
+        reserve_in in amount(),
+        reserve_out in amount(),
+    ) {
+        prop_assert_eq!(
+            amm_sdk::math::get_amount_out(fee, amount, reserve_in, reserve_out).ok(),
+            amm::constant_product::get_amount_out(fee, amount, reserve_in, reserve_out).ok()
+        );
+        prop_assert_eq!(
+            amm_sdk::math::get_amount_in(fee, amount, reserve_in, reserve_out).ok(),
+            amm::constant_product::get_amount_in(fee, amount, reserve_in, reserve_out).ok()
+        );
+        prop_assert_eq!(
+            amm_sdk::math::get_zap_amount(fee, amount, reserve_in).ok(),
+            amm::constant_product::get_zap_amount(fee, amount, reserve_in).ok()
+        );
+        prop_assert_eq!(
+            amm_sdk::math::swap_fee(fee, amount).ok(),
+            amm::math::swap_fee(fee, amount).ok()
+        );
+    }
+
+    #[test]
+    fn liquidity_matches_program(
+        reserve0 in 0..MAX_AMOUNT,
+        reserve1 in 0..MAX_AMOUNT,
+        lp_supply in 0..MAX_AMOUNT,
+        amount0 in amount(),
+        amount1 in amount(),
+        amount0_min in 0..MAX_AMOUNT,
+        amount1_min in 0..MAX_AMOUNT,
+    ) {
+        prop_assert_eq!(
+            amm_sdk::math::quote(amount0, reserve0, reserve1).ok(),
+            amm::constant_product::quote(amount0, reserve0, reserve1).ok()
+        );
+        prop_assert_eq!(
+            amm_sdk::math::calculate_liquidity_amounts(
+                reserve0,
+                reserve1,
+                amount0,
+                amount1,
+                amount0_min,
+                amount1_min,
+            )
+            .ok(),
+            amm::constant_product::calculate_liquidity_amounts(
+                reserve0,
+                reserve1,
+                amount0,
+                amount1,
+                amount0_min,
+                amount1_min,
+            )
+            .ok()
+        );
+        prop_assert_eq!(
+            amm_sdk::math::calculate_liquidity(amount0, amount1, reserve0, reserve1, lp_supply)
+                .ok(),
+            amm::constant_product::calculate_liquidity(amount0, amount1, reserve0, reserve1, lp_supply)
+                .ok()
+        );
+        prop_assert_eq!(
+            amm_sdk::math::calculate_removed_amounts(
+                amount0,
+                lp_supply,
+                reserve0,
+                reserve1,
+                amount0_min,
+                amount1_min,
+            )
+            .ok(),
+            amm::constant_product::calculate_removed_amounts(
+                amount0,
+                lp_supply,
+                reserve0,
+                reserve1,
+                amount0_min,
+                amount1_min,
+            )
+            .ok()
+        );
+    }
+
+    #[test]
+    fn stable_swap_matches_program(
+        amp in amp(),
+        fee in fee(),
+        amount in amount(),
+        reserve_in in amount(),
+        reserve_out in amount(),
+    ) {
+        let d = amm_sdk::stable_swap::compute_d(amp, reserve_in, reserve_out).ok();
+        prop_assert_eq!(d, amm::stable_swap::compute_d(amp, reserve_in, reserve_out).ok());
+        if let Some(d) = d {
+            prop_assert_eq!(
+                amm_sdk::stable_swap::compute_y(amp, amount, d).ok(),
+                amm::stable_swap::compute_y(amp, amount, d).ok()
+            );
+        }
+        prop_assert_eq!(
+            amm_sdk::stable_swap::get_amount_out(amp, fee, amount, reserve_in, reserve_out).ok(),
+            amm::stable_swap::get_amount_out(amp, fee, amount, reserve_in, reserve_out).ok()
+        );
+        prop_assert_eq!(
+            amm_sdk::stable_swap::get_amount_in(amp, fee, amount, reserve_in, reserve_out).ok(),
+            amm::stable_swap::get_amount_in(amp, fee, amount, reserve_in, reserve_out).ok()
+        );
+        prop_assert_eq!(
+            amm_sdk::stable_swap::get_zap_amount(amp, fee, amount, reserve_in, reserve_out).ok(),
+            amm::stable_swap::get_zap_amount(amp, fee, amount, reserve_in, reserve_out).ok()
+        );
+    }
+
+    #[test]
+    fn weighted_product_matches_program(
+        weight_in in weight(),
+        fee in fee(),
+        amount in amount(),
+        reserve_in in amount(),
+        reserve_out in amount(),
+    ) {
+        let weight_out = BASIS_POINTS - weight_in;
+        prop_assert_eq!(
+            amm_sdk::weighted_product::compute_invariant(
+                weight_in,
+                weight_out,
+                reserve_in,
+                reserve_out,
+            )
+            .ok(),
+            amm::weighted_product::compute_invariant(weight_in, weight_out, reserve_in, reserve_out)
+                .ok()
+        );
+        prop_assert_eq!(
+            amm_sdk::weighted_product::get_amount_out(
+                weight_in,
+                weight_out,
+                fee,
+                amount,
+                reserve_in,
+                reserve_out,
+            )
+            .ok(),
+            amm::weighted_product::get_amount_out(
+                weight_in,
+                weight_out,
+                fee,
+                amount,
+                reserve_in,
+                reserve_out,
+            )
+            .ok()
+        );
+        prop_assert_eq!(
+            amm_sdk::weighted_product::get_amount_in(
+                weight_in,
+                weight_out,
+                fee,
+                amount,
+                reserve_in,
+                reserve_out,
+            )
+            .ok(),
+            amm::weighted_product::get_amount_in(
+                weight_in,
+                weight_out,
+                fee,
+                amount,
+                reserve_in,
+                reserve_out,
+            )
+            .ok()
+        );
+        prop_assert_eq!(
+            amm_sdk::weighted_product::get_zap_amount(
+                weight_in,
+                weight_out,
+                fee,
+                amount,
+                reserve_in,
+            )
+            .ok(),
+            amm::weighted_product::get_zap_amount(weight_in, weight_out, fee, amount, reserve_in)
+                .ok()
+        );
+    }
+
+    #[test]
+    fn fixed_point_matches_program(
+        base in 1..u128::MAX,
+        exponent in 0..1u128 << 64,
+        power in -(64i128 << 64)..64i128 << 64,
+    ) {
+        let (base, exponent) = (U64F64::from_bits(base), U64F64::from_bits(exponent));
+        let power = I64F64::from_bits(power);
+        prop_assert_eq!(amm_sdk::math::log2(base).ok(), amm::math::log2(base).ok());
+        prop_assert_eq!(amm_sdk::math::exp2(power).ok(), amm::math::exp2(power).ok());
+        prop_assert_eq!(
+            amm_sdk::math::pow(base, exponent).ok(),
+            amm::math::pow(base, exponent).ok()
+        );
+    }
+
+    // the amplification of a stable pool ramping, or not, read at any time around the ramp
+    #[test]
+    fn amp_matches_program(
+        initial_amp in amp(),
+        future_amp in amp(),
+        initial_amp_time in 0..1i64 << 40,
+        duration in prop_oneof![Just(0), 1..1i64 << 20],
+        elapsed in -(1i64 << 20)..2 << 20,
+    ) {
+        let pool = Pool {
+            token0: Pubkey::new_from_array([0; 32]),
+            token1: Pubkey::new_from_array([1; 32]),
+            fee: 0,
+            curve_type: amm::state::CurveType::Stable,
+            initial_amp,
+            future_amp: if duration == 0 { initial_amp } else { future_amp },
+            initial_amp_time,
+            future_amp_time: initial_amp_time + duration,
+            weight0: 0,
+            weight1: 0,
+            paused: false,
+            k_last: 0,
+            reserve0_last: 0,
+            reserve1_last: 0,
+            protocol_fees: 0,
+            reserve0: 0,
+            reserve1: 0,
+            price0_cumulative_last: 0,
+            price1_cumulative_last: 0,
+            block_timestamp_last: 0,
+        };
+        let mut data = Vec::new();
+        pool.try_serialize(&mut data).unwrap();
+        let timestamp = initial_amp_time + elapsed;
+        prop_assert_eq!(
+            PoolState::from_account_data(&data, 0, timestamp).unwrap().amp,
+            pool.amp(timestamp)
+        );
+    }
+
+    // the curve's part of the pool address
+    #[test]
+    fn curve_seed_matches_program(amp in amp(), weight0 in weight()) {
+        use amm::state::CurveParams as ProgramCurve;
+        use amm_sdk::state::CurveParams;
+
+        let weight1 = BASIS_POINTS - weight0;
+        prop_assert_eq!(
+            CurveParams::ConstantProduct.seed(),
+            ProgramCurve::ConstantProduct.seed()
+        );
+        prop_assert_eq!(
+            CurveParams::Stable { amp }.seed(),
+            ProgramCurve::Stable { amp }.seed()
+        );
+        prop_assert_eq!(
+            CurveParams::Weighted { weight0, weight1 }.seed(),
+            ProgramCurve::Weighted { weight0, weight1 }.seed()
+        );
+    }
+
+    // the protocol fee, the zap and a swap through a pool of each kind
+    #[test]
+    fn pool_matches_program_curve(
+        kind in 0..3u8,
+        fee in fee(),
+        amp in amp(),
+        weight0 in weight(),
+        protocol_fee_share in 0..BASIS_POINTS,
+        amount in amount(),
+        reserve0 in amount(),
+        reserve1 in amount(),
+        last0 in amount(),
+        last1 in amount(),
+        lp_supply in amount(),
+    ) {
+        let (pool, curve) = pool(kind, fee, amp, weight0, reserve0, reserve1, lp_supply);
+        // the invariant at smaller reserves, as the last liquidity operation left it
+        let (reserve0_last, reserve1_last) = (last0 % reserve0 + 1, last1 % reserve1 + 1);
+        let k_last = curve.k_last(reserve0_last, reserve1_last);
+        prop_assume!(k_last.is_ok());
+        let pool = PoolState {
+            k_last: k_last.unwrap(),
+            reserve0_last,
+            reserve1_last,
+            ..pool
+        };
+
+        prop_assert_eq!(
+            pool.mint_fee(protocol_fee_share).ok(),
+            if protocol_fee_share == 0 {
+                Some(0)
+            } else {
+                curve
+                    .protocol_fee_liquidity(
+                        protocol_fee_share,
+                        pool.k_last,
+                        reserve0,
+                        reserve1,
+                        lp_supply,
+                    )
+                    .ok()
+            }
+        );
+        prop_assert_eq!(
+            pool.zap_amount(pool.token0, amount).ok(),
+            curve.zap_amount(true, amount, reserve0, reserve1).ok()
+        );
+        prop_assert_eq!(
+            pool.get_amount_out(pool.token1, amount).ok(),
+            curve.swap_exact_input(false, amount, reserve0, reserve1).ok()
+        );
+    }
+
+    // burning more than the supply is an error rather than a panic
+    #[test]
+    fn remove_liquidity_single_beyond_supply(
+        kind in 0..3u8,
+        fee in fee(),
+        amp in amp(),
+        weight0 in weight(),
+        excess in amount(),
+        reserve0 in amount(),
+        reserve1 in amount(),
+        lp_supply in amount(),
+    ) {
+        let (pool, _) = pool(kind, fee, amp, weight0, reserve0, reserve1, lp_supply);
+        prop_assert!(pool.remove_liquidity_single(pool.token0, lp_supply + excess, 0).is_err());
+        prop_assert!(pool.remove_liquidity_single(pool.token1, lp_supply + excess, 0).is_err());
+    }
+}
+
+// the SDK's view of a pool and the program's curve of it: 0 the constant product, 1 StableSwap
+// and 2 weighted
+fn pool(
+    kind: u8,
+    fee: u64,
+    amp: u64,
+    weight0: u64,
+    reserve0: u64,
+    reserve1: u64,
+    lp_supply: u64,
+) -> (PoolState, Box<dyn Curve>) {
+    let weight1 = BASIS_POINTS - weight0;
+    let (curve_type, curve): (_, Box<dyn Curve>) = match kind {
+        0 => (
+            CurveType::ConstantProduct,
+            Box::new(ConstantProduct { fee }),
+        ),
+        1 => (CurveType::Stable, Box::new(StableSwap { fee, amp })),
+        _ => (
+            CurveType::Weighted,
+            Box::new(WeightedProduct {
+                fee,
+                weight0,
+                weight1,
+            }),
+        ),
+    };
+    let pool = PoolState {
+        token0: Pubkey::new_from_array([0; 32]),
+        token1: Pubkey::new_from_array([1; 32]),
+        fee,
+        curve_type,
+        amp: if curve_type == CurveType::Stable {
+            amp
+        } else {
+            0
+        },
+        weight0: if curve_type == CurveType::Weighted {
+            weight0
+        } else {
+            0
+        },
+        weight1: if curve_type == CurveType::Weighted {
+            weight1
+        } else {
+            0
+        },
+        k_last: 0,
+        reserve0_last: 0,
+        reserve1_last: 0,
+        reserve0,
+        reserve1,
+        lp_supply,
+    };
+    (pool, curve)
+}
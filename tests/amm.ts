@@ -28,6 +28,8 @@ interface LPProvider {
 }
 
 const BASIS_POINTS = 10000;
+const MAX_PROTOCOL_FEE_SHARE = 5000;
+const MINIMUM_LIQUIDITY = 1000;
 let fee = 30;
 
 describe('Amm', () => {
@@ -43,6 +45,10 @@ describe('Amm', () => {
         [Buffer.from(anchor.utils.bytes.utf8.encode('config'))],
         program.programId
     );
+    const [feeTiersPDA] = web3.PublicKey.findProgramAddressSync(
+        [Buffer.from('fee_tiers')],
+        program.programId
+    );
     let n_decimals = 9;
     let pool: Pool;
 
@@ -55,42 +61,101 @@ describe('Amm', () => {
 
         expect(config.owner.toBase58()).to.eq(wallet.publicKey.toBase58());
         expect(config.feeTo.toBase58()).to.eq(wallet.publicKey.toBase58());
-        expect(config.fee.eq(new BN(fee))).to.be.true;
+
+        const feeTiers = await program.account.feeTiers.fetch(feeTiersPDA);
+        expect(feeTiers.fees.map((tier) => tier.toNumber())).to.deep.eq([fee]);
     });
 
+    const feeTo = web3.Keypair.generate();
     it('SetFeeTo', async () => {
-        const newFeeTo = web3.Keypair.generate();
-
         const tx = await program.methods
-            .setFeeTo(newFeeTo.publicKey)
+            .setFeeTo(feeTo.publicKey)
             .accounts({ config: configPDA })
             .rpc();
 
         const config = await program.account.config.fetch(configPDA);
-        expect(config.feeTo.toBase58()).to.eq(newFeeTo.publicKey.toBase58());
+        expect(config.feeTo.toBase58()).to.eq(feeTo.publicKey.toBase58());
     });
 
-    it('Set fee', async () => {
+    it('Fee tiers', async () => {
         try {
-            await program.methods
-                .setFee(new BN(BASIS_POINTS))
-                .accounts({ config: configPDA })
-                .rpc();
+            await program.methods.enableFeeTier(new BN(BASIS_POINTS)).rpc();
+            expect.fail('should reject a fee of 100%');
         } catch (e) {
             expect(e).to.be.instanceOf(AnchorError);
             expect((e as AnchorError).error.errorCode.code).to.eq('InvalidFee');
         }
 
-        // assign fee to 50
+        try {
+            await program.methods.enableFeeTier(new BN(fee)).rpc();
+            expect.fail('should reject a tier that is already enabled');
+        } catch (e) {
+            expect(e).to.be.instanceOf(AnchorError);
+            expect((e as AnchorError).error.errorCode.code).to.eq(
+                'FeeTierAlreadyEnabled'
+            );
+        }
+
+        await program.methods.enableFeeTier(new BN(5)).rpc();
+        await program.methods.enableFeeTier(new BN(100)).rpc();
+        await program.methods.disableFeeTier(new BN(100)).rpc();
+
+        // pools below are created on the 50 bps tier
         fee = 50;
+        await program.methods.enableFeeTier(new BN(fee)).rpc();
 
-        const tx = await program.methods
-            .setFee(new BN(fee))
-            .accounts({ config: configPDA })
+        const feeTiers = await program.account.feeTiers.fetch(feeTiersPDA);
+        expect(feeTiers.fees.map((tier) => tier.toNumber())).to.deep.eq([
+            30, 5, 50,
+        ]);
+    });
+
+    it('Ownership transfer', async () => {
+        const newOwner = web3.Keypair.generate();
+        await program.methods.proposeOwner(newOwner.publicKey).rpc();
+
+        let config = await program.account.config.fetch(configPDA);
+        expect(config.owner.toBase58()).to.eq(wallet.publicKey.toBase58());
+        expect(config.pendingOwner.toBase58()).to.eq(
+            newOwner.publicKey.toBase58()
+        );
+
+        const stranger = web3.Keypair.generate();
+        try {
+            await program.methods
+                .acceptOwner()
+                .accounts({ pendingOwner: stranger.publicKey })
+                .signers([stranger])
+                .rpc();
+            expect.fail('should only let the pending owner accept');
+        } catch (e) {
+            expect(e).to.be.instanceOf(AnchorError);
+            expect((e as AnchorError).error.errorCode.code).to.eq(
+                'ConstraintHasOne'
+            );
+        }
+
+        await program.methods
+            .acceptOwner()
+            .accounts({ pendingOwner: newOwner.publicKey })
+            .signers([newOwner])
             .rpc();
+        config = await program.account.config.fetch(configPDA);
+        expect(config.owner.toBase58()).to.eq(newOwner.publicKey.toBase58());
+        expect(config.pendingOwner.equals(web3.PublicKey.default)).to.be.true;
 
-        const config = await program.account.config.fetch(configPDA);
-        expect(config.fee.eq(new BN(fee))).to.be.true;
+        // hand it back for the rest of the suite
+        await program.methods
+            .proposeOwner(wallet.publicKey)
+            .accounts({ owner: newOwner.publicKey })
+            .signers([newOwner])
+            .rpc();
+        await program.methods
+            .acceptOwner()
+            .accounts({ pendingOwner: wallet.publicKey })
+            .rpc();
+        config = await program.account.config.fetch(configPDA);
+        expect(config.owner.toBase58()).to.eq(wallet.publicKey.toBase58());
     });
 
     it('Create pool', async () => {
@@ -121,7 +186,14 @@ describe('Amm', () => {
         }
 
         let [poolState, poolState_b] = web3.PublicKey.findProgramAddressSync(
-            [Buffer.from('pool'), mint0.toBuffer(), mint1.toBuffer()],
+            [
+                Buffer.from('pool'),
+                mint0.toBuffer(),
+                mint1.toBuffer(),
+                new BN(fee).toArrayLike(Buffer, 'le', 8),
+                // the constant product curve's seed
+                Buffer.from([0]),
+            ],
             program.programId
         );
 
@@ -152,13 +224,16 @@ describe('Amm', () => {
 
         try {
             await program.methods
-                .createPool()
-                .accounts({
-                    owner: wallet.publicKey,
+                .createPool(new BN(fee), { constantProduct: {} })
+                .accountsPartial({
+                    payer: wallet.publicKey,
+                    pool: poolState,
                     mint0: mint0,
                     mint1: mint1,
                     vault0: vault0.address,
                     vault1: vault1Address,
+                    tokenProgram0: token.TOKEN_PROGRAM_ID,
+                    tokenProgram1: token.TOKEN_PROGRAM_ID,
                 })
                 .rpc();
         } catch (e) {
@@ -174,14 +249,49 @@ describe('Amm', () => {
             true
         );
 
+        const [disabledPool] = web3.PublicKey.findProgramAddressSync(
+            [
+                Buffer.from('pool'),
+                mint0.toBuffer(),
+                mint1.toBuffer(),
+                new BN(100).toArrayLike(Buffer, 'le', 8),
+                Buffer.from([0]),
+            ],
+            program.programId
+        );
+        try {
+            await program.methods
+                .createPool(new BN(100), { constantProduct: {} })
+                .accountsPartial({
+                    payer: wallet.publicKey,
+                    pool: disabledPool,
+                    mint0: mint0,
+                    mint1: mint1,
+                    vault0: vault0.address,
+                    vault1: vault1.address,
+                    tokenProgram0: token.TOKEN_PROGRAM_ID,
+                    tokenProgram1: token.TOKEN_PROGRAM_ID,
+                })
+                .rpc();
+            expect.fail('should reject a disabled fee tier');
+        } catch (e) {
+            expect(e).to.be.instanceOf(AnchorError);
+            expect((e as AnchorError).error.errorCode.code).to.eq(
+                'FeeTierNotEnabled'
+            );
+        }
+
         const tx = await program.methods
-            .createPool()
-            .accounts({
-                owner: wallet.publicKey,
+            .createPool(new BN(fee), { constantProduct: {} })
+            .accountsPartial({
+                payer: wallet.publicKey,
+                pool: poolState,
                 mint0: mint0,
                 mint1: mint1,
                 vault0: vault0.address,
                 vault1: vault1.address,
+                tokenProgram0: token.TOKEN_PROGRAM_ID,
+                tokenProgram1: token.TOKEN_PROGRAM_ID,
             })
             .rpc();
 
@@ -189,6 +299,7 @@ describe('Amm', () => {
 
         expect(poolData.token0.toBase58()).to.eq(mint0.toBase58());
         expect(poolData.token1.toBase58()).to.eq(mint1.toBase58());
+        expect(poolData.fee.eqn(fee)).to.be.true;
         expect(poolData.kLast.eq(new BN(0))).to.be.true;
 
         let vaultLp = await token.getOrCreateAssociatedTokenAccount(
@@ -236,17 +347,22 @@ describe('Amm', () => {
                 src_amount0_in,
                 src_amount1_in,
                 src_amount0_in,
-                src_amount1_in
+                src_amount1_in,
+                null
             )
             .accounts({
                 owner: lp_user_signer.publicKey,
                 pool: pool.poolState,
                 vault0: pool.vault0,
                 vault1: pool.vault1,
+                mint0: pool.mint0,
+                mint1: pool.mint1,
                 vaultLp: pool.vaultLP,
                 userAta0: lpUser0.userAta0,
                 userAta1: lpUser0.userAta1,
                 userLpAta: lpUser0.lpAta,
+                tokenProgram0: token.TOKEN_PROGRAM_ID,
+                tokenProgram1: token.TOKEN_PROGRAM_ID,
             })
             .signers([lpUser0.signer])
             .rpc();
@@ -254,6 +370,8 @@ describe('Amm', () => {
         let poolData = await program.account.pool.fetch(pool.poolState);
         expect(poolData.kLast.eq(src_amount0_in.mul(src_amount1_in))).to.be
             .true;
+        expect(poolData.reserve0.eq(src_amount0_in)).to.be.true;
+        expect(poolData.reserve1.eq(src_amount1_in)).to.be.true;
         let userMint0Balance = await connection.getTokenAccountBalance(
             userAta0
         );
@@ -268,8 +386,16 @@ describe('Amm', () => {
         expect(userMint1Balance.value.amount).to.be.eq(
             lp_amount(100).sub(src_amount1_in).toString()
         );
-        liquidityAdded = sqrt(src_amount0_in.mul(src_amount1_in));
+        liquidityAdded = sqrt(src_amount0_in.mul(src_amount1_in)).sub(
+            new BN(MINIMUM_LIQUIDITY)
+        );
         expect(userLpBalance.value.amount).to.be.eq(liquidityAdded.toString());
+        let lockedLpBalance = await connection.getTokenAccountBalance(
+            pool.vaultLP
+        );
+        expect(lockedLpBalance.value.amount).to.be.eq(
+            MINIMUM_LIQUIDITY.toString()
+        );
 
         let poolMint0Balance = await connection.getTokenAccountBalance(
             pool.vault0
@@ -285,29 +411,80 @@ describe('Amm', () => {
         );
 
         let poolLp = await connection.getTokenSupply(pool.poolMint);
-        expect(poolLp.value.amount).to.be.eq(userLpBalance.value.amount);
+        expect(poolLp.value.amount).to.be.eq(
+            new BN(userLpBalance.value.amount)
+                .add(new BN(MINIMUM_LIQUIDITY))
+                .toString()
+        );
 
-        // Reserve pool input
+        // accounts must follow the pool's token order
+        const liquidityAccounts = {
+            owner: lp_user_signer.publicKey,
+            pool: pool.poolState,
+            vault0: pool.vault0,
+            vault1: pool.vault1,
+            mint0: pool.mint0,
+            mint1: pool.mint1,
+            vaultLp: pool.vaultLP,
+            userAta0: lpUser0.userAta0,
+            userAta1: lpUser0.userAta1,
+            userLpAta: lpUser0.lpAta,
+            tokenProgram0: token.TOKEN_PROGRAM_ID,
+            tokenProgram1: token.TOKEN_PROGRAM_ID,
+        };
+        const addLiquidity = (accounts) =>
+            program.methods
+                .addLiquidity(
+                    src_amount0_in,
+                    src_amount1_in,
+                    src_amount0_in,
+                    src_amount1_in,
+                    null
+                )
+                .accounts(accounts)
+                .signers([lpUser0.signer])
+                .rpc();
+        const expectError = async (accounts, code: string) => {
+            try {
+                await addLiquidity(accounts);
+                expect.fail(`should reject with ${code}`);
+            } catch (e) {
+                expect(e).to.be.instanceOf(AnchorError);
+                expect((e as AnchorError).error.errorCode.code).to.eq(code);
+            }
+        };
 
-        await program.methods
-            .addLiquidity(
-                src_amount1_in,
-                src_amount0_in,
-                src_amount1_in,
-                src_amount0_in
-            )
-            .accounts({
-                owner: lp_user_signer.publicKey,
-                pool: pool.poolState,
+        await expectError(
+            {
+                ...liquidityAccounts,
                 vault0: pool.vault1,
                 vault1: pool.vault0,
-                vaultLp: pool.vaultLP,
+                mint0: pool.mint1,
+                mint1: pool.mint0,
                 userAta0: lpUser0.userAta1,
                 userAta1: lpUser0.userAta0,
-                userLpAta: lpUser0.lpAta,
-            })
-            .signers([lpUser0.signer])
-            .rpc();
+            },
+            'InvalidUserTokenAccount'
+        );
+        await expectError(
+            { ...liquidityAccounts, vault0: pool.vault1, vault1: pool.vault0 },
+            'InvalidVault'
+        );
+        // a token account of the right mint held by the pool authority is
+        // still not the vault
+        const strayVault = await token.createAccount(
+            connection,
+            pool.payer,
+            pool.mint0,
+            pool.poolAuthority,
+            web3.Keypair.generate()
+        );
+        await expectError(
+            { ...liquidityAccounts, vault0: strayVault },
+            'InvalidVault'
+        );
+
+        await addLiquidity(liquidityAccounts);
     });
 
     it('Remove liquidity', async () => {
@@ -320,21 +497,29 @@ describe('Amm', () => {
         let userLpBalanceBefore = await connection.getTokenAccountBalance(
             lpUser0.lpAta
         );
+        let poolData = await program.account.pool.fetch(pool.poolState);
+        let lpSupply = new BN(
+            (await connection.getTokenSupply(pool.poolMint)).value.amount
+        );
+        let liquidity = liquidityAdded.div(new anchor.BN(2));
+        let amount0Out = liquidity.mul(poolData.reserve0).div(lpSupply);
+        let amount1Out = liquidity.mul(poolData.reserve1).div(lpSupply);
+
         await program.methods
-            .removeLiquidity(
-                liquidityAdded.div(new anchor.BN(2)),
-                src_amount0_in.div(new anchor.BN(2)),
-                src_amount1_in.div(new anchor.BN(2))
-            )
+            .removeLiquidity(liquidity, amount0Out, amount1Out, null)
             .accounts({
                 owner: lpUser0.signer.publicKey,
                 pool: pool.poolState,
                 vault0: pool.vault0,
                 vault1: pool.vault1,
+                mint0: pool.mint0,
+                mint1: pool.mint1,
                 vaultLp: pool.vaultLP,
                 userAta0: lpUser0.userAta0,
                 userAta1: lpUser0.userAta1,
                 userLpAta: lpUser0.lpAta,
+                tokenProgram0: token.TOKEN_PROGRAM_ID,
+                tokenProgram1: token.TOKEN_PROGRAM_ID,
             })
             .signers([lpUser0.signer])
             .rpc();
@@ -351,17 +536,17 @@ describe('Amm', () => {
         expect(
             new BN(userMint0BalanceAfter.value.amount)
                 .sub(new BN(userMint0BalanceBefore.value.amount))
-                .eq(src_amount0_in.div(new anchor.BN(2)))
+                .eq(amount0Out)
         ).to.be.true;
         expect(
             new BN(userMint1BalanceAfter.value.amount)
                 .sub(new BN(userMint1BalanceBefore.value.amount))
-                .eq(src_amount1_in.div(new anchor.BN(2)))
+                .eq(amount1Out)
         ).to.be.true;
         expect(
             new BN(userLpBalanceBefore.value.amount)
                 .sub(new BN(userLpBalanceAfter.value.amount))
-                .eq(liquidityAdded.div(new anchor.BN(2)))
+                .eq(liquidity)
         ).to.be.true;
     });
 
@@ -389,16 +574,52 @@ describe('Amm', () => {
             fee
         );
 
+        const swapAccounts = {
+            owner: lpUser0.signer.publicKey,
+            pool: pool.poolState,
+            userAtaSrc: lpUser0.userAta0,
+            userAtaDes: lpUser0.userAta1,
+            vaultSrc: pool.vault0,
+            vaultDes: pool.vault1,
+            mintSrc: pool.mint0,
+            mintDes: pool.mint1,
+            tokenProgramSrc: token.TOKEN_PROGRAM_ID,
+            tokenProgramDes: token.TOKEN_PROGRAM_ID,
+        };
+        try {
+            const expired = new BN(Math.floor(Date.now() / 1000) - 60);
+            await program.methods
+                .swapExactInput(amountIn, amountOut, expired)
+                .accounts(swapAccounts)
+                .signers([lpUser0.signer])
+                .rpc();
+            expect.fail('should reject a swap past its deadline');
+        } catch (e) {
+            expect(e).to.be.instanceOf(AnchorError);
+            expect((e as AnchorError).error.errorCode.code).to.eq('Expired');
+        }
+        try {
+            await program.methods
+                .swapExactInput(amountIn, amountOut, null)
+                .accounts({
+                    ...swapAccounts,
+                    userAtaDes: lpUser0.userAta0,
+                    vaultDes: pool.vault0,
+                    mintDes: pool.mint0,
+                })
+                .signers([lpUser0.signer])
+                .rpc();
+            expect.fail('should reject a swap from a mint to itself');
+        } catch (e) {
+            expect(e).to.be.instanceOf(AnchorError);
+            expect((e as AnchorError).error.errorCode.code).to.eq(
+                'InvalidSwapDirection'
+            );
+        }
+
         const tx = await program.methods
-            .swapExactInput(amountIn, amountOut)
-            .accounts({
-                owner: lpUser0.signer.publicKey,
-                pool: pool.poolState,
-                userAtaSrc: lpUser0.userAta0,
-                userAtaDes: lpUser0.userAta1,
-                vaultSrc: pool.vault0,
-                vaultDes: pool.vault1,
-            })
+            .swapExactInput(amountIn, amountOut, null)
+            .accounts(swapAccounts)
             .signers([lpUser0.signer])
             .rpc();
 
@@ -446,7 +667,7 @@ describe('Amm', () => {
         );
 
         const tx = await program.methods
-            .swapExactOutput(amountOut, amountIn)
+            .swapExactOutput(amountOut, amountIn, null)
             .accounts({
                 owner: lpUser0.signer.publicKey,
                 pool: pool.poolState,
@@ -454,6 +675,10 @@ describe('Amm', () => {
                 userAtaDes: lpUser0.userAta1,
                 vaultSrc: pool.vault0,
                 vaultDes: pool.vault1,
+                mintSrc: pool.mint0,
+                mintDes: pool.mint1,
+                tokenProgramSrc: token.TOKEN_PROGRAM_ID,
+                tokenProgramDes: token.TOKEN_PROGRAM_ID,
             })
             .signers([lpUser0.signer])
             .rpc();
@@ -476,6 +701,1133 @@ describe('Amm', () => {
                 .sub(new BN(userMint1BalanceBefore.value.amount))
                 .eq(amountOut)
         ).to.be.true;
+
+        const eventParser = new anchor.EventParser(
+            program.programId,
+            program.coder
+        );
+        const events = [
+            ...eventParser.parseLogs(await readLogs(connection, tx)),
+        ];
+        const swapEvent = events.find((event) => event.name === 'swap');
+        const poolData = await program.account.pool.fetch(pool.poolState);
+        expect(swapEvent.data.pool.toBase58()).to.eq(pool.poolState.toBase58());
+        expect(swapEvent.data.amountIn.eq(amountIn)).to.be.true;
+        expect(swapEvent.data.amountOut.eq(amountOut)).to.be.true;
+        const feeAmount = amountIn.muln(fee).divn(BASIS_POINTS);
+        expect(swapEvent.data.feeAmount.eq(feeAmount)).to.be.true;
+        expect(swapEvent.data.reserve0.eq(poolData.reserve0)).to.be.true;
+        expect(swapEvent.data.reserve1.eq(poolData.reserve1)).to.be.true;
+    });
+
+    it('Swap route', async () => {
+        // second pool pairing mint1 with a fresh mint2
+        let mint2 = await token.createMint(
+            connection,
+            pool.auth,
+            pool.auth.publicKey,
+            pool.auth.publicKey,
+            n_decimals
+        );
+        let [mintA, mintB] =
+            pool.mint1.toBase58() < mint2.toBase58()
+                ? [pool.mint1, mint2]
+                : [mint2, pool.mint1];
+        let [poolB] = web3.PublicKey.findProgramAddressSync(
+            [
+                Buffer.from('pool'),
+                mintA.toBuffer(),
+                mintB.toBuffer(),
+                new BN(fee).toArrayLike(Buffer, 'le', 8),
+                Buffer.from([0]),
+            ],
+            program.programId
+        );
+        let [poolBAuthority] = web3.PublicKey.findProgramAddressSync(
+            [Buffer.from('authority'), poolB.toBuffer()],
+            program.programId
+        );
+        let [poolBMint] = web3.PublicKey.findProgramAddressSync(
+            [Buffer.from('lp_mint'), poolB.toBuffer()],
+            program.programId
+        );
+        let [poolBObservations] = web3.PublicKey.findProgramAddressSync(
+            [Buffer.from('observations'), poolB.toBuffer()],
+            program.programId
+        );
+        let vaultB1 = await token.getOrCreateAssociatedTokenAccount(
+            connection,
+            pool.auth,
+            pool.mint1,
+            poolBAuthority,
+            true
+        );
+        let vaultB2 = await token.getOrCreateAssociatedTokenAccount(
+            connection,
+            pool.auth,
+            mint2,
+            poolBAuthority,
+            true
+        );
+        await program.methods
+            .createPool(new BN(fee), { constantProduct: {} })
+            .accountsPartial({
+                payer: wallet.publicKey,
+                pool: poolB,
+                mint0: mintA,
+                mint1: mintB,
+                vault0: mintA.equals(mint2) ? vaultB2.address : vaultB1.address,
+                vault1: mintA.equals(mint2) ? vaultB1.address : vaultB2.address,
+                tokenProgram0: token.TOKEN_PROGRAM_ID,
+                tokenProgram1: token.TOKEN_PROGRAM_ID,
+            })
+            .rpc();
+
+        let userAta2 = await token.createAssociatedTokenAccount(
+            connection,
+            pool.payer,
+            mint2,
+            lpUser0.signer.publicKey
+        );
+        await token.mintTo(
+            connection,
+            pool.payer,
+            mint2,
+            userAta2,
+            pool.auth,
+            100 * 10 ** n_decimals
+        );
+        // accounts follow the pool's token order
+        let poolBFlipped = mintA.equals(mint2);
+        await program.methods
+            .addLiquidity(
+                lp_amount(20),
+                lp_amount(20),
+                new BN(0),
+                new BN(0),
+                null
+            )
+            .accounts({
+                owner: lpUser0.signer.publicKey,
+                pool: poolB,
+                vault0: poolBFlipped ? vaultB2.address : vaultB1.address,
+                vault1: poolBFlipped ? vaultB1.address : vaultB2.address,
+                mint0: mintA,
+                mint1: mintB,
+                vaultLp: token.getAssociatedTokenAddressSync(
+                    poolBMint,
+                    poolBAuthority,
+                    true
+                ),
+                userAta0: poolBFlipped ? userAta2 : lpUser0.userAta1,
+                userAta1: poolBFlipped ? lpUser0.userAta1 : userAta2,
+                userLpAta: token.getAssociatedTokenAddressSync(
+                    poolBMint,
+                    lpUser0.signer.publicKey
+                ),
+                tokenProgram0: token.TOKEN_PROGRAM_ID,
+                tokenProgram1: token.TOKEN_PROGRAM_ID,
+            })
+            .signers([lpUser0.signer])
+            .rpc();
+
+        // mint0 -> mint1 through the first pool, mint1 -> mint2 through the second
+        let poolA = await program.account.pool.fetch(pool.poolState);
+        let poolBData = await program.account.pool.fetch(poolB);
+        let [reserveA0, reserveA1] = [poolA.reserve0, poolA.reserve1];
+        let [reserveB1, reserveB2] = mintA.equals(pool.mint1)
+            ? [poolBData.reserve0, poolBData.reserve1]
+            : [poolBData.reserve1, poolBData.reserve0];
+
+        let amountIn = lp_amount(1);
+        let amountMid = getAmountOut(amountIn, reserveA0, reserveA1, fee);
+        let amountOut = getAmountOut(amountMid, reserveB1, reserveB2, fee);
+
+        let [poolAObservations] = web3.PublicKey.findProgramAddressSync(
+            [Buffer.from('observations'), pool.poolState.toBuffer()],
+            program.programId
+        );
+        const hop = (
+            poolState: web3.PublicKey,
+            authority: web3.PublicKey,
+            vaultSrc: web3.PublicKey,
+            vaultDes: web3.PublicKey,
+            observations: web3.PublicKey,
+            mintDes: web3.PublicKey
+        ) => [
+            { pubkey: poolState, isSigner: false, isWritable: true },
+            { pubkey: authority, isSigner: false, isWritable: false },
+            { pubkey: vaultSrc, isSigner: false, isWritable: true },
+            { pubkey: vaultDes, isSigner: false, isWritable: true },
+            { pubkey: observations, isSigner: false, isWritable: true },
+            { pubkey: mintDes, isSigner: false, isWritable: false },
+            {
+                pubkey: token.TOKEN_PROGRAM_ID,
+                isSigner: false,
+                isWritable: false,
+            },
+        ];
+        const route = [
+            ...hop(
+                pool.poolState,
+                pool.poolAuthority,
+                pool.vault0,
+                pool.vault1,
+                poolAObservations,
+                pool.mint1
+            ),
+            ...hop(
+                poolB,
+                poolBAuthority,
+                vaultB1.address,
+                vaultB2.address,
+                poolBObservations,
+                mint2
+            ),
+        ];
+
+        try {
+            await program.methods
+                .swapExactInputRoute(amountIn, amountOut.addn(1), null)
+                .accounts({
+                    owner: lpUser0.signer.publicKey,
+                    userAtaSrc: lpUser0.userAta0,
+                    userAtaDes: userAta2,
+                    mintSrc: pool.mint0,
+                    mintDes: mint2,
+                    tokenProgramSrc: token.TOKEN_PROGRAM_ID,
+                    tokenProgramDes: token.TOKEN_PROGRAM_ID,
+                })
+                .remainingAccounts(route)
+                .signers([lpUser0.signer])
+                .rpc();
+            expect.fail('should enforce the overall minimum output');
+        } catch (e) {
+            expect(e).to.be.instanceOf(AnchorError);
+            expect((e as AnchorError).error.errorCode.code).to.eq(
+                'InsufficientOutputAmount'
+            );
+        }
+
+        let userMint2BalanceBefore = await connection.getTokenAccountBalance(
+            userAta2
+        );
+        await program.methods
+            .swapExactInputRoute(amountIn, amountOut, null)
+            .accounts({
+                owner: lpUser0.signer.publicKey,
+                userAtaSrc: lpUser0.userAta0,
+                userAtaDes: userAta2,
+                mintSrc: pool.mint0,
+                mintDes: mint2,
+                tokenProgramSrc: token.TOKEN_PROGRAM_ID,
+                tokenProgramDes: token.TOKEN_PROGRAM_ID,
+            })
+            .remainingAccounts(route)
+            .signers([lpUser0.signer])
+            .rpc();
+        let userMint2BalanceAfter = await connection.getTokenAccountBalance(
+            userAta2
+        );
+        expect(
+            new BN(userMint2BalanceAfter.value.amount)
+                .sub(new BN(userMint2BalanceBefore.value.amount))
+                .eq(amountOut)
+        ).to.be.true;
+
+        poolA = await program.account.pool.fetch(pool.poolState);
+        expect(poolA.reserve0.eq(reserveA0.add(amountIn))).to.be.true;
+        expect(poolA.reserve1.eq(reserveA1.sub(amountMid))).to.be.true;
+    });
+
+    it('Flash swap', async () => {
+        let vault0BalanceBefore = await connection.getTokenAccountBalance(
+            pool.vault0
+        );
+        let vault1BalanceBefore = await connection.getTokenAccountBalance(
+            pool.vault1
+        );
+        let userMint0BalanceBefore = await connection.getTokenAccountBalance(
+            lpUser0.userAta0
+        );
+
+        let amountOut = new anchor.BN(1).mul(new anchor.BN(10 ** n_decimals));
+        // one unit of headroom for the fee being rounded up on-chain
+        let repayment = getAmountIn(
+            amountOut,
+            new anchor.BN(vault1BalanceBefore.value.amount),
+            new anchor.BN(vault0BalanceBefore.value.amount),
+            fee
+        ).addn(1);
+
+        // the token program itself serves as callback: it repays the pool in token1
+        const repay = token.createTransferInstruction(
+            lpUser0.userAta1,
+            pool.vault1,
+            lpUser0.signer.publicKey,
+            BigInt(repayment.toString())
+        );
+
+        // underpaying breaks the invariant
+        const underpay = token.createTransferInstruction(
+            lpUser0.userAta1,
+            pool.vault1,
+            lpUser0.signer.publicKey,
+            BigInt(repayment.subn(3).toString())
+        );
+        try {
+            await program.methods
+                .flashSwap(amountOut, new BN(0), underpay.data)
+                .accounts({
+                    owner: lpUser0.signer.publicKey,
+                    receiver0: lpUser0.userAta0,
+                    receiver1: lpUser0.userAta1,
+                    pool: pool.poolState,
+                    vault0: pool.vault0,
+                    vault1: pool.vault1,
+                    mint0: pool.mint0,
+                    mint1: pool.mint1,
+                    callbackProgram: token.TOKEN_PROGRAM_ID,
+                    tokenProgram0: token.TOKEN_PROGRAM_ID,
+                    tokenProgram1: token.TOKEN_PROGRAM_ID,
+                })
+                .remainingAccounts(underpay.keys)
+                .signers([lpUser0.signer])
+                .rpc();
+            expect.fail('should reject an underpaid flash swap');
+        } catch (e) {
+            expect(e).to.be.instanceOf(AnchorError);
+            expect((e as AnchorError).error.errorCode.code).to.eq(
+                'InvariantViolated'
+            );
+        }
+
+        await program.methods
+            .flashSwap(amountOut, new BN(0), repay.data)
+            .accounts({
+                owner: lpUser0.signer.publicKey,
+                receiver0: lpUser0.userAta0,
+                receiver1: lpUser0.userAta1,
+                pool: pool.poolState,
+                vault0: pool.vault0,
+                vault1: pool.vault1,
+                mint0: pool.mint0,
+                mint1: pool.mint1,
+                callbackProgram: token.TOKEN_PROGRAM_ID,
+                tokenProgram0: token.TOKEN_PROGRAM_ID,
+                tokenProgram1: token.TOKEN_PROGRAM_ID,
+            })
+            .remainingAccounts(repay.keys)
+            .signers([lpUser0.signer])
+            .rpc();
+
+        let userMint0BalanceAfter = await connection.getTokenAccountBalance(
+            lpUser0.userAta0
+        );
+        let vault1BalanceAfter = await connection.getTokenAccountBalance(
+            pool.vault1
+        );
+        expect(
+            new BN(userMint0BalanceAfter.value.amount)
+                .sub(new BN(userMint0BalanceBefore.value.amount))
+                .eq(amountOut)
+        ).to.be.true;
+        expect(
+            new BN(vault1BalanceAfter.value.amount)
+                .sub(new BN(vault1BalanceBefore.value.amount))
+                .eq(repayment)
+        ).to.be.true;
+    });
+
+    it('Skim and sync', async () => {
+        const donation = lp_amount(1);
+        await token.transfer(
+            connection,
+            pool.payer,
+            lpUser0.userAta0,
+            pool.vault0,
+            lpUser0.signer,
+            BigInt(donation.toString())
+        );
+
+        // donations do not move the reserves
+        let poolData = await program.account.pool.fetch(pool.poolState);
+        let vault0Balance = await connection.getTokenAccountBalance(
+            pool.vault0
+        );
+        expect(
+            new BN(vault0Balance.value.amount)
+                .sub(poolData.reserve0)
+                .eq(donation)
+        ).to.be.true;
+
+        let userMint0BalanceBefore = await connection.getTokenAccountBalance(
+            lpUser0.userAta0
+        );
+        await program.methods
+            .skim()
+            .accounts({
+                pool: pool.poolState,
+                vault0: pool.vault0,
+                vault1: pool.vault1,
+                recipient0: lpUser0.userAta0,
+                recipient1: lpUser0.userAta1,
+                mint0: pool.mint0,
+                mint1: pool.mint1,
+                tokenProgram0: token.TOKEN_PROGRAM_ID,
+                tokenProgram1: token.TOKEN_PROGRAM_ID,
+            })
+            .rpc();
+        let userMint0BalanceAfter = await connection.getTokenAccountBalance(
+            lpUser0.userAta0
+        );
+        expect(
+            new BN(userMint0BalanceAfter.value.amount)
+                .sub(new BN(userMint0BalanceBefore.value.amount))
+                .eq(donation)
+        ).to.be.true;
+
+        // sync adopts a donation as reserves
+        await token.transfer(
+            connection,
+            pool.payer,
+            lpUser0.userAta0,
+            pool.vault0,
+            lpUser0.signer,
+            BigInt(donation.toString())
+        );
+        await program.methods
+            .sync()
+            .accounts({
+                pool: pool.poolState,
+                vault0: pool.vault0,
+                vault1: pool.vault1,
+            })
+            .rpc();
+
+        poolData = await program.account.pool.fetch(pool.poolState);
+        vault0Balance = await connection.getTokenAccountBalance(pool.vault0);
+        expect(poolData.reserve0.toString()).to.eq(vault0Balance.value.amount);
+    });
+
+    it('Protocol fee', async () => {
+        try {
+            await program.methods
+                .setProtocolFeeShare(new BN(MAX_PROTOCOL_FEE_SHARE + 1))
+                .rpc();
+            expect.fail('should reject a share above 50%');
+        } catch (e) {
+            expect(e).to.be.instanceOf(AnchorError);
+            expect((e as AnchorError).error.errorCode.code).to.eq(
+                'InvalidProtocolFeeShare'
+            );
+        }
+        const protocolFeeShare = 2000;
+        await program.methods
+            .setProtocolFeeShare(new BN(protocolFeeShare))
+            .rpc();
+
+        // swaps since the last liquidity event grew k, the protocol's share is
+        // minted on the next one
+        let poolData = await program.account.pool.fetch(pool.poolState);
+        let lpSupply = new BN(
+            (await connection.getTokenSupply(pool.poolMint)).value.amount
+        );
+        const rootK = sqrt(poolData.reserve0.mul(poolData.reserve1));
+        const rootKLast = sqrt(poolData.kLast);
+        const protocolFee = lpSupply
+            .mul(rootK.sub(rootKLast))
+            .muln(protocolFeeShare)
+            .div(
+                rootK
+                    .muln(BASIS_POINTS - protocolFeeShare)
+                    .add(rootKLast.muln(protocolFeeShare))
+            );
+        expect(protocolFee.gtn(0)).to.be.true;
+
+        const removeLiquidity = () =>
+            program.methods
+                .removeLiquidity(
+                    new BN(MINIMUM_LIQUIDITY),
+                    new BN(0),
+                    new BN(0),
+                    null
+                )
+                .accounts({
+                    owner: lpUser0.signer.publicKey,
+                    pool: pool.poolState,
+                    vault0: pool.vault0,
+                    vault1: pool.vault1,
+                    mint0: pool.mint0,
+                    mint1: pool.mint1,
+                    vaultLp: pool.vaultLP,
+                    userAta0: lpUser0.userAta0,
+                    userAta1: lpUser0.userAta1,
+                    userLpAta: lpUser0.lpAta,
+                    tokenProgram0: token.TOKEN_PROGRAM_ID,
+                    tokenProgram1: token.TOKEN_PROGRAM_ID,
+                })
+                .signers([lpUser0.signer])
+                .rpc();
+        await removeLiquidity();
+
+        poolData = await program.account.pool.fetch(pool.poolState);
+        expect(poolData.protocolFees.eq(protocolFee)).to.be.true;
+        let vaultLpBalance = await connection.getTokenAccountBalance(
+            pool.vaultLP
+        );
+        expect(vaultLpBalance.value.amount).to.eq(
+            protocolFee.addn(MINIMUM_LIQUIDITY).toString()
+        );
+
+        // only fee_to can claim
+        const feeToLpAta = await token.createAssociatedTokenAccount(
+            connection,
+            pool.payer,
+            pool.poolMint,
+            feeTo.publicKey
+        );
+        await program.methods
+            .claimProtocolFees()
+            .accounts({
+                feeTo: feeTo.publicKey,
+                pool: pool.poolState,
+                vaultLp: pool.vaultLP,
+                recipient: feeToLpAta,
+            })
+            .signers([feeTo])
+            .rpc();
+
+        let feeToLpBalance = await connection.getTokenAccountBalance(
+            feeToLpAta
+        );
+        expect(feeToLpBalance.value.amount).to.eq(protocolFee.toString());
+        poolData = await program.account.pool.fetch(pool.poolState);
+        expect(poolData.protocolFees.eqn(0)).to.be.true;
+        vaultLpBalance = await connection.getTokenAccountBalance(pool.vaultLP);
+        expect(vaultLpBalance.value.amount).to.eq(MINIMUM_LIQUIDITY.toString());
+
+        // unsetting fee_to switches the protocol fee off and clears k_last
+        await program.methods.setFeeTo(web3.PublicKey.default).rpc();
+        await removeLiquidity();
+        poolData = await program.account.pool.fetch(pool.poolState);
+        expect(poolData.kLast.eqn(0)).to.be.true;
+
+        await program.methods.setFeeTo(feeTo.publicKey).rpc();
+    });
+
+    it('Pause', async () => {
+        const swapExactInput = () =>
+            program.methods
+                .swapExactInput(lp_amount(1), new BN(0), null)
+                .accounts({
+                    owner: lpUser0.signer.publicKey,
+                    pool: pool.poolState,
+                    userAtaSrc: lpUser0.userAta0,
+                    userAtaDes: lpUser0.userAta1,
+                    vaultSrc: pool.vault0,
+                    vaultDes: pool.vault1,
+                    mintSrc: pool.mint0,
+                    mintDes: pool.mint1,
+                    tokenProgramSrc: token.TOKEN_PROGRAM_ID,
+                    tokenProgramDes: token.TOKEN_PROGRAM_ID,
+                })
+                .signers([lpUser0.signer])
+                .rpc();
+        const liquidityAccounts = {
+            owner: lpUser0.signer.publicKey,
+            pool: pool.poolState,
+            vault0: pool.vault0,
+            vault1: pool.vault1,
+            mint0: pool.mint0,
+            mint1: pool.mint1,
+            vaultLp: pool.vaultLP,
+            userAta0: lpUser0.userAta0,
+            userAta1: lpUser0.userAta1,
+            userLpAta: lpUser0.lpAta,
+            tokenProgram0: token.TOKEN_PROGRAM_ID,
+            tokenProgram1: token.TOKEN_PROGRAM_ID,
+        };
+        const expectPaused = (e) => {
+            expect(e).to.be.instanceOf(AnchorError);
+            expect((e as AnchorError).error.errorCode.code).to.eq('Paused');
+        };
+
+        await program.methods.setPaused(true).rpc();
+        try {
+            await swapExactInput();
+            expect.fail('should reject swaps while paused');
+        } catch (e) {
+            expectPaused(e);
+        }
+        // LPs can always exit to both tokens
+        await program.methods
+            .removeLiquidity(
+                new BN(MINIMUM_LIQUIDITY),
+                new BN(0),
+                new BN(0),
+                null
+            )
+            .accounts(liquidityAccounts)
+            .signers([lpUser0.signer])
+            .rpc();
+        await program.methods.setPaused(false).rpc();
+
+        await program.methods
+            .setPoolPaused(true)
+            .accounts({ pool: pool.poolState })
+            .rpc();
+        try {
+            await program.methods
+                .addLiquidity(
+                    lp_amount(1),
+                    lp_amount(1),
+                    new BN(0),
+                    new BN(0),
+                    null
+                )
+                .accounts(liquidityAccounts)
+                .signers([lpUser0.signer])
+                .rpc();
+            expect.fail('should reject deposits while the pool is paused');
+        } catch (e) {
+            expectPaused(e);
+        }
+        await program.methods
+            .setPoolPaused(false)
+            .accounts({ pool: pool.poolState })
+            .rpc();
+
+        await swapExactInput();
+    });
+
+    it('Quotes', async () => {
+        const quoteSwapAccounts = {
+            pool: pool.poolState,
+            mintSrc: pool.mint0,
+            mintDes: pool.mint1,
+        };
+        const quoteLiquidityAccounts = {
+            pool: pool.poolState,
+            mint0: pool.mint0,
+            mint1: pool.mint1,
+        };
+        const liquidityAccounts = {
+            owner: lpUser0.signer.publicKey,
+            pool: pool.poolState,
+            vault0: pool.vault0,
+            vault1: pool.vault1,
+            mint0: pool.mint0,
+            mint1: pool.mint1,
+            vaultLp: pool.vaultLP,
+            userAta0: lpUser0.userAta0,
+            userAta1: lpUser0.userAta1,
+            userLpAta: lpUser0.lpAta,
+            tokenProgram0: token.TOKEN_PROGRAM_ID,
+            tokenProgram1: token.TOKEN_PROGRAM_ID,
+        };
+        const balance = async (account: web3.PublicKey) => {
+            const { value } = await connection.getTokenAccountBalance(account);
+            return new BN(value.amount);
+        };
+
+        // the quoted output is exactly what the swap pays out
+        const amountIn = lp_amount(1);
+        const quotedOut = await program.methods
+            .quoteExactInput(amountIn)
+            .accounts(quoteSwapAccounts)
+            .view();
+        const balance1Before = await balance(lpUser0.userAta1);
+        await program.methods
+            .swapExactInput(amountIn, quotedOut, null)
+            .accounts({
+                owner: lpUser0.signer.publicKey,
+                pool: pool.poolState,
+                userAtaSrc: lpUser0.userAta0,
+                userAtaDes: lpUser0.userAta1,
+                vaultSrc: pool.vault0,
+                vaultDes: pool.vault1,
+                mintSrc: pool.mint0,
+                mintDes: pool.mint1,
+                tokenProgramSrc: token.TOKEN_PROGRAM_ID,
+                tokenProgramDes: token.TOKEN_PROGRAM_ID,
+            })
+            .signers([lpUser0.signer])
+            .rpc();
+        const balance1After = await balance(lpUser0.userAta1);
+        expect(balance1After.sub(balance1Before).eq(quotedOut)).to.be.true;
+
+        const quotedIn = await program.methods
+            .quoteExactOutput(quotedOut)
+            .accounts({
+                ...quoteSwapAccounts,
+                mintSrc: pool.mint1,
+                mintDes: pool.mint0,
+            })
+            .view();
+        expect(quotedIn.gtn(0)).to.be.true;
+
+        try {
+            await program.methods
+                .quoteExactInput(amountIn)
+                .accounts({ ...quoteSwapAccounts, mintDes: pool.mint0 })
+                .view();
+            expect.fail('should reject a swap from a mint to itself');
+        } catch (e) {
+            expect(e.toString()).to.include('InvalidSwapDirection');
+        }
+
+        // the quoted deposit is priced after the pending protocol fee is minted
+        const deposit = await program.methods
+            .quoteAddLiquidity(lp_amount(1), lp_amount(1))
+            .accounts(quoteLiquidityAccounts)
+            .view();
+        const lpBefore = await balance(lpUser0.lpAta);
+        await program.methods
+            .addLiquidity(
+                lp_amount(1),
+                lp_amount(1),
+                deposit.amount0,
+                deposit.amount1,
+                null
+            )
+            .accounts(liquidityAccounts)
+            .signers([lpUser0.signer])
+            .rpc();
+        const lpAfter = await balance(lpUser0.lpAta);
+        expect(lpAfter.sub(lpBefore).eq(deposit.liquidity)).to.be.true;
+
+        const withdrawal = await program.methods
+            .quoteRemoveLiquidity(deposit.liquidity)
+            .accounts(quoteLiquidityAccounts)
+            .view();
+        const balance0Before = await balance(lpUser0.userAta0);
+        await program.methods
+            .removeLiquidity(
+                deposit.liquidity,
+                withdrawal.amount0,
+                withdrawal.amount1,
+                null
+            )
+            .accounts(liquidityAccounts)
+            .signers([lpUser0.signer])
+            .rpc();
+        const balance0After = await balance(lpUser0.userAta0);
+        expect(balance0After.sub(balance0Before).eq(withdrawal.amount0)).to.be
+            .true;
+    });
+
+    it('TWAP', async () => {
+        const before = await program.account.pool.fetch(pool.poolState);
+        expect(before.blockTimestampLast.toNumber()).to.be.gt(0);
+
+        // let some time pass so the window is non-empty
+        await new Promise((resolve) => setTimeout(resolve, 2000));
+
+        let vault0Balance = await connection.getTokenAccountBalance(
+            pool.vault0
+        );
+        let vault1Balance = await connection.getTokenAccountBalance(
+            pool.vault1
+        );
+
+        const twap = await program.methods
+            .getTwap({
+                timestamp: before.blockTimestampLast,
+                price0Cumulative: before.price0CumulativeLast,
+                price1Cumulative: before.price1CumulativeLast,
+            })
+            .accounts({ pool: pool.poolState })
+            .view();
+
+        // reserves have not moved since the last swap, so the average is the spot price
+        let spotPrice0 = new BN(vault1Balance.value.amount)
+            .shln(64)
+            .div(new BN(vault0Balance.value.amount));
+        expect(twap.price0Average.sub(spotPrice0).abs().lten(1)).to.be.true;
+        expect(twap.observation.timestamp.gt(before.blockTimestampLast)).to.be
+            .true;
+    });
+
+    it('Observations', async () => {
+        const [observationsPDA] = web3.PublicKey.findProgramAddressSync(
+            [Buffer.from('observations'), pool.poolState.toBuffer()],
+            program.programId
+        );
+
+        await program.methods
+            .increaseObservationCardinality(4)
+            .accounts({ pool: pool.poolState })
+            .rpc();
+
+        let observations = await program.account.observations.fetch(
+            observationsPDA
+        );
+        expect(observations.cardinality).to.eq(1);
+        expect(observations.cardinalityNext).to.eq(4);
+        // 64 byte slots follow the discriminator and the 40 byte header
+        const observationsAccount = await connection.getAccountInfo(
+            observationsPDA
+        );
+        expect((observationsAccount.data.length - 48) / 64).to.eq(4);
+
+        const [now, earlier] = await program.methods
+            .consult([0, 1])
+            .accounts({ pool: pool.poolState })
+            .view();
+        expect(now.timestamp.sub(earlier.timestamp).eqn(1)).to.be.true;
+        expect(now.price0Cumulative.gt(earlier.price0Cumulative)).to.be.true;
+
+        try {
+            await program.methods
+                .consult([1_000_000])
+                .accounts({ pool: pool.poolState })
+                .view();
+            expect.fail('should reject observations older than the buffer');
+        } catch (e) {
+            expect(e.toString()).to.include('ObservationTooOld');
+        }
+    });
+
+    it('Token-2022 transfer fee', async () => {
+        // 1% transfer fee on a Token-2022 mint, paired with the classic mint0
+        const transferFeeBasisPoints = 100;
+        const feeMintKeypair = web3.Keypair.generate();
+        const feeMint = feeMintKeypair.publicKey;
+        const mintLen = token.getMintLen([
+            token.ExtensionType.TransferFeeConfig,
+        ]);
+        const lamports = await connection.getMinimumBalanceForRentExemption(
+            mintLen
+        );
+        await web3.sendAndConfirmTransaction(
+            connection,
+            new web3.Transaction().add(
+                web3.SystemProgram.createAccount({
+                    fromPubkey: pool.payer.publicKey,
+                    newAccountPubkey: feeMint,
+                    space: mintLen,
+                    lamports,
+                    programId: token.TOKEN_2022_PROGRAM_ID,
+                }),
+                token.createInitializeTransferFeeConfigInstruction(
+                    feeMint,
+                    pool.auth.publicKey,
+                    pool.auth.publicKey,
+                    transferFeeBasisPoints,
+                    BigInt(lp_amount(1000).toString()),
+                    token.TOKEN_2022_PROGRAM_ID
+                ),
+                token.createInitializeMintInstruction(
+                    feeMint,
+                    n_decimals,
+                    pool.auth.publicKey,
+                    null,
+                    token.TOKEN_2022_PROGRAM_ID
+                )
+            ),
+            [pool.payer, feeMintKeypair]
+        );
+        const transferFee = (amount: anchor.BN) =>
+            amount
+                .muln(transferFeeBasisPoints)
+                .addn(BASIS_POINTS - 1)
+                .divn(BASIS_POINTS);
+
+        const feeMintFirst = feeMint.toBase58() < pool.mint0.toBase58();
+        const [mint0, mint1] = feeMintFirst
+            ? [feeMint, pool.mint0]
+            : [pool.mint0, feeMint];
+        const [tokenProgram0, tokenProgram1] = feeMintFirst
+            ? [token.TOKEN_2022_PROGRAM_ID, token.TOKEN_PROGRAM_ID]
+            : [token.TOKEN_PROGRAM_ID, token.TOKEN_2022_PROGRAM_ID];
+        const [poolState] = web3.PublicKey.findProgramAddressSync(
+            [
+                Buffer.from('pool'),
+                mint0.toBuffer(),
+                mint1.toBuffer(),
+                new BN(fee).toArrayLike(Buffer, 'le', 8),
+                Buffer.from([0]),
+            ],
+            program.programId
+        );
+        const [poolAuthority] = web3.PublicKey.findProgramAddressSync(
+            [Buffer.from('authority'), poolState.toBuffer()],
+            program.programId
+        );
+        const [poolMint] = web3.PublicKey.findProgramAddressSync(
+            [Buffer.from('lp_mint'), poolState.toBuffer()],
+            program.programId
+        );
+        const vaultFee = await token.getOrCreateAssociatedTokenAccount(
+            connection,
+            pool.payer,
+            feeMint,
+            poolAuthority,
+            true,
+            undefined,
+            undefined,
+            token.TOKEN_2022_PROGRAM_ID
+        );
+        const vaultClassic = await token.getOrCreateAssociatedTokenAccount(
+            connection,
+            pool.payer,
+            pool.mint0,
+            poolAuthority,
+            true
+        );
+        const [vault0, vault1] = feeMintFirst
+            ? [vaultFee.address, vaultClassic.address]
+            : [vaultClassic.address, vaultFee.address];
+
+        await program.methods
+            .createPool(new BN(fee), { constantProduct: {} })
+            .accountsPartial({
+                payer: wallet.publicKey,
+                pool: poolState,
+                mint0,
+                mint1,
+                vault0,
+                vault1,
+                tokenProgram0,
+                tokenProgram1,
+            })
+            .rpc();
+
+        const userAtaFee = await token.createAssociatedTokenAccount(
+            connection,
+            pool.payer,
+            feeMint,
+            lpUser0.signer.publicKey,
+            undefined,
+            token.TOKEN_2022_PROGRAM_ID
+        );
+        await token.mintTo(
+            connection,
+            pool.payer,
+            feeMint,
+            userAtaFee,
+            pool.auth,
+            100 * 10 ** n_decimals,
+            [],
+            undefined,
+            token.TOKEN_2022_PROGRAM_ID
+        );
+        const [userAta0, userAta1] = feeMintFirst
+            ? [userAtaFee, lpUser0.userAta0]
+            : [lpUser0.userAta0, userAtaFee];
+
+        const deposit = lp_amount(10);
+        await program.methods
+            .addLiquidity(deposit, deposit, new BN(0), new BN(0), null)
+            .accounts({
+                owner: lpUser0.signer.publicKey,
+                pool: poolState,
+                vault0,
+                vault1,
+                mint0,
+                mint1,
+                vaultLp: token.getAssociatedTokenAddressSync(
+                    poolMint,
+                    poolAuthority,
+                    true
+                ),
+                userAta0,
+                userAta1,
+                userLpAta: token.getAssociatedTokenAddressSync(
+                    poolMint,
+                    lpUser0.signer.publicKey
+                ),
+                tokenProgram0,
+                tokenProgram1,
+            })
+            .signers([lpUser0.signer])
+            .rpc();
+
+        // the reserves only count what arrived in the vaults
+        const reserves = async () => {
+            const poolData = await program.account.pool.fetch(poolState);
+            return feeMintFirst
+                ? [poolData.reserve0, poolData.reserve1]
+                : [poolData.reserve1, poolData.reserve0];
+        };
+        let [reserveFee, reserveClassic] = await reserves();
+        expect(reserveFee.eq(deposit.sub(transferFee(deposit)))).to.be.true;
+        expect(reserveClassic.eq(deposit.sub(transferFee(deposit)))).to.be
+            .true;
+
+        const amountIn = lp_amount(1);
+        const amountOut = getAmountOut(
+            amountIn.sub(transferFee(amountIn)),
+            reserveFee,
+            reserveClassic,
+            fee
+        );
+        const balanceBefore = await connection.getTokenAccountBalance(
+            lpUser0.userAta0
+        );
+        await program.methods
+            .swapExactInput(amountIn, amountOut, null)
+            .accounts({
+                owner: lpUser0.signer.publicKey,
+                pool: poolState,
+                userAtaSrc: userAtaFee,
+                userAtaDes: lpUser0.userAta0,
+                vaultSrc: vaultFee.address,
+                vaultDes: vaultClassic.address,
+                mintSrc: feeMint,
+                mintDes: pool.mint0,
+                tokenProgramSrc: token.TOKEN_2022_PROGRAM_ID,
+                tokenProgramDes: token.TOKEN_PROGRAM_ID,
+            })
+            .signers([lpUser0.signer])
+            .rpc();
+
+        const balanceAfter = await connection.getTokenAccountBalance(
+            lpUser0.userAta0
+        );
+        expect(
+            new BN(balanceAfter.value.amount)
+                .sub(new BN(balanceBefore.value.amount))
+                .eq(amountOut)
+        ).to.be.true;
+        const [reserveFeeAfter] = await reserves();
+        const vaultFeeBalance = await connection.getTokenAccountBalance(
+            vaultFee.address
+        );
+        expect(reserveFeeAfter.toString()).to.eq(vaultFeeBalance.value.amount);
+    });
+
+    it('Permissionless pool creation', async () => {
+        const creator = web3.Keypair.generate();
+        await connection.confirmTransaction(
+            await connection.requestAirdrop(
+                creator.publicKey,
+                10 * web3.LAMPORTS_PER_SOL
+            ),
+            'confirmed'
+        );
+
+        let mint0 = await token.createMint(
+            connection,
+            pool.payer,
+            pool.auth.publicKey,
+            pool.auth.publicKey,
+            n_decimals
+        );
+        let mint1 = await token.createMint(
+            connection,
+            pool.payer,
+            pool.auth.publicKey,
+            pool.auth.publicKey,
+            n_decimals
+        );
+        if (mint0.toBase58() > mint1.toBase58()) {
+            [mint0, mint1] = [mint1, mint0];
+        }
+        const [poolState] = web3.PublicKey.findProgramAddressSync(
+            [
+                Buffer.from('pool'),
+                mint0.toBuffer(),
+                mint1.toBuffer(),
+                new BN(fee).toArrayLike(Buffer, 'le', 8),
+                Buffer.from([0]),
+            ],
+            program.programId
+        );
+        const [poolAuthority] = web3.PublicKey.findProgramAddressSync(
+            [Buffer.from('authority'), poolState.toBuffer()],
+            program.programId
+        );
+        const vault0 = await token.getOrCreateAssociatedTokenAccount(
+            connection,
+            pool.payer,
+            mint0,
+            poolAuthority,
+            true
+        );
+        const vault1 = await token.getOrCreateAssociatedTokenAccount(
+            connection,
+            pool.payer,
+            mint1,
+            poolAuthority,
+            true
+        );
+        const createPool = (feeTo: web3.PublicKey | null) =>
+            program.methods
+                .createPool(new BN(fee), { constantProduct: {} })
+                .accountsPartial({
+                    payer: creator.publicKey,
+                    pool: poolState,
+                    mint0,
+                    mint1,
+                    vault0: vault0.address,
+                    vault1: vault1.address,
+                    feeTo,
+                    tokenProgram0: token.TOKEN_PROGRAM_ID,
+                    tokenProgram1: token.TOKEN_PROGRAM_ID,
+                })
+                .signers([creator])
+                .rpc();
+
+        // allowlist mode: only the owner lists pools
+        await program.methods.setPoolCreationRestricted(true).rpc();
+        try {
+            await createPool(null);
+            expect.fail('should reject pool creation by anyone but the owner');
+        } catch (e) {
+            expect(e).to.be.instanceOf(AnchorError);
+            expect((e as AnchorError).error.errorCode.code).to.eq(
+                'PoolCreationRestricted'
+            );
+        }
+        await program.methods.setPoolCreationRestricted(false).rpc();
+
+        const creationFee = new BN(web3.LAMPORTS_PER_SOL / 2);
+        await program.methods
+            .setPoolCreationFee(web3.PublicKey.default, creationFee)
+            .rpc();
+        try {
+            await createPool(null);
+            expect.fail('should require fee_to to charge the creation fee');
+        } catch (e) {
+            expect(e).to.be.instanceOf(AnchorError);
+            expect((e as AnchorError).error.errorCode.code).to.eq(
+                'MissingCreationFeeAccount'
+            );
+        }
+
+        const feeToBalanceBefore = await connection.getBalance(
+            feeTo.publicKey
+        );
+        await createPool(feeTo.publicKey);
+        const feeToBalanceAfter = await connection.getBalance(feeTo.publicKey);
+        expect(feeToBalanceAfter - feeToBalanceBefore).to.eq(
+            creationFee.toNumber()
+        );
+
+        const poolData = await program.account.pool.fetch(poolState);
+        expect(poolData.token0.toBase58()).to.eq(mint0.toBase58());
+
+        await program.methods
+            .setPoolCreationFee(web3.PublicKey.default, new BN(0))
+            .rpc();
+    });
+
+    it('Renounce ownership', async () => {
+        await program.methods.renounceOwner().rpc();
+
+        const config = await program.account.config.fetch(configPDA);
+        expect(config.owner.equals(web3.PublicKey.default)).to.be.true;
+        try {
+            await program.methods.setFeeTo(wallet.publicKey).rpc();
+            expect.fail('should leave no one able to administer the config');
+        } catch (e) {
+            expect(e).to.be.instanceOf(AnchorError);
+            expect((e as AnchorError).error.errorCode.code).to.eq(
+                'ConstraintHasOne'
+            );
+        }
     });
 
     async function setup_lp_provider(user: web3.PublicKey, amount: number) {